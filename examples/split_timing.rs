@@ -0,0 +1,120 @@
+//! Host model of stepping from an interrupt while the main loop polls the UART.
+//!
+//! Run with `cargo run --release --example split_timing --features critical-section`.
+//! A step "interrupt" thread issues steps at a fixed period, entering the
+//! critical section around each step like an interrupt masked by it would,
+//! while the main thread keeps reading a register from a chip that never
+//! answers. The reported jitter shows how much the UART side delays steps.
+//! On a desktop OS it is dominated by scheduler noise; port the loop to the
+//! target with a real timer and cycle counter to get meaningful numbers.
+
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use critical_section::Mutex;
+use embedded_hal::digital::{ErrorType as PinErrorType, OutputPin};
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+use tmc2209_driver::registers::REG_DRVSTATUS;
+use tmc2209_driver::{SharedSerial, StepTiming, Tmc2209FullUartDiagnosticsAndControl, TxIdle};
+
+const STEP_PERIOD: Duration = Duration::from_micros(50);
+const STEPS: u32 = 20_000;
+
+struct NullPin;
+
+impl PinErrorType for NullPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NullPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// A UART with nothing attached: writes vanish, nothing is ever received.
+struct SilentUart;
+
+impl ErrorType for SilentUart {
+    type Error = Infallible;
+}
+
+impl Write for SilentUart {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl Read for SilentUart {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Infallible> {
+        unreachable!("read_ready is always false")
+    }
+}
+
+impl ReadReady for SilentUart {
+    fn read_ready(&mut self) -> Result<bool, Infallible> {
+        Ok(false)
+    }
+}
+
+impl TxIdle for SilentUart {
+    fn tx_idle(&mut self) -> Result<bool, Infallible> {
+        Ok(true)
+    }
+}
+
+static SERIAL: Mutex<RefCell<SilentUart>> = Mutex::new(RefCell::new(SilentUart));
+static DONE: AtomicBool = AtomicBool::new(false);
+
+fn main() {
+    let driver = Tmc2209FullUartDiagnosticsAndControl::new(
+        NullPin,
+        NullPin,
+        NullPin,
+        SharedSerial::new(&SERIAL),
+        0,
+    );
+    let (mut motion, mut uart) = driver.split();
+    uart.set_read_timeout(1_000);
+
+    let stepper = thread::spawn(move || {
+        let mut timing = StepTiming::new(STEP_PERIOD.as_nanos() as u32);
+        let start = Instant::now();
+        let mut next = start;
+        for _ in 0..STEPS {
+            next += STEP_PERIOD;
+            while Instant::now() < next {}
+            critical_section::with(|_| {
+                timing.record(start.elapsed().as_nanos() as u32);
+                motion.step_pulse().unwrap();
+            });
+        }
+        DONE.store(true, Ordering::Relaxed);
+        (timing, motion.position())
+    });
+
+    let mut reads = 0u32;
+    while !DONE.load(Ordering::Relaxed) {
+        // Every read times out; that is the worst case for the step thread.
+        let _ = uart.read_register(REG_DRVSTATUS);
+        reads += 1;
+    }
+
+    let (timing, position) = stepper.join().unwrap();
+    println!("steps:            {}", timing.steps());
+    println!("position:         {position}");
+    println!("timed-out reads:  {reads}");
+    println!("max late:         {} ns", timing.max_late_ns());
+    println!("max jitter:       {} ns", timing.max_jitter_ns());
+}
@@ -0,0 +1,70 @@
+//! Host benchmark of the stepping hot path.
+//!
+//! Run with `cargo run --release --example step_rate --features bench`. The
+//! pins are no-ops, so the figures show the per-step overhead of the driver
+//! and ramp code alone, as an upper bound on the achievable step rate.
+
+use std::convert::Infallible;
+use std::hint::black_box;
+use std::time::Instant;
+
+use embedded_hal::digital::{ErrorType, OutputPin};
+use tmc2209_driver::{RampConfig, Tmc2209StandaloneLegacy, TrapezoidRamp};
+
+const STEPS: u32 = 10_000_000;
+
+/// A pin that does nothing, but not in a way the optimiser can see through.
+struct NullPin(u32);
+
+impl ErrorType for NullPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NullPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0 = self.0.wrapping_add(1);
+        black_box(&mut self.0);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0 = self.0.wrapping_add(1);
+        black_box(&mut self.0);
+        Ok(())
+    }
+}
+
+fn report(name: &str, start: Instant) {
+    let ns = start.elapsed().as_nanos() as f64 / STEPS as f64;
+    println!("{name:>24}: {ns:6.1} ns/step ({:.0} kHz)", 1e6 / ns);
+}
+
+fn main() {
+    let mut driver = Tmc2209StandaloneLegacy::new_basic(NullPin(0), NullPin(0), NullPin(0));
+
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        driver.step_pulse().unwrap();
+    }
+    report("step_pulse", start);
+
+    let ramp = RampConfig {
+        start_speed: 1_000,
+        max_speed: 100_000,
+        acceleration: 1_000_000,
+    };
+    let start = Instant::now();
+    let mut total = 0u64;
+    for delay in TrapezoidRamp::new(STEPS, &ramp) {
+        total += delay.0 as u64;
+    }
+    black_box(total);
+    report("TrapezoidRamp::next", start);
+
+    let start = Instant::now();
+    for delay in TrapezoidRamp::new(STEPS, &ramp) {
+        driver.step_pulse().unwrap();
+        black_box(delay);
+    }
+    report("ramp + step_pulse", start);
+}
@@ -0,0 +1,253 @@
+//! Async variants of the long-running Full UART routines.
+//!
+//! Homing, probing and stealthChop tuning take seconds, most of it spent
+//! waiting between steps.
+//! These variants wait on an `embedded-hal-async` delay instead, so an
+//! executor such as Embassy runs other tasks in the gaps. Register accesses
+//! stay blocking; each is only a few hundred microseconds of UART traffic, and
+//! the routine yields after every one of them while it waits for the next step.
+//!
+//! Stop a running routine with `set_cancel_check`: it then fails with
+//! [`TmcError::Cancelled`] after restoring the run current and motion state,
+//! like the blocking versions. Dropping the future instead, e.g. the losing
+//! branch of a `select`, stops stepping at once but skips that cleanup; call
+//! `disable` and re-apply the current afterwards.
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::autotune::{AutotuneConfig, AutotuneResult};
+use crate::errors::TmcError;
+use crate::homing::{HomingConfig, HomingResult, StallApproach};
+use crate::motion::MotionPhase;
+use crate::probe::{ProbeConfig, ProbeContact};
+use crate::protocol::Crc8Provider;
+use crate::ramp::{ConstantRate, StepDelayNs};
+use crate::registers::REG_IHOLD_IRUN;
+use crate::stall::StallDetector;
+use crate::state::DriverState;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Async version of [`Self::run_profile`].
+    pub async fn run_profile_async<I, D>(
+        &mut self,
+        profile: I,
+        delay: &mut D,
+    ) -> Result<(), TmcError>
+    where
+        I: IntoIterator<Item = StepDelayNs>,
+        D: DelayNs,
+    {
+        self.ensure_can_move()?;
+        let nested = self.state() == DriverState::Moving;
+        let profile = profile.into_iter();
+        if !nested {
+            let total = profile
+                .size_hint()
+                .1
+                .map(|n| n.min(u32::MAX as usize) as u32);
+            self.progress_mut().begin(MotionPhase::Move, total);
+        }
+        self.set_moving(true);
+        self.settle_direction_async(delay).await;
+        let mut result = Ok(());
+        for StepDelayNs(ns) in profile {
+            result = self.timed_step_async(ns, delay).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        if !nested {
+            self.set_moving(false);
+        }
+        result
+    }
+
+    /// Async version of [`Self::home`].
+    pub async fn home_async<D: DelayNs>(
+        &mut self,
+        config: &HomingConfig,
+        delay: &mut D,
+    ) -> Result<HomingResult, TmcError> {
+        self.ensure_can_move()?;
+        let saved_current = self.reduce_current(config.current_percent)?;
+        self.set_moving(true);
+        let result = self.home_inner_async(config, delay).await;
+        let restored = self.restore_current(saved_current);
+        self.set_moving(false);
+        let result = result?;
+        restored?;
+        Ok(result)
+    }
+
+    /// Async version of [`Self::probe_towards`].
+    pub async fn probe_towards_async<D: DelayNs>(
+        &mut self,
+        target: i32,
+        config: &ProbeConfig,
+        delay: &mut D,
+    ) -> Result<Option<ProbeContact>, TmcError> {
+        let steps = self.steps_to(target)?;
+        if steps == 0 {
+            return Ok(None);
+        }
+
+        self.ensure_can_move()?;
+        let saved_current = self.reduce_current(config.current_percent)?;
+        self.set_moving(true);
+        let result = self.probe_inner_async(steps, config, delay).await;
+        let restored = self.restore_current(saved_current);
+        self.set_moving(false);
+        let result = result?;
+        restored?;
+        Ok(result)
+    }
+
+    /// Async version of [`Self::autotune_stealthchop`].
+    pub async fn autotune_stealthchop_async<D: DelayNs>(
+        &mut self,
+        config: &AutotuneConfig,
+        delay: &mut D,
+    ) -> Result<AutotuneResult, TmcError> {
+        self.ensure_can_move()?;
+        let saved_current = self.begin_autotune()?;
+        self.set_moving(true);
+        let result = self.autotune_inner_async(config, delay).await;
+        let restored = self.write_register(REG_IHOLD_IRUN, saved_current);
+        self.set_moving(false);
+        let result = result?;
+        restored?;
+        Ok(result)
+    }
+
+    async fn autotune_inner_async<D: DelayNs>(
+        &mut self,
+        config: &AutotuneConfig,
+        delay: &mut D,
+    ) -> Result<AutotuneResult, TmcError> {
+        self.check_cancel()?;
+        delay.delay_ms(config.standstill_ms).await;
+        let profile = self.begin_autotune_move(config)?;
+        self.run_profile_async(profile, delay).await?;
+        self.autotune_result()
+    }
+
+    async fn home_inner_async<D: DelayNs>(
+        &mut self,
+        config: &HomingConfig,
+        delay: &mut D,
+    ) -> Result<HomingResult, TmcError> {
+        let detector = self.begin_homing(config)?;
+
+        self.begin_homing_phase(MotionPhase::HomingFast, config.direction, None)?;
+        let fast_sg = self
+            .step_until_stall_async(
+                config.fast_speed,
+                u32::MAX,
+                config.timeout,
+                &detector,
+                delay,
+            )
+            .await?
+            .ok_or(TmcError::MotionTimeout)?;
+
+        self.begin_homing_phase(
+            MotionPhase::HomingBackoff,
+            !config.direction,
+            Some(config.backoff_steps),
+        )?;
+        self.run_profile_async(
+            ConstantRate::new(config.backoff_steps, config.slow_speed),
+            delay,
+        )
+        .await?;
+
+        self.begin_homing_phase(MotionPhase::HomingSlow, config.direction, None)?;
+        let slow_sg = self
+            .step_until_stall_async(
+                config.slow_speed,
+                u32::MAX,
+                config.timeout,
+                &detector,
+                delay,
+            )
+            .await?
+            .ok_or(TmcError::MotionTimeout)?;
+
+        Ok(self.finish_homing(fast_sg, slow_sg))
+    }
+
+    async fn probe_inner_async<D: DelayNs>(
+        &mut self,
+        steps: i32,
+        config: &ProbeConfig,
+        delay: &mut D,
+    ) -> Result<Option<ProbeContact>, TmcError> {
+        let detector = self.begin_probe(steps, config)?;
+        let hit = self
+            .step_until_stall_async(
+                config.speed,
+                steps.unsigned_abs(),
+                config.timeout,
+                &detector,
+                delay,
+            )
+            .await?;
+        Ok(self.probe_contact(hit))
+    }
+
+    /// Async version of `step_until_stall`.
+    async fn step_until_stall_async<D: DelayNs>(
+        &mut self,
+        speed: u32,
+        max_steps: u32,
+        timeout_ms: u32,
+        detector: &StallDetector,
+        delay: &mut D,
+    ) -> Result<Option<u16>, TmcError> {
+        let mut approach = StallApproach::new(speed, timeout_ms);
+        self.settle_direction_async(delay).await;
+        for _ in 0..max_steps {
+            self.timed_step_async(approach.period_ns(), delay).await?;
+            if let Some(sg) = self.after_approach_step(&mut approach, detector)? {
+                return Ok(Some(sg));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Async version of `timed_step`.
+    async fn timed_step_async<D: DelayNs>(
+        &mut self,
+        period_ns: u32,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        self.check_cancel()?;
+        let high_ns = self.step_high_ns();
+        self.begin_step()?;
+        if high_ns > 0 {
+            delay.delay_ns(high_ns).await;
+        }
+        self.end_step()?;
+        self.progress_mut().step();
+        delay.delay_ns(self.step_gap_ns(period_ns, high_ns)).await;
+        Ok(())
+    }
+
+    async fn settle_direction_async<D: DelayNs>(&mut self, delay: &mut D) {
+        let wait_ns = self.take_dir_settle_ns();
+        if wait_ns > 0 {
+            delay.delay_ns(wait_ns).await;
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Sanity checks on the live configuration.
+//!
+//! Some register combinations are accepted by the chip but leave the motor
+//! dead, overheat the driver or silently disable a feature. `audit_config`
+//! reports them as [`ConfigWarning`]s so they can be caught during bring-up.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::current::scale_to_ma;
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::state::DriverState;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Highest continuous RMS motor current of the TMC2209, in mA.
+pub const TMC2209_MAX_RMS_MA: u32 = 2_000;
+
+/// Number of distinct warnings [`ConfigAudit`] can hold.
+const AUDIT_CAPACITY: usize = 4;
+
+/// A dangerous or nonsensical configuration found by `audit_config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// CHOPCONF.toff is 0, which switches the outputs off, while the driver is
+    /// enabled or rotating.
+    OutputsOff,
+    /// IRUN, CHOPCONF.vsense and the sense resistor give more RMS current than
+    /// the chip is rated for.
+    ExcessiveCurrent {
+        /// Resulting run current, in mA RMS
+        rms_ma: u32,
+    },
+    /// TCOOLTHRS is not above TPWMTHRS, so CoolStep and StallGuard4 (which
+    /// need stealthChop) are only enabled at speeds where the chip runs
+    /// spreadCycle. They run while TCOOLTHRS ≥ TSTEP > TPWMTHRS.
+    CoolStepBelowStealthChop,
+    /// GCONF.i_scale_analog is set while currents are set over UART, so the
+    /// actual current also scales with the VREF pin voltage.
+    AnalogScalingWithUartCurrent,
+}
+
+/// Warnings found by `audit_config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConfigAudit {
+    warnings: [Option<ConfigWarning>; AUDIT_CAPACITY],
+}
+
+impl ConfigAudit {
+    /// `true` if nothing suspicious was found.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.iter().all(Option::is_none)
+    }
+
+    /// Number of warnings.
+    pub fn len(&self) -> usize {
+        self.warnings.iter().flatten().count()
+    }
+
+    /// `true` if there are no warnings, same as [`Self::is_clean`].
+    pub fn is_empty(&self) -> bool {
+        self.is_clean()
+    }
+
+    /// The warnings found.
+    pub fn iter(&self) -> impl Iterator<Item = ConfigWarning> + '_ {
+        self.warnings.iter().flatten().copied()
+    }
+
+    fn push(&mut self, warning: ConfigWarning) {
+        if let Some(slot) = self.warnings.iter_mut().find(|w| w.is_none()) {
+            *slot = Some(warning);
+        }
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Check the live configuration for dangerous or nonsensical combinations.
+    ///
+    /// Reads GCONF and CHOPCONF and uses the shadow copies of the write-only
+    /// registers; settings the driver has not written are not checked. The
+    /// current check uses the sense resistor set with `set_sense_resistor`.
+    pub fn audit_config(&mut self) -> Result<ConfigAudit, TmcError> {
+        let gconf = self.read_register_blocking(REG_GCONF)?;
+        let chopconf = self.read_register_blocking(REG_CHOPCONF)?;
+        let ihold_irun = self.shadow_register(REG_IHOLD_IRUN);
+        let mut audit = ConfigAudit::default();
+
+        let active = matches!(self.state(), DriverState::Enabled | DriverState::Moving)
+            || self.vactual() != 0;
+        if chopconf & 0x0F == 0 && active {
+            audit.push(ConfigWarning::OutputsOff);
+        }
+
+        if let Some(raw) = ihold_irun {
+            let irun = ((raw >> 8) & 0x1F) as u8;
+            let vsense = chopconf & CHOPCONF_VSENSE != 0;
+            let rms_ma = scale_to_ma(irun, self.current_settings().rsense_mohm(), vsense);
+            if rms_ma > TMC2209_MAX_RMS_MA {
+                audit.push(ConfigWarning::ExcessiveCurrent { rms_ma });
+            }
+        }
+
+        let tcoolthrs = self.shadow_register(REG_TCOOLTHRS).unwrap_or(0);
+        let tpwmthrs = self.shadow_register(REG_TPWMTHRS).unwrap_or(0);
+        if tcoolthrs != 0 && tpwmthrs != 0 && tcoolthrs <= tpwmthrs {
+            audit.push(ConfigWarning::CoolStepBelowStealthChop);
+        }
+
+        if gconf & GCONF_I_SCALE_ANALOG != 0 && ihold_irun.is_some() {
+            audit.push(ConfigWarning::AnalogScalingWithUartCurrent);
+        }
+        Ok(audit)
+    }
+}
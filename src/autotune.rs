@@ -0,0 +1,148 @@
+//! stealthChop automatic tuning.
+//!
+//! With PWMCONF.pwm_autoscale and pwm_autograd set, the chip regulates the
+//! stealthChop amplitude on its own, but first has to learn two parameters:
+//! PWM_OFS_AUTO at standstill with the run current flowing (AT#1 in the
+//! datasheet) and PWM_GRAD_AUTO while moving at medium speed in stealthChop
+//! (AT#2). `autotune_stealthchop` runs both phases and reads back what the
+//! chip learnt. The learnt values are lost on power-down, so tune after every
+//! power-up, e.g. right before homing.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::fields::{PwmAuto, PwmScale};
+use crate::motion::MotionPhase;
+use crate::protocol::Crc8Provider;
+use crate::ramp::ConstantRate;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Parameters of `autotune_stealthchop`.
+///
+/// The defaults suit a 1.8° motor at 16 microsteps: 60 RPM for 400 full steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutotuneConfig {
+    /// Standstill time at run current for AT#1, in milliseconds; the datasheet
+    /// asks for at least 130 ms
+    pub standstill_ms: u32,
+    /// Speed of the AT#2 move, in steps/s; must stay below the TPWMTHRS
+    /// velocity so the move runs in stealthChop
+    pub speed: u32,
+    /// Length of the AT#2 move, in steps; a few hundred full steps
+    pub steps: u32,
+    /// Direction of the AT#2 move. `true` => clockwise
+    pub direction: bool,
+}
+
+impl Default for AutotuneConfig {
+    fn default() -> Self {
+        AutotuneConfig {
+            standstill_ms: 200,
+            speed: 3_200,
+            steps: 6_400,
+            direction: true,
+        }
+    }
+}
+
+/// stealthChop parameters after a tuning run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutotuneResult {
+    /// Learnt PWM_OFS_AUTO and PWM_GRAD_AUTO
+    pub pwm_auto: PwmAuto,
+    /// PWM_SCALE at the end of the move; a PWM_SCALE_AUTO near 0 means the
+    /// regulation has settled
+    pub pwm_scale: PwmScale,
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Run the stealthChop automatic tuning: switch on pwm_autoscale and
+    /// pwm_autograd, hold the motor at run current for
+    /// `config.standstill_ms` (AT#1), then move it `config.steps` steps
+    /// (AT#2).
+    ///
+    /// The outputs must be enabled and the run current set with `set_current`
+    /// or `set_current_ma`. IHOLD is raised to IRUN during the standstill and
+    /// restored afterwards, also if tuning fails. Fails with
+    /// [`TmcError::InvalidArgument`] before anything is written if no run
+    /// current is known or GCONF.en_spreadcycle rules out stealthChop.
+    pub fn autotune_stealthchop<D: DelayNs>(
+        &mut self,
+        config: &AutotuneConfig,
+        delay: &mut D,
+    ) -> Result<AutotuneResult, TmcError> {
+        self.ensure_can_move()?;
+        let saved_current = self.begin_autotune()?;
+        self.set_moving(true);
+        let result = self.autotune_inner(config, delay);
+        let restored = self.write_register(REG_IHOLD_IRUN, saved_current);
+        self.set_moving(false);
+        let result = result?;
+        restored?;
+        Ok(result)
+    }
+
+    fn autotune_inner<D: DelayNs>(
+        &mut self,
+        config: &AutotuneConfig,
+        delay: &mut D,
+    ) -> Result<AutotuneResult, TmcError> {
+        self.check_cancel()?;
+        delay.delay_ms(config.standstill_ms);
+        let profile = self.begin_autotune_move(config)?;
+        self.run_profile(profile, delay)?;
+        self.autotune_result()
+    }
+
+    /// Check that tuning can run, enable it and raise IHOLD to IRUN for AT#1.
+    /// Returns the IHOLD_IRUN value to restore.
+    pub(crate) fn begin_autotune(&mut self) -> Result<u32, TmcError> {
+        let ihold_irun = self
+            .shadow_register(REG_IHOLD_IRUN)
+            .ok_or(TmcError::InvalidArgument)?;
+        if self.read_register_blocking(REG_GCONF)? & GCONF_EN_SPREADCYCLE != 0 {
+            return Err(TmcError::InvalidArgument);
+        }
+        let pwmconf = self.read_register_blocking(REG_PWMCONF)?;
+        let tuned = pwmconf | PWMCONF_PWM_AUTOSCALE | PWMCONF_PWM_AUTOGRAD;
+        if tuned != pwmconf {
+            self.write_register(REG_PWMCONF, tuned)?;
+        }
+        let irun = (ihold_irun >> 8) & 0x1F;
+        self.write_register(REG_IHOLD_IRUN, (ihold_irun & !0x1F) | irun)?;
+        Ok(ihold_irun)
+    }
+
+    /// Start the AT#2 phase and return the profile of its move.
+    pub(crate) fn begin_autotune_move(
+        &mut self,
+        config: &AutotuneConfig,
+    ) -> Result<ConstantRate, TmcError> {
+        self.check_cancel()?;
+        self.set_direction(config.direction)?;
+        self.progress_mut()
+            .begin(MotionPhase::Autotune, Some(config.steps));
+        Ok(ConstantRate::new(config.steps, config.speed))
+    }
+
+    /// Read back the tuned stealthChop parameters.
+    pub(crate) fn autotune_result(&mut self) -> Result<AutotuneResult, TmcError> {
+        let pwm_scale = self.read_pwm_scale()?;
+        let pwm_auto = self.read_pwm_auto()?;
+        Ok(AutotuneResult {
+            pwm_auto,
+            pwm_scale,
+        })
+    }
+}
@@ -0,0 +1,112 @@
+//! Step timing for hardware pulse generators.
+//!
+//! Above a few tens of kHz, toggling STEP from software (or even from one
+//! interrupt per step) eats the CPU. A timer fed by DMA, or an RP2040 PIO state
+//! machine, can play back a precomputed list of intervals instead.
+//! [`StepBatch`] turns any step-interval iterator, such as a
+//! [`crate::TrapezoidRamp`] or a planner segment's ramp, into buffers of
+//! timer ticks, so the crate keeps doing the planning and the hardware only
+//! plays the pulses. The driver counts the steps once the hardware reports
+//! them done, see `begin_batch`.
+//!
+//! Tick conversion carries the rounding error from one step to the next, so a
+//! long batch does not drift from the ramp however coarse the timer.
+
+use crate::ramp::StepDelayNs;
+
+/// Converts step intervals into timer ticks, one buffer at a time.
+///
+/// Each buffer entry stands for one step. Fill a buffer, hand it to the DMA
+/// channel or PIO FIFO, and refill the other half while it plays.
+#[derive(Debug, Clone)]
+pub struct StepBatch<R> {
+    ramp: R,
+    timer_hz: u32,
+    /// Nanoseconds × `timer_hz` not yet converted to ticks
+    remainder: u64,
+    /// Compare value of the next step
+    compare: u32,
+    counter_mask: u32,
+    emitted: u32,
+}
+
+impl<R: Iterator<Item = StepDelayNs>> StepBatch<R> {
+    /// Batches for `ramp` on a timer counting at `timer_hz`, with a 32-bit
+    /// counter and the first compare value at 0.
+    pub fn new(ramp: R, timer_hz: u32) -> Self {
+        StepBatch {
+            ramp,
+            timer_hz,
+            remainder: 0,
+            compare: 0,
+            counter_mask: u32::MAX,
+            emitted: 0,
+        }
+    }
+
+    /// Use a counter `bits` wide (e.g. 16 for most STM32 timers), with the
+    /// first step at compare value `start`. Intervals are clamped to the
+    /// counter range, so choose `timer_hz` such that the slowest step fits.
+    pub fn with_counter(mut self, bits: u32, start: u32) -> Self {
+        self.counter_mask = match bits {
+            0 => 0,
+            1..=31 => (1 << bits) - 1,
+            _ => u32::MAX,
+        };
+        self.compare = start & self.counter_mask;
+        self
+    }
+
+    /// Fill `buf` with the number of timer ticks to wait after each step
+    /// before the next one, e.g. for auto-reload values or PIO delay loops.
+    /// Returns the entries written; fewer than `buf.len()` once the ramp ends.
+    pub fn fill_intervals(&mut self, buf: &mut [u32]) -> usize {
+        let mut written = 0;
+        for slot in buf.iter_mut() {
+            let Some(ticks) = self.next_ticks() else {
+                break;
+            };
+            *slot = ticks;
+            written += 1;
+        }
+        self.emitted = self.emitted.wrapping_add(written as u32);
+        written
+    }
+
+    /// Fill `buf` with the counter value at which each step fires, wrapping
+    /// with the counter, for output-compare toggling from DMA. Returns the
+    /// entries written; fewer than `buf.len()` once the ramp ends.
+    pub fn fill_compare_values(&mut self, buf: &mut [u32]) -> usize {
+        let mut written = 0;
+        for slot in buf.iter_mut() {
+            let Some(ticks) = self.next_ticks() else {
+                break;
+            };
+            *slot = self.compare;
+            self.compare = self.compare.wrapping_add(ticks) & self.counter_mask;
+            written += 1;
+        }
+        self.emitted = self.emitted.wrapping_add(written as u32);
+        written
+    }
+
+    /// Entries (steps) handed out so far.
+    pub fn emitted(&self) -> u32 {
+        self.emitted
+    }
+
+    /// `true` once the ramp has no steps left.
+    pub fn is_done(&self) -> bool {
+        self.ramp.size_hint().1 == Some(0)
+    }
+
+    /// Next interval in ticks, at least 1 and at most the counter range.
+    fn next_ticks(&mut self) -> Option<u32> {
+        const NS_PER_S: u64 = 1_000_000_000;
+        let StepDelayNs(ns) = self.ramp.next()?;
+        let total = ns as u64 * self.timer_hz as u64 + self.remainder;
+        self.remainder = total % NS_PER_S;
+        let ticks = (total / NS_PER_S).min(self.counter_mask as u64) as u32;
+        Some(ticks.max(1))
+    }
+}
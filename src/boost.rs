@@ -0,0 +1,184 @@
+//! Automatic run current boost on an imminent stall.
+//!
+//! A load spike (a sticky spot on a rail, a cut through a knot) can stall a
+//! motor that runs at a current chosen for the average load. [`StallBoost`]
+//! watches SG_RESULT and, when it keeps falling towards zero, raises IRUN for a
+//! bounded time to push through, then restores the configured current. The
+//! boost is time-limited and followed by a cooldown, so a real jam cannot keep
+//! the motor at the higher current.
+
+use crate::erased::ErasedTmc2209;
+use crate::errors::TmcError;
+use crate::registers::*;
+
+/// Tuning for [`StallBoost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StallBoostConfig {
+    /// SG_RESULT below which a falling reading counts towards a boost
+    pub sg_threshold: u16,
+    /// Consecutive falling readings below `sg_threshold` that start a boost
+    pub falling_samples: u8,
+    /// IRUN increase while boosting, in current scale steps; capped at 31 and
+    /// at the driver's derating and sense resistor limits
+    pub irun_boost: u8,
+    /// Length of a boost, in milliseconds
+    pub duration_ms: u32,
+    /// Time after a boost before another one may start, in milliseconds
+    pub cooldown_ms: u32,
+}
+
+impl Default for StallBoostConfig {
+    fn default() -> Self {
+        StallBoostConfig {
+            sg_threshold: 100,
+            falling_samples: 3,
+            irun_boost: 6,
+            duration_ms: 500,
+            cooldown_ms: 2_000,
+        }
+    }
+}
+
+/// Start or end of a boost, returned by [`StallBoost::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoostEvent {
+    /// IRUN was raised.
+    Started {
+        /// SG_RESULT that triggered the boost
+        sg_result: u16,
+        /// IRUN applied while boosting
+        irun: u8,
+    },
+    /// The configured current was restored.
+    Ended {
+        /// SG_RESULT at the end of the boost
+        sg_result: u16,
+    },
+}
+
+/// Temporary IRUN increase driven by a falling SG_RESULT.
+///
+/// The boost starts from the driver's shadow copy of IHOLD_IRUN and writes it
+/// back when it ends, so change currents only while [`Self::is_boosting`] is
+/// `false`, and do not combine it with a [`crate::ThermalManager`] on the same
+/// axis. The boosted IRUN stays within the derating curve and sense resistor
+/// rating set on the driver (see `set_current_ma`). Without a shadowed
+/// IHOLD_IRUN, or with no headroom under those limits, no boost is applied.
+pub struct StallBoost {
+    config: StallBoostConfig,
+    last_sg: Option<u16>,
+    falling: u8,
+    boost_since_ms: Option<u32>,
+    last_end_ms: Option<u32>,
+    base_current: Option<u32>,
+    boosts: u32,
+}
+
+impl StallBoost {
+    /// Create a policy that is not boosting.
+    pub fn new(config: StallBoostConfig) -> Self {
+        StallBoost {
+            config,
+            last_sg: None,
+            falling: 0,
+            boost_since_ms: None,
+            last_end_ms: None,
+            base_current: None,
+            boosts: 0,
+        }
+    }
+
+    /// Read SG_RESULT and start or end a boost.
+    ///
+    /// Call regularly while the motor moves, with a millisecond timestamp
+    /// from the application's clock (wrapping is fine). SG_RESULT is only
+    /// meaningful above the StallGuard velocity threshold, so do not call it
+    /// at standstill.
+    pub fn update<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+        now_ms: u32,
+    ) -> Result<Option<BoostEvent>, TmcError> {
+        let sg_result = (driver.read_register(REG_SG_RESULT)? & 0x3FF) as u16;
+        let falling = self.last_sg.is_some_and(|last| sg_result < last);
+        self.last_sg = Some(sg_result);
+
+        if let Some(since) = self.boost_since_ms {
+            if now_ms.wrapping_sub(since) < self.config.duration_ms {
+                return Ok(None);
+            }
+            return self.end(driver, sg_result, now_ms).map(Some);
+        }
+
+        if falling && sg_result < self.config.sg_threshold {
+            self.falling = self.falling.saturating_add(1);
+        } else {
+            self.falling = 0;
+        }
+        let cooled = self
+            .last_end_ms
+            .is_none_or(|end| now_ms.wrapping_sub(end) >= self.config.cooldown_ms);
+        if self.falling < self.config.falling_samples.max(1) || !cooled {
+            return Ok(None);
+        }
+        let Some(base) = driver.shadow_register(REG_IHOLD_IRUN) else {
+            return Ok(None);
+        };
+        let base_irun = (base >> 8) & 0x1F;
+        let limit = driver.irun_limit()?.map_or(0x1F, |irun| irun.get() as u32);
+        let irun = (base_irun + self.config.irun_boost as u32).min(limit);
+        if irun <= base_irun {
+            return Ok(None);
+        }
+        driver.write_register(REG_IHOLD_IRUN, (base & !(0x1F << 8)) | (irun << 8))?;
+        self.base_current = Some(base);
+        self.boost_since_ms = Some(now_ms);
+        self.falling = 0;
+        self.boosts = self.boosts.wrapping_add(1);
+        Ok(Some(BoostEvent::Started {
+            sg_result,
+            irun: irun as u8,
+        }))
+    }
+
+    /// End a boost in progress now, e.g. when the move ends. Returns `true`
+    /// if one was ended.
+    pub fn cancel<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+        now_ms: u32,
+    ) -> Result<bool, TmcError> {
+        if self.boost_since_ms.is_none() {
+            return Ok(false);
+        }
+        let sg_result = self.last_sg.unwrap_or(0);
+        self.end(driver, sg_result, now_ms)?;
+        Ok(true)
+    }
+
+    /// `true` while IRUN is raised.
+    pub fn is_boosting(&self) -> bool {
+        self.boost_since_ms.is_some()
+    }
+
+    /// Boosts started since creation.
+    pub fn boosts(&self) -> u32 {
+        self.boosts
+    }
+
+    fn end<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+        sg_result: u16,
+        now_ms: u32,
+    ) -> Result<BoostEvent, TmcError> {
+        if let Some(base) = self.base_current {
+            driver.write_register(REG_IHOLD_IRUN, base)?;
+        }
+        self.base_current = None;
+        self.boost_since_ms = None;
+        self.last_end_ms = Some(now_ms);
+        self.falling = 0;
+        Ok(BoostEvent::Ended { sg_result })
+    }
+}
@@ -0,0 +1,442 @@
+//! Several TMC2209 sharing one UART.
+//!
+//! Up to four chips can sit on the same single-wire UART, each with its own
+//! node address set by MS1/MS2. [`Tmc2209Bus`] finds the nodes that answer,
+//! keeps a configuration per node and applies it, and notices when one goes
+//! away, so a single unplugged driver does not stall the whole bus. With the
+//! EN pins handed over, it also switches all nodes on and off together.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::config::MotorConfig;
+use crate::errors::TmcError;
+use crate::events::{EventQueue, TmcEvent};
+use crate::history::DEFAULT_HISTORY_LEN;
+use crate::pins::NoPin;
+use crate::protocol::{Crc8Provider, SoftwareCrc8};
+use crate::registers::*;
+use crate::shadow::ShadowRegisters;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Number of node addresses on one bus.
+pub const BUS_ADDRESSES: usize = 4;
+
+/// Consecutive timeouts after which a node is considered lost.
+const LOST_AFTER_TIMEOUTS: u8 = 3;
+
+/// Capacity of the bus event queue.
+const BUS_EVENT_QUEUE_LEN: usize = 4;
+
+/// Pause between the register and pin phases of [`Tmc2209Bus::enable_all`]
+/// and [`Tmc2209Bus::disable_all`], in µs.
+pub const ENABLE_SETTLE_US: u32 = 100;
+
+/// TOFF written by [`Tmc2209Bus::enable_all`] to a node whose TOFF has never
+/// been seen non-zero (the power-up default).
+const DEFAULT_TOFF: u32 = 3;
+
+/// Register-only driver handed out by [`Tmc2209Bus`].
+///
+/// It has no pins, so only UART operations are meaningful. The shadow copies
+/// of write-only registers are kept per address and follow the node it is
+/// talking to; other driver state is shared by all addresses of the bus.
+pub type BusDriver<SERIAL, CRC = SoftwareCrc8> =
+    Tmc2209FullUartDiagnosticsAndControl<NoPin, NoPin, NoPin, SERIAL, DEFAULT_HISTORY_LEN, CRC>;
+
+/// Outcome of an operation applied to every node of a bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BusResults {
+    results: [Option<Result<(), TmcError>>; BUS_ADDRESSES],
+}
+
+impl BusResults {
+    /// Result for `addr`, or `None` if the operation was not run there.
+    pub fn get(&self, addr: u8) -> Option<Result<(), TmcError>> {
+        self.results.get(addr as usize).copied().flatten()
+    }
+
+    /// `true` if the operation succeeded on every node it was run on.
+    pub fn all_ok(&self) -> bool {
+        self.results.iter().flatten().all(Result::is_ok)
+    }
+
+    /// Addresses where the operation failed, with the error.
+    pub fn failures(&self) -> impl Iterator<Item = (u8, TmcError)> + '_ {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(addr, r)| match r {
+                Some(Err(e)) => Some((addr as u8, *e)),
+                _ => None,
+            })
+    }
+
+    fn set(&mut self, addr: u8, result: Result<(), TmcError>) {
+        self.results[addr as usize] = Some(result);
+    }
+}
+
+/// Manager for up to four TMC2209 on one UART.
+///
+/// `EN` is the type of the nodes' EN pins, if handed over with
+/// [`Self::with_enable_pins`].
+pub struct Tmc2209Bus<SERIAL, CRC = SoftwareCrc8, EN = NoPin>
+where
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+    EN: OutputPin,
+{
+    link: BusDriver<SERIAL, CRC>,
+    present: u8,
+    quarantined: u8,
+    timeouts: [u8; BUS_ADDRESSES],
+    events: EventQueue<BUS_EVENT_QUEUE_LEN>,
+    configs: [Option<MotorConfig>; BUS_ADDRESSES],
+    en_pins: [Option<EN>; BUS_ADDRESSES],
+    /// Last non-zero TOFF seen by `disable_all`, 0 if none
+    saved_toff: [u8; BUS_ADDRESSES],
+    /// Shadow copies of the nodes `link` is not addressing; the slot of
+    /// `selected` is unused while its copies are in `link`
+    shadows: [ShadowRegisters; BUS_ADDRESSES],
+    /// Address `link` is talking to
+    selected: u8,
+}
+
+impl<SERIAL> Tmc2209Bus<SERIAL>
+where
+    SERIAL: Write + Read + ReadReady,
+{
+    /// Manage the chips on `serial`. Call [`Self::discover`] next.
+    pub fn new(serial: SERIAL) -> Self {
+        Self::new_with_crc(serial, SoftwareCrc8)
+    }
+}
+
+impl<SERIAL, CRC> Tmc2209Bus<SERIAL, CRC>
+where
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Manage the chips on `serial`, using `crc` for checksums.
+    pub fn new_with_crc(serial: SERIAL, crc: CRC) -> Self {
+        Tmc2209Bus {
+            link: Tmc2209FullUartDiagnosticsAndControl::new_with_crc(
+                NoPin, NoPin, NoPin, serial, 0, crc,
+            ),
+            present: 0,
+            quarantined: 0,
+            timeouts: [0; BUS_ADDRESSES],
+            events: EventQueue::new(),
+            configs: [None; BUS_ADDRESSES],
+            en_pins: [None, None, None, None],
+            saved_toff: [0; BUS_ADDRESSES],
+            shadows: Default::default(),
+            selected: 0,
+        }
+    }
+
+    /// Hand over the EN pins of the nodes, by address, for
+    /// [`Self::enable_all`] and [`Self::disable_all`]. Nodes whose EN is
+    /// wired elsewhere get `None`.
+    pub fn with_enable_pins<E: OutputPin>(
+        self,
+        pins: [Option<E>; BUS_ADDRESSES],
+    ) -> Tmc2209Bus<SERIAL, CRC, E> {
+        Tmc2209Bus {
+            link: self.link,
+            present: self.present,
+            quarantined: self.quarantined,
+            timeouts: self.timeouts,
+            events: self.events,
+            configs: self.configs,
+            en_pins: pins,
+            saved_toff: self.saved_toff,
+            shadows: self.shadows,
+            selected: self.selected,
+        }
+    }
+}
+
+impl<SERIAL, CRC, EN> Tmc2209Bus<SERIAL, CRC, EN>
+where
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+    EN: OutputPin,
+{
+    /// Probe every address and remember which ones answer. Returns the
+    /// number of chips found. Clears any quarantine.
+    pub fn discover(&mut self) -> Result<usize, TmcError> {
+        self.present = 0;
+        self.quarantined = 0;
+        self.timeouts = [0; BUS_ADDRESSES];
+        for addr in 0..BUS_ADDRESSES as u8 {
+            self.select(addr);
+            match self.link.read_register(REG_IOIN) {
+                Ok(_) => self.present |= 1 << addr,
+                Err(e) if e.is_transient() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(self.present.count_ones() as usize)
+    }
+
+    /// Addresses found by the last [`Self::discover`] and not quarantined.
+    pub fn addresses(&self) -> impl Iterator<Item = u8> {
+        let present = self.present & !self.quarantined;
+        (0..BUS_ADDRESSES as u8).filter(move |addr| present & (1 << addr) != 0)
+    }
+
+    /// `true` if `addr` answered during discovery and has not been lost since.
+    pub fn is_present(&self, addr: u8) -> bool {
+        (addr as usize) < BUS_ADDRESSES && (self.present & !self.quarantined) & (1 << addr) != 0
+    }
+
+    /// `true` if `addr` stopped answering and is skipped until the next
+    /// [`Self::discover`].
+    pub fn is_quarantined(&self, addr: u8) -> bool {
+        (addr as usize) < BUS_ADDRESSES && self.quarantined & (1 << addr) != 0
+    }
+
+    /// Read GSTAT from every node to check it is still there.
+    ///
+    /// A node that times out on this many consecutive operations (polls or
+    /// [`Self::for_each_driver`] calls) is quarantined and reported as
+    /// [`TmcEvent::DriverLost`]; quarantined nodes are not polled again, so they
+    /// cannot hold up the bus with timeouts.
+    pub fn poll(&mut self) -> BusResults {
+        self.for_each_driver(|_, drv| drv.read_register(REG_GSTAT).map(|_| ()))
+    }
+
+    /// Drain bus events such as [`TmcEvent::DriverLost`].
+    pub fn poll_events(&mut self) -> impl Iterator<Item = TmcEvent> + '_ {
+        core::iter::from_fn(move || self.events.pop())
+    }
+
+    /// Point the link at `addr`, swapping in that node's shadow copies.
+    fn select(&mut self, addr: u8) {
+        if addr != self.selected {
+            self.link
+                .swap_shadow(&mut self.shadows[self.selected as usize]);
+            self.link.swap_shadow(&mut self.shadows[addr as usize]);
+            self.selected = addr;
+        }
+        self.link.set_slave_address(addr);
+    }
+
+    /// Count consecutive timeouts of `addr` and quarantine it when lost.
+    fn track(&mut self, addr: u8, result: &Result<(), TmcError>) {
+        let timeouts = &mut self.timeouts[addr as usize];
+        match result {
+            Err(TmcError::Timeout { .. }) => *timeouts = timeouts.saturating_add(1),
+            _ => *timeouts = 0,
+        }
+        if *timeouts >= LOST_AFTER_TIMEOUTS {
+            self.quarantined |= 1 << addr;
+            self.events.push(TmcEvent::DriverLost(addr));
+        }
+    }
+
+    /// Driver for the node at `addr`, whether or not it was discovered.
+    pub fn driver(&mut self, addr: u8) -> Result<&mut BusDriver<SERIAL, CRC>, TmcError> {
+        if addr as usize >= BUS_ADDRESSES {
+            return Err(TmcError::InvalidArgument);
+        }
+        self.select(addr);
+        Ok(&mut self.link)
+    }
+
+    /// Run `f` for every discovered node, continuing past failures.
+    pub fn for_each_driver<F>(&mut self, mut f: F) -> BusResults
+    where
+        F: FnMut(u8, &mut BusDriver<SERIAL, CRC>) -> Result<(), TmcError>,
+    {
+        let mut results = BusResults::default();
+        for addr in 0..BUS_ADDRESSES as u8 {
+            if self.is_present(addr) {
+                self.select(addr);
+                let result = f(addr, &mut self.link);
+                self.track(addr, &result);
+                results.set(addr, result);
+            }
+        }
+        results
+    }
+
+    /// Set the same run/hold current on every discovered node.
+    pub fn apply_to_all(&mut self, config: &MotorConfig) -> BusResults {
+        self.for_each_driver(|_, drv| apply_config(drv, config))
+    }
+
+    /// Remember `config` for the node at `addr`, e.g. a different current for
+    /// the extruder than for the Z axis. Applied by [`Self::apply`] and
+    /// [`Self::apply_configs`].
+    pub fn set_config(&mut self, addr: u8, config: MotorConfig) -> Result<(), TmcError> {
+        let slot = self
+            .configs
+            .get_mut(addr as usize)
+            .ok_or(TmcError::InvalidArgument)?;
+        *slot = Some(config);
+        Ok(())
+    }
+
+    /// Configuration stored for `addr`, if any.
+    pub fn config_for(&self, addr: u8) -> Option<&MotorConfig> {
+        self.configs.get(addr as usize)?.as_ref()
+    }
+
+    /// Write the stored configuration of `addr` to that node.
+    ///
+    /// Returns [`TmcError::InvalidArgument`] if none is stored and
+    /// [`TmcError::Unsupported`] if the node is absent or quarantined.
+    pub fn apply(&mut self, addr: u8) -> Result<(), TmcError> {
+        let config = *self.config_for(addr).ok_or(TmcError::InvalidArgument)?;
+        if !self.is_present(addr) {
+            return Err(TmcError::Unsupported);
+        }
+        self.select(addr);
+        let result = apply_config(&mut self.link, &config);
+        self.track(addr, &result);
+        result
+    }
+
+    /// Write the stored configuration to every discovered node that has one.
+    pub fn apply_configs(&mut self) -> BusResults {
+        let configs = self.configs;
+        self.for_each_driver(|addr, drv| match &configs[addr as usize] {
+            Some(config) => apply_config(drv, config),
+            None => Ok(()),
+        })
+    }
+
+    /// Switch the outputs of every discovered node on together, so coupled
+    /// axes such as the two sides of a gantry take the load at the same time.
+    ///
+    /// Reads CHOPCONF from all nodes first, then writes them back to back
+    /// with TOFF restored: the last non-zero value seen by
+    /// [`Self::disable_all`], or 3 if there is none. After
+    /// [`ENABLE_SETTLE_US`] it pulls the owned EN pins low one after the
+    /// other. A node whose register phase failed keeps its EN pin high.
+    pub fn enable_all<D: DelayNs>(&mut self, delay: &mut D) -> BusResults {
+        let mut chopconf = [0u32; BUS_ADDRESSES];
+        let mut results = self.read_chopconf(&mut chopconf);
+        let saved_toff = self.saved_toff;
+        self.continue_each(&mut results, |addr, drv| {
+            let raw = chopconf[addr as usize];
+            if raw & CHOPCONF_TOFF_MASK != 0 {
+                return Ok(());
+            }
+            let toff = match saved_toff[addr as usize] {
+                0 => DEFAULT_TOFF,
+                toff => toff as u32,
+            };
+            drv.write_register(REG_CHOPCONF, raw | toff)
+        });
+        if self.has_enable_pins(&results) {
+            delay.delay_us(ENABLE_SETTLE_US);
+            self.set_enable_pins(&mut results, true);
+        }
+        results
+    }
+
+    /// Switch the outputs of every discovered node off together.
+    ///
+    /// The reverse of [`Self::enable_all`]: drives the owned EN pins high one
+    /// after the other, waits [`ENABLE_SETTLE_US`], then reads CHOPCONF from
+    /// all nodes and writes them back to back with TOFF = 0, remembering the
+    /// previous TOFF. The register phase also runs for nodes whose pin
+    /// failed, so each node is switched off by at least one of the two.
+    pub fn disable_all<D: DelayNs>(&mut self, delay: &mut D) -> BusResults {
+        let mut pin_results = BusResults::default();
+        for addr in self.addresses() {
+            pin_results.set(addr, Ok(()));
+        }
+        if self.has_enable_pins(&pin_results) {
+            self.set_enable_pins(&mut pin_results, false);
+            delay.delay_us(ENABLE_SETTLE_US);
+        }
+        let mut chopconf = [0u32; BUS_ADDRESSES];
+        let mut results = self.read_chopconf(&mut chopconf);
+        let mut saved_toff = self.saved_toff;
+        self.continue_each(&mut results, |addr, drv| {
+            let raw = chopconf[addr as usize];
+            let toff = raw & CHOPCONF_TOFF_MASK;
+            if toff == 0 {
+                return Ok(());
+            }
+            saved_toff[addr as usize] = toff as u8;
+            drv.write_register(REG_CHOPCONF, raw & !CHOPCONF_TOFF_MASK)
+        });
+        self.saved_toff = saved_toff;
+        for (addr, error) in pin_results.failures() {
+            if results.get(addr) == Some(Ok(())) {
+                results.set(addr, Err(error));
+            }
+        }
+        results
+    }
+
+    /// Read CHOPCONF from every discovered node into `out`, by address.
+    fn read_chopconf(&mut self, out: &mut [u32; BUS_ADDRESSES]) -> BusResults {
+        self.for_each_driver(|addr, drv| {
+            out[addr as usize] = drv.read_register(REG_CHOPCONF)?;
+            Ok(())
+        })
+    }
+
+    /// Run `f` for every node that is still present and succeeded so far in
+    /// `results`, recording the outcome there.
+    fn continue_each<F>(&mut self, results: &mut BusResults, mut f: F)
+    where
+        F: FnMut(u8, &mut BusDriver<SERIAL, CRC>) -> Result<(), TmcError>,
+    {
+        for addr in 0..BUS_ADDRESSES as u8 {
+            if self.is_present(addr) && results.get(addr) == Some(Ok(())) {
+                self.select(addr);
+                let result = f(addr, &mut self.link);
+                self.track(addr, &result);
+                results.set(addr, result);
+            }
+        }
+    }
+
+    /// `true` if a node that succeeded so far in `results` has an EN pin.
+    fn has_enable_pins(&self, results: &BusResults) -> bool {
+        (0..BUS_ADDRESSES as u8)
+            .any(|addr| results.get(addr) == Some(Ok(())) && self.en_pins[addr as usize].is_some())
+    }
+
+    /// Drive the EN pins of the nodes that succeeded so far in `results`,
+    /// low to enable, in address order without pausing in between.
+    fn set_enable_pins(&mut self, results: &mut BusResults, enabled: bool) {
+        for (addr, pin) in self.en_pins.iter_mut().enumerate() {
+            let Some(pin) = pin else {
+                continue;
+            };
+            if results.get(addr as u8) != Some(Ok(())) {
+                continue;
+            }
+            let result = if enabled {
+                pin.set_low()
+            } else {
+                pin.set_high()
+            };
+            if result.is_err() {
+                results.set(addr as u8, Err(TmcError::PinError));
+            }
+        }
+    }
+}
+
+/// Write `config` to the node `drv` currently addresses.
+fn apply_config<SERIAL, CRC>(
+    drv: &mut BusDriver<SERIAL, CRC>,
+    config: &MotorConfig,
+) -> Result<(), TmcError>
+where
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    drv.set_current(config.run_current, config.hold_current, config.hold_delay)
+}
@@ -1,5 +1,12 @@
 //! Configuration objects or enums for TMC2209 usage
-//! Currently not used.
+
+use crate::errors::TmcError;
+use crate::fields::VACTUAL_MAX;
+use crate::planner::PlannedSegment;
+use crate::ramp::{isqrt, RampConfig, StepDelayNs};
+#[cfg(feature = "uart")]
+use crate::registers::{STALLGUARD_REGISTER_ADDRS, TMC2209_REGISTER_ADDRS};
+use crate::values::{Ihold, IholdDelay, Irun};
 
 #[derive(Debug, Clone, Copy)]
 pub struct MotorConfig {
@@ -20,3 +27,351 @@ impl Default for MotorConfig {
         }
     }
 }
+
+impl MotorConfig {
+    /// Check every field against its register range, as `set_current` does.
+    ///
+    /// A `const fn`, so a `const` configuration can be checked at build time
+    /// with [`const_validate!`](crate::const_validate).
+    pub const fn validate(&self) -> Result<(), TmcError> {
+        if self.run_current > Irun::MAX
+            || self.hold_current > Ihold::MAX
+            || self.hold_delay > IholdDelay::MAX
+        {
+            return Err(TmcError::InvalidArgument);
+        }
+        Ok(())
+    }
+}
+
+/// Shape of the pulses on the STEP pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StepPulse {
+    /// Minimum active time of each pulse, in ns
+    pub high_ns: u32,
+    /// Minimum inactive time between pulses, in ns
+    pub low_ns: u32,
+    /// `true` if the STEP signal is inverted between MCU and driver, i.e. the
+    /// pin idles high and pulses low
+    pub inverted: bool,
+}
+
+/// Clock source driving the TMC2209.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockSource {
+    /// Internal oscillator, nominally 12 MHz.
+    #[default]
+    Internal,
+    /// External clock on the CLK pin, frequency in Hz.
+    External(u32),
+}
+
+impl ClockSource {
+    /// Nominal frequency of the internal oscillator.
+    pub const INTERNAL_HZ: u32 = 12_000_000;
+    /// Lowest external clock frequency allowed by the datasheet.
+    pub const EXTERNAL_MIN_HZ: u32 = 4_000_000;
+    /// Highest external clock frequency allowed by the datasheet.
+    pub const EXTERNAL_MAX_HZ: u32 = 16_000_000;
+
+    /// Clock frequency in Hz.
+    pub fn frequency_hz(&self) -> u32 {
+        match *self {
+            ClockSource::Internal => Self::INTERNAL_HZ,
+            ClockSource::External(hz) => hz,
+        }
+    }
+
+    /// Check an external clock against the 4–16 MHz datasheet range.
+    pub fn validate(&self) -> Result<(), TmcError> {
+        match *self {
+            ClockSource::Internal => Ok(()),
+            ClockSource::External(hz) => {
+                if (Self::EXTERNAL_MIN_HZ..=Self::EXTERNAL_MAX_HZ).contains(&hz) {
+                    Ok(())
+                } else {
+                    Err(TmcError::InvalidArgument)
+                }
+            }
+        }
+    }
+
+    /// TSTEP value seen at `rpm` on a motor with `full_steps_per_rev` full steps.
+    ///
+    /// TSTEP counts clocks per 1/256 microstep regardless of MRES. Returns the
+    /// 20-bit maximum for speeds too low to measure (including 0).
+    pub fn tstep_at_rpm(&self, rpm: u32, full_steps_per_rev: u16) -> u32 {
+        const TSTEP_MAX: u64 = 0x000F_FFFF;
+        let usteps_per_min = rpm as u64 * full_steps_per_rev as u64 * 256;
+        if usteps_per_min == 0 {
+            return TSTEP_MAX as u32;
+        }
+        let tstep = self.frequency_hz() as u64 * 60 / usteps_per_min;
+        tstep.min(TSTEP_MAX) as u32
+    }
+
+    /// Baud rate range (min, max) the UART autobaud detection can follow.
+    ///
+    /// The datasheet specifies 9000..500k baud at 12 MHz; both limits scale
+    /// linearly with the clock.
+    pub fn baud_range(&self) -> (u32, u32) {
+        let hz = self.frequency_hz() as u64;
+        let min = hz * 9_000 / Self::INTERNAL_HZ as u64;
+        let max = hz * 500_000 / Self::INTERNAL_HZ as u64;
+        (min as u32, max as u32)
+    }
+}
+
+/// Chip on the other end of the UART. The register maps differ slightly.
+#[cfg(feature = "uart")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChipVariant {
+    /// TMC2209 / TMC2226, with StallGuard and CoolStep.
+    #[default]
+    Tmc2209,
+    /// TMC2208 / TMC2225, without StallGuard and CoolStep registers.
+    Tmc2208,
+    /// Any chip: every address is sent as is, e.g. for registers missing
+    /// from this crate's map or a compatible chip with more of them.
+    Unchecked,
+}
+
+#[cfg(feature = "uart")]
+impl ChipVariant {
+    /// `true` if the chip has a register at `reg`. Always `true` for
+    /// [`ChipVariant::Unchecked`].
+    pub fn has_register(&self, reg: u8) -> bool {
+        let reg = reg & 0x7F;
+        match self {
+            ChipVariant::Tmc2209 => TMC2209_REGISTER_ADDRS.contains(&reg),
+            ChipVariant::Tmc2208 => {
+                TMC2209_REGISTER_ADDRS.contains(&reg) && !STALLGUARD_REGISTER_ADDRS.contains(&reg)
+            }
+            ChipVariant::Unchecked => true,
+        }
+    }
+}
+
+/// Sanity checks run by `init_uart_with`.
+#[cfg(feature = "uart")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitChecks {
+    /// Required IOIN.VERSION, or `None` to accept any chip (0x21 for the
+    /// TMC2209, 0x20 for the TMC2208)
+    pub expected_version: Option<u8>,
+    /// Fail if GSTAT.drv_err is still set after clearing it
+    pub fail_on_drv_err: bool,
+    /// Fail if GSTAT.uv_cp is still set after clearing it
+    pub fail_on_uv_cp: bool,
+}
+
+#[cfg(feature = "uart")]
+impl Default for InitChecks {
+    fn default() -> Self {
+        InitChecks {
+            expected_version: Some(crate::fields::TMC2209_VERSION),
+            fail_on_drv_err: true,
+            fail_on_uv_cp: true,
+        }
+    }
+}
+
+/// Parameters of `self_test_with`.
+#[cfg(feature = "uart")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestConfig {
+    /// Required IOIN.VERSION, or `None` to accept any chip
+    pub expected_version: Option<u8>,
+    /// Run current during the motion check; keep it low so an unloaded motor
+    /// does not jump
+    pub irun: Irun,
+    /// Steps taken forward and back again in the motion check, at least 1
+    pub steps: u32,
+    /// Step rate of the motion check, in steps/s
+    pub speed: u32,
+}
+
+#[cfg(feature = "uart")]
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        SelfTestConfig {
+            expected_version: Some(crate::fields::TMC2209_VERSION),
+            irun: Irun::saturating(8),
+            steps: 16,
+            speed: 200,
+        }
+    }
+}
+
+/// What to do once the motor has been idle for the configured time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleAction {
+    /// Lower the hold current (IHOLD) to the given value in [0..31].
+    ReduceHoldCurrent(u8),
+    /// Disable the driver outputs via EN. Holding torque is lost.
+    Disable,
+}
+
+/// Automatic power saving after a period without motion commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdlePolicy {
+    /// Time without motion before `action` is applied, in milliseconds
+    pub timeout_ms: u32,
+    /// Power-saving action
+    pub action: IdleAction,
+}
+
+/// Limits for manual jogging with `start_jog`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JogConfig {
+    /// Highest jog velocity, in VACTUAL units; faster requests are clamped
+    pub max_velocity: u32,
+    /// Time without `jog_keepalive` after which the jog stops, in milliseconds
+    pub keepalive_timeout_ms: u32,
+}
+
+impl Default for JogConfig {
+    fn default() -> Self {
+        JogConfig {
+            max_velocity: 10_000,
+            keepalive_timeout_ms: 250,
+        }
+    }
+}
+
+/// Physical limits of an axis, enforced by every motion command of a driver.
+///
+/// Units are steps at the microstep resolution in effect, as for
+/// [`crate::RampConfig`]. The default places no limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KinematicLimits {
+    /// Highest speed, in steps/s
+    pub max_v: u32,
+    /// Highest acceleration, in steps/s²
+    pub max_a: u32,
+    /// Highest jerk, in steps/s³
+    pub max_jerk: u32,
+}
+
+impl KinematicLimits {
+    /// No limits.
+    pub const UNLIMITED: Self = KinematicLimits {
+        max_v: u32::MAX,
+        max_a: u32::MAX,
+        max_jerk: u32::MAX,
+    };
+
+    /// Highest acceleration for a speed change of `delta_v` steps/s.
+    ///
+    /// The ramps in this crate are trapezoidal, so jerk is bounded by capping
+    /// the acceleration at √(max_jerk × Δv), the peak acceleration of a
+    /// jerk-limited profile covering the same speed change. Never below 1.
+    pub fn acceleration_for(&self, delta_v: u32) -> u32 {
+        let jerk_cap = isqrt(self.max_jerk as u64 * delta_v as u64);
+        (self.max_a as u64).min(jerk_cap).max(1) as u32
+    }
+
+    /// `ramp` with its speeds and acceleration brought within the limits.
+    pub fn limit_ramp(&self, ramp: &RampConfig) -> RampConfig {
+        let max_speed = ramp.max_speed.min(self.max_v);
+        let start_speed = ramp.start_speed.min(max_speed);
+        RampConfig {
+            start_speed,
+            max_speed,
+            acceleration: ramp
+                .acceleration
+                .min(self.acceleration_for(max_speed - start_speed)),
+        }
+    }
+
+    /// `segment` with its speeds and acceleration brought within the limits.
+    pub fn limit_segment(&self, segment: &PlannedSegment) -> PlannedSegment {
+        let cruise_speed = segment.cruise_speed.min(self.max_v);
+        let entry_speed = segment.entry_speed.min(cruise_speed);
+        let exit_speed = segment.exit_speed.min(cruise_speed);
+        let delta_v = cruise_speed - entry_speed.min(exit_speed);
+        PlannedSegment {
+            steps: segment.steps,
+            entry_speed,
+            cruise_speed,
+            exit_speed,
+            acceleration: segment.acceleration.min(self.acceleration_for(delta_v)),
+        }
+    }
+
+    /// Shortest step period allowed by `max_v`, in ns.
+    pub fn min_step_period_ns(&self) -> u32 {
+        StepDelayNs::from_speed(self.max_v).0
+    }
+
+    /// Highest VACTUAL magnitude allowed by `max_v` with a chip clock of
+    /// `clock_hz`, at most [`crate::VACTUAL_MAX`].
+    pub fn max_vactual(&self, clock_hz: u32) -> u32 {
+        // VACTUAL is in microsteps per 2^24 clock cycles.
+        let vactual = ((self.max_v as u64) << 24) / clock_hz.max(1) as u64;
+        vactual.min(VACTUAL_MAX as u64) as u32
+    }
+}
+
+impl Default for KinematicLimits {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+/// Suspension of UART traffic after a burst of CRC failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommQuarantine {
+    /// Consecutive CRC failures that trigger the quarantine
+    pub crc_failures: u8,
+    /// Time UART traffic stays suspended, in milliseconds
+    pub backoff_ms: u32,
+}
+
+impl Default for CommQuarantine {
+    fn default() -> Self {
+        CommQuarantine {
+            crc_failures: 3,
+            backoff_ms: 100,
+        }
+    }
+}
+
+/// Default number of polls per reply byte before a read times out.
+pub(crate) const DEFAULT_READ_TIMEOUT_POLLS: u32 = 100_000;
+
+/// Settings for a Full UART driver created with `with_pins`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UartOptions {
+    /// Node address set by the MS1/MS2 pins, in [0..3]
+    pub slave_address: u8,
+    /// Clock the chip runs from
+    pub clock: ClockSource,
+    /// Polls per reply byte before a read times out
+    pub read_timeout_polls: u32,
+    /// Consume the echo of transmitted datagrams (single-wire UART)
+    pub echo_handling: bool,
+    /// Retries of a failed register read
+    pub retries: u8,
+    /// STEP pulse timing and polarity
+    pub step_pulse: StepPulse,
+    /// Wait between a direction change and the next step, in ns
+    pub dir_setup_ns: u32,
+    /// Automatic power saving, if any
+    pub idle_policy: Option<IdlePolicy>,
+}
+
+impl Default for UartOptions {
+    fn default() -> Self {
+        UartOptions {
+            slave_address: 0,
+            clock: ClockSource::Internal,
+            read_timeout_polls: DEFAULT_READ_TIMEOUT_POLLS,
+            echo_handling: false,
+            retries: 0,
+            step_pulse: StepPulse::default(),
+            dir_setup_ns: 0,
+            idle_policy: None,
+        }
+    }
+}
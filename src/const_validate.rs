@@ -0,0 +1,60 @@
+//! Build-time checks for configuration known at compile time.
+//!
+//! Firmware usually fixes its motor settings in the image. The range checks
+//! behind the runtime setters are `const fn`s ([`MotorConfig::validate`],
+//! [`encode_mres`], [`Irun::try_new`] and the other bounded values), and
+//! [`const_validate!`](crate::const_validate) evaluates one of them while
+//! compiling, so a bad current or microstep value fails the build instead of
+//! returning [`TmcError::InvalidArgument`] on the bench.
+//!
+//! For values that come in as const generics, put the check in an associated
+//! const and use it from the constructor, as
+//! [`Tmc2209FullUartAt`](crate::Tmc2209FullUartAt) does for its address.
+//!
+//! [`MotorConfig::validate`]: crate::MotorConfig::validate
+//! [`encode_mres`]: crate::encode_mres
+//! [`Irun::try_new`]: crate::Irun::try_new
+//! [`TmcError::InvalidArgument`]: crate::TmcError::InvalidArgument
+
+/// Fail the build unless a constant check returns `Ok`.
+///
+/// Takes any expression of type `Result<_, TmcError>` that can be evaluated
+/// in a `const` context, and an optional message for the compile error:
+///
+/// ```
+/// use tmc2209_driver::{const_validate, encode_mres, MotorConfig};
+///
+/// const MOTOR: MotorConfig = MotorConfig {
+///     run_current: 20,
+///     hold_current: 10,
+///     hold_delay: 6,
+/// };
+/// const MICROSTEPS: u16 = 16;
+///
+/// const_validate!(MOTOR.validate());
+/// const_validate!(encode_mres(MICROSTEPS), "microsteps must be 1, 2, 4, ..., 256");
+/// ```
+///
+/// An out-of-range value stops the build:
+///
+/// ```compile_fail
+/// use tmc2209_driver::{const_validate, Irun};
+///
+/// const_validate!(Irun::try_new(40)); // IRUN is at most 31
+/// ```
+///
+/// Expands to an unnamed `const` item, so it can stand wherever items can,
+/// e.g. next to the constants it checks.
+#[macro_export]
+macro_rules! const_validate {
+    ($check:expr $(,)?) => {
+        const _: () = ::core::assert!(
+            $check.is_ok(),
+            "{}",
+            ::core::concat!("invalid configuration: ", ::core::stringify!($check)),
+        );
+    };
+    ($check:expr, $msg:literal $(,)?) => {
+        const _: () = ::core::assert!($check.is_ok(), $msg);
+    };
+}
@@ -0,0 +1,108 @@
+//! CoolStep current statistics.
+//!
+//! [`CurrentHistogram`] records how long the motor spent at each actual
+//! current scale (DRV_STATUS.CS_ACTUAL). With CoolStep enabled the scale drops
+//! during light-load phases; the histogram shows whether that happens and how
+//! much coil power it saves compared to running at IRUN all the time.
+
+use crate::erased::ErasedTmc2209;
+use crate::errors::TmcError;
+use crate::fields::DrvStatus;
+use crate::registers::*;
+use crate::values::Percent;
+
+/// Number of CS_ACTUAL values (0..=31).
+const CS_LEVELS: usize = 32;
+
+/// Time-weighted histogram of CS_ACTUAL with `BINS` equal-width bins.
+#[derive(Debug, Clone)]
+pub struct CurrentHistogram<const BINS: usize = 8> {
+    bins: [u32; BINS],
+    total_ms: u32,
+    /// Sum of (CS_ACTUAL + 1)² weighted by time, proportional to coil power
+    power_sum: u64,
+    last: Option<(u32, u8)>,
+}
+
+impl<const BINS: usize> CurrentHistogram<BINS> {
+    /// Empty histogram.
+    pub const fn new() -> Self {
+        CurrentHistogram {
+            bins: [0; BINS],
+            total_ms: 0,
+            power_sum: 0,
+            last: None,
+        }
+    }
+
+    /// Read CS_ACTUAL from `driver` and account the time since the previous
+    /// sample to the level seen then.
+    ///
+    /// `now_ms` is a millisecond timestamp from the application's clock;
+    /// wrapping is fine as long as samples are less than 49 days apart.
+    pub fn sample<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+        now_ms: u32,
+    ) -> Result<u8, TmcError> {
+        let cs_actual = DrvStatus::from_raw(driver.read_register(REG_DRVSTATUS)?).cs_actual;
+        if let Some((last_ms, last_cs)) = self.last {
+            self.record(last_cs, now_ms.wrapping_sub(last_ms));
+        }
+        self.last = Some((now_ms, cs_actual));
+        Ok(cs_actual)
+    }
+
+    /// Add `duration_ms` spent at current scale `cs_actual` (0..=31).
+    pub fn record(&mut self, cs_actual: u8, duration_ms: u32) {
+        let cs = cs_actual.min(CS_LEVELS as u8 - 1);
+        let bin = cs as usize * BINS / CS_LEVELS;
+        if let Some(slot) = self.bins.get_mut(bin) {
+            *slot = slot.saturating_add(duration_ms);
+        }
+        self.total_ms = self.total_ms.saturating_add(duration_ms);
+        let scale = cs as u64 + 1;
+        self.power_sum += scale * scale * duration_ms as u64;
+    }
+
+    /// Time spent in each bin, in milliseconds.
+    pub fn bins(&self) -> &[u32; BINS] {
+        &self.bins
+    }
+
+    /// Lowest and highest CS_ACTUAL counted in bin `index`.
+    pub fn bin_range(index: usize) -> (u8, u8) {
+        let low = (index * CS_LEVELS).div_ceil(BINS);
+        let high = ((index + 1) * CS_LEVELS).div_ceil(BINS) - 1;
+        (low as u8, high.min(CS_LEVELS - 1) as u8)
+    }
+
+    /// Total time recorded, in milliseconds.
+    pub fn total_ms(&self) -> u32 {
+        self.total_ms
+    }
+
+    /// Average coil power relative to running at `irun` (0..=31) the whole
+    /// time, or `None` before anything was recorded.
+    ///
+    /// Coil power goes with the square of the current, so a motor that spent
+    /// half its time at half current reads 62 %.
+    pub fn relative_power(&self, irun: u8) -> Option<Percent> {
+        if self.total_ms == 0 {
+            return None;
+        }
+        let full = (irun.min(CS_LEVELS as u8 - 1) as u64 + 1).pow(2) * self.total_ms as u64;
+        Some(Percent::new((self.power_sum * 100 / full).min(100) as u8))
+    }
+
+    /// Forget all samples.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<const BINS: usize> Default for CurrentHistogram<BINS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
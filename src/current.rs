@@ -0,0 +1,257 @@
+//! Run and hold current in milliamps, with supply-voltage derating.
+//!
+//! IRUN/IHOLD scale a full-scale current set by the sense resistors and
+//! CHOPCONF.vsense. Compact boards often cannot dissipate the same current at
+//! 24 V as at 12 V, so a derating curve caps IRUN depending on the measured
+//! motor supply. A power rating for the sense resistors guards against
+//! currents that would burn them.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::ramp::isqrt;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::{Ihold, IholdDelay, Irun};
+
+/// Sense voltage at full scale with CHOPCONF.vsense = 0, in mV.
+const VFS_HIGH_MV: u32 = 325;
+/// Sense voltage at full scale with CHOPCONF.vsense = 1, in mV.
+const VFS_LOW_MV: u32 = 180;
+/// Resistance of the internal sense path added to Rsense, in mΩ.
+const RSENSE_PATH_MOHM: u32 = 20;
+/// Sense resistor fitted to most TMC2209 boards, in mΩ.
+const DEFAULT_RSENSE_MOHM: u16 = 110;
+
+/// One point of a current derating curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeratingPoint {
+    /// Supply voltage from which this limit applies, in mV
+    pub supply_mv: u32,
+    /// Highest IRUN allowed at or above `supply_mv`
+    pub max_irun: Irun,
+}
+
+/// What `set_current_ma` does with a run current the sense resistors
+/// cannot dissipate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RsenseOverload {
+    /// Fail with [`TmcError::InvalidArgument`] and leave the current unchanged.
+    Refuse,
+    /// Lower IRUN to the highest safe step and report it via
+    /// `is_current_limited`.
+    Clamp,
+}
+
+/// Power rating of the sense resistors and how to enforce it.
+#[derive(Debug, Clone, Copy)]
+struct RsensePower {
+    rating_mw: u32,
+    on_overload: RsenseOverload,
+}
+
+/// Current-related settings kept by the driver.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CurrentSettings {
+    rsense_mohm: u16,
+    supply_mv: Option<u32>,
+    derating: &'static [DeratingPoint],
+    power: Option<RsensePower>,
+    limited: bool,
+}
+
+impl Default for CurrentSettings {
+    fn default() -> Self {
+        CurrentSettings {
+            rsense_mohm: DEFAULT_RSENSE_MOHM,
+            supply_mv: None,
+            derating: &[],
+            power: None,
+            limited: false,
+        }
+    }
+}
+
+impl CurrentSettings {
+    /// Configured sense resistor, in mΩ.
+    pub(crate) fn rsense_mohm(&self) -> u16 {
+        self.rsense_mohm
+    }
+
+    /// IRUN ceiling for the last reported supply voltage, if the curve has one.
+    fn ceiling(&self) -> Option<Irun> {
+        let supply = self.supply_mv?;
+        self.derating
+            .iter()
+            .filter(|p| p.supply_mv <= supply)
+            .max_by_key(|p| p.supply_mv)
+            .map(|p| p.max_irun)
+    }
+
+    /// Highest RMS current the sense resistors can dissipate, in mA.
+    fn power_limit_ma(&self) -> Option<u32> {
+        // P = I_rms² · R, so I_rms = sqrt(P / R); in mW, mA and mΩ that is
+        // sqrt(P · 10⁶ / R).
+        let power = self.power?;
+        let squared = power.rating_mw as u64 * 1_000_000 / self.rsense_mohm as u64;
+        Some(isqrt(squared).min(u32::MAX as u64) as u32)
+    }
+
+    /// Highest IRUN allowed by both the derating curve and the sense resistor
+    /// rating, `None` if neither limits it.
+    fn max_irun(&self, vsense: bool) -> Option<Irun> {
+        let rated = self
+            .power_limit_ma()
+            .map(|ma| Irun::saturating(current_scale(ma, self.rsense_mohm, vsense)));
+        match (self.ceiling(), rated) {
+            (Some(ceiling), Some(rated)) => Some(ceiling.min(rated)),
+            (ceiling, rated) => ceiling.or(rated),
+        }
+    }
+}
+
+/// RMS current at current scale `cs` (0..=31), in mA.
+pub(crate) fn scale_to_ma(cs: u8, rsense_mohm: u16, vsense: bool) -> u32 {
+    let vfs = if vsense { VFS_LOW_MV } else { VFS_HIGH_MV } as u64;
+    let r = rsense_mohm as u64 + RSENSE_PATH_MOHM as u64;
+    let ma = (cs as u64 + 1) * vfs * 1_000 * 100_000 / (32 * r * 141_421);
+    ma as u32
+}
+
+/// Current scale CS (0..=31) giving at most `ma` milliamps RMS.
+fn current_scale(ma: u32, rsense_mohm: u16, vsense: bool) -> u8 {
+    let vfs = if vsense { VFS_LOW_MV } else { VFS_HIGH_MV } as u64;
+    let r = rsense_mohm as u64 + RSENSE_PATH_MOHM as u64;
+    // I_rms = (CS + 1) / 32 * Vfs / R / sqrt(2); sqrt(2) as 141_421 / 100_000.
+    let cs_plus_1 = ma as u64 * 32 * r * 141_421 / (vfs * 1_000 * 100_000);
+    cs_plus_1.clamp(1, 32) as u8 - 1
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Set the sense resistor value used by [`Self::set_current_ma`], in mΩ.
+    /// Defaults to 110 mΩ.
+    pub fn set_sense_resistor(&mut self, rsense_mohm: u16) -> Result<(), TmcError> {
+        if rsense_mohm == 0 {
+            return Err(TmcError::InvalidArgument);
+        }
+        self.current_settings_mut().rsense_mohm = rsense_mohm;
+        Ok(())
+    }
+
+    /// Set the power rating of each sense resistor, in mW, and what
+    /// [`Self::set_current_ma`] does with a run current above what it allows.
+    ///
+    /// The limit follows from the rating and the sense resistor value, so set
+    /// both. Currents written directly with `set_current` are not checked.
+    pub fn set_sense_resistor_power(
+        &mut self,
+        rating_mw: u32,
+        on_overload: RsenseOverload,
+    ) -> Result<(), TmcError> {
+        if rating_mw == 0 {
+            return Err(TmcError::InvalidArgument);
+        }
+        self.current_settings_mut().power = Some(RsensePower {
+            rating_mw,
+            on_overload,
+        });
+        Ok(())
+    }
+
+    /// Highest RMS run current the sense resistors are rated for, in mA,
+    /// `None` without a power rating.
+    pub fn sense_resistor_limit_ma(&self) -> Option<u32> {
+        self.current_settings().power_limit_ma()
+    }
+
+    /// Register a derating curve capping IRUN by supply voltage. At a given
+    /// supply the point with the highest `supply_mv` not above it applies;
+    /// below the first point there is no cap. An empty curve removes it.
+    pub fn set_current_derating(&mut self, curve: &'static [DeratingPoint]) {
+        self.current_settings_mut().derating = curve;
+    }
+
+    /// Report the measured motor supply voltage, in mV.
+    ///
+    /// Takes effect at the next [`Self::set_current_ma`]; call that again to
+    /// re-apply the current after the supply has changed.
+    pub fn set_supply_voltage(&mut self, supply_mv: u32) {
+        self.current_settings_mut().supply_mv = Some(supply_mv);
+    }
+
+    /// IRUN ceiling imposed by the derating curve at the reported supply
+    /// voltage, `None` if unlimited.
+    pub fn current_ceiling(&self) -> Option<Irun> {
+        self.current_settings().ceiling()
+    }
+
+    /// Highest IRUN the derating curve and the sense resistor rating allow,
+    /// `None` if unlimited. Reads CHOPCONF.vsense if a rating is set.
+    ///
+    /// For code that raises IRUN itself, such as [`crate::StallBoost`], so it
+    /// stays within the limits [`Self::set_current_ma`] enforces.
+    pub fn irun_limit(&mut self) -> Result<Option<Irun>, TmcError> {
+        let settings = *self.current_settings();
+        let vsense = match settings.power {
+            Some(_) => self.read_register_blocking(REG_CHOPCONF)? & CHOPCONF_VSENSE != 0,
+            None => false,
+        };
+        Ok(settings.max_irun(vsense))
+    }
+
+    /// `true` if the last [`Self::set_current_ma`] was cut down by the
+    /// derating curve or the sense resistor rating, e.g. to explain reduced
+    /// torque in a UI.
+    pub fn is_current_limited(&self) -> bool {
+        self.current_settings().limited
+    }
+
+    /// Set run and hold current in mA RMS, using the configured sense
+    /// resistor and the CHOPCONF.vsense setting read from the chip.
+    ///
+    /// Values are rounded down to the next IRUN/IHOLD step, and IRUN is capped
+    /// by the derating curve. A run current above the sense resistor rating is
+    /// refused with [`TmcError::InvalidArgument`] or clamped, as set with
+    /// [`Self::set_sense_resistor_power`]. IHOLDDELAY keeps its last written
+    /// value. Returns the IRUN applied.
+    pub fn set_current_ma(&mut self, run_ma: u32, hold_ma: u32) -> Result<Irun, TmcError> {
+        let vsense = self.read_register_blocking(REG_CHOPCONF)? & CHOPCONF_VSENSE != 0;
+        let settings = *self.current_settings();
+        let requested = Irun::saturating(current_scale(run_ma, settings.rsense_mohm, vsense));
+        let mut irun = match settings.ceiling() {
+            Some(ceiling) if ceiling < requested => ceiling,
+            _ => requested,
+        };
+        if let (Some(power), Some(limit_ma)) = (settings.power, settings.power_limit_ma()) {
+            if scale_to_ma(irun.get(), settings.rsense_mohm, vsense) > limit_ma {
+                match power.on_overload {
+                    RsenseOverload::Refuse => return Err(TmcError::InvalidArgument),
+                    RsenseOverload::Clamp => {
+                        irun =
+                            Irun::saturating(current_scale(limit_ma, settings.rsense_mohm, vsense))
+                    }
+                }
+            }
+        }
+        let ihold =
+            Ihold::saturating(current_scale(hold_ma, settings.rsense_mohm, vsense).min(irun.get()));
+        let delay = self
+            .shadow_register(REG_IHOLD_IRUN)
+            .map_or(IholdDelay::saturating(0), |raw| {
+                IholdDelay::saturating(((raw >> 16) & 0x0F) as u8)
+            });
+        self.set_run_hold_current(irun, ihold, delay)?;
+        self.current_settings_mut().limited = irun < requested;
+        Ok(irun)
+    }
+}
@@ -0,0 +1,72 @@
+//! Single-step debugging for the Full UART driver.
+//!
+//! Rough or noisy motion is often tied to particular points of the microstep
+//! table. `debug_step` advances the motor by one step and captures where in the
+//! table it landed together with the coil currents and load, so a sequence of
+//! captures can be lined up against the symptom.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::fields::{DrvStatus, MsCurAct};
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Chip state captured by `debug_step` right after its step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StepCapture {
+    /// Position counter after the step
+    pub position: i32,
+    /// MSCNT, position in the 1024-entry microstep table
+    pub mscnt: u16,
+    /// Actual coil currents (MSCURACT)
+    pub mscuract: MsCurAct,
+    /// StallGuard load measurement (SG_RESULT)
+    pub sg_result: u16,
+    /// Driver status flags and CS_ACTUAL
+    pub drv_status: DrvStatus,
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Issue one step in the direction last set with `set_direction`, then
+    /// read MSCNT, MSCURACT, SG_RESULT and DRV_STATUS into `capture`.
+    ///
+    /// The step uses the configured pulse shape and direction settle time.
+    /// `capture` is only updated once all four reads succeeded. StallGuard
+    /// needs a minimum speed to measure load, so SG_RESULT is of little use
+    /// when stepping one at a time; it is captured for completeness. Refused
+    /// with [`TmcError::InvalidState`] while faulted or emergency-stopped.
+    pub fn debug_step<D: DelayNs>(
+        &mut self,
+        capture: &mut StepCapture,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        self.ensure_can_move()?;
+        self.settle_direction(delay);
+        self.shaped_step(delay)?;
+
+        let mscnt = (self.read_register_blocking(REG_MSCNT)? & MSCNT_MASK) as u16;
+        let mscuract = self.read_mscuract()?;
+        let sg_result = self.read_sg_result()?;
+        let drv_status = self.read_drv_status()?;
+        *capture = StepCapture {
+            position: self.position(),
+            mscnt,
+            mscuract,
+            sg_result,
+            drv_status,
+        };
+        Ok(())
+    }
+}
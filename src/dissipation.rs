@@ -0,0 +1,224 @@
+//! Estimated power dissipation and chip temperature.
+//!
+//! The chip only reports temperature as comparator flags, the first of which
+//! (otpw, 120 °C) already means the design is at its limit. For enclosure and
+//! heatsink validation it helps to know earlier where things are heading.
+//! [`DissipationEstimator`] turns supply voltage, currents, sense resistor and
+//! motion duty cycle into a dissipated power, runs it through a first-order
+//! thermal model on top of a user-supplied ambient temperature and raises an
+//! advisory warning before otpw would trip.
+//!
+//! The model is crude: conduction losses in the bridge MOSFETs plus the
+//! supply current, no switching losses, and one thermal resistance and time
+//! constant for chip and board together. Calibrate `rth_c_per_w` against a
+//! thermocouple on the actual board before relying on absolute numbers.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::current::scale_to_ma;
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::{Ihold, Irun, Percent};
+
+/// Temperature at which the chip sets DRV_STATUS.otpw, in °C.
+pub const OTPW_C: i32 = 120;
+
+/// Drop below the warning threshold that clears a warning, in m°C.
+const WARNING_HYSTERESIS_MC: i32 = 5_000;
+
+/// Board and package parameters for [`DissipationEstimator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DissipationConfig {
+    /// On-resistance of one bridge path, high side plus low side, in mΩ
+    pub rdson_mohm: u32,
+    /// Supply current drawn from VS besides the motor current, in mA
+    pub supply_ma: u32,
+    /// Thermal resistance from junction to ambient, in °C/W
+    pub rth_c_per_w: u32,
+    /// Thermal time constant of chip and board, in milliseconds
+    pub tau_ms: u32,
+    /// Estimated temperature that raises a warning, in °C; keep it well
+    /// below [`OTPW_C`] to get the warning before the chip's own
+    pub warning_c: i32,
+}
+
+impl Default for DissipationConfig {
+    /// Typical values for the QFN28 package on a small 2-layer board, with
+    /// the on-resistance taken warm.
+    fn default() -> Self {
+        DissipationConfig {
+            rdson_mohm: 500,
+            supply_ma: 10,
+            rth_c_per_w: 50,
+            tau_ms: 60_000,
+            warning_c: 100,
+        }
+    }
+}
+
+/// Operating point of the driver, as fed to [`DissipationEstimator::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DissipationInputs {
+    /// Motor supply voltage VS, in mV
+    pub supply_mv: u32,
+    /// Run current scale
+    pub irun: Irun,
+    /// Hold current scale
+    pub ihold: Ihold,
+    /// Sense resistor, in mΩ
+    pub rsense_mohm: u16,
+    /// CHOPCONF.vsense
+    pub vsense: bool,
+    /// Share of time spent moving at the run current; the rest is at hold
+    pub duty: Percent,
+}
+
+impl DissipationInputs {
+    /// Mean power dissipated in the chip, in mW.
+    pub fn power_mw(&self, config: &DissipationConfig) -> u32 {
+        let run_ma = scale_to_ma(self.irun.get(), self.rsense_mohm, self.vsense) as u64;
+        let hold_ma = scale_to_ma(self.ihold.get(), self.rsense_mohm, self.vsense) as u64;
+        let duty = self.duty.get() as u64;
+        // Mean square phase current, in mA².
+        let mean_sq = (run_ma * run_ma * duty + hold_ma * hold_ma * (100 - duty)) / 100;
+        // Two phases, each through one bridge path: mA² × mΩ = nW.
+        let conduction_mw = 2 * mean_sq * config.rdson_mohm as u64 / 1_000_000;
+        let supply_mw = self.supply_mv as u64 * config.supply_ma as u64 / 1_000;
+        (conduction_mw + supply_mw).min(u32::MAX as u64) as u32
+    }
+}
+
+/// Advisory change reported by [`DissipationEstimator::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DissipationWarning {
+    /// The estimate reached `warning_c`
+    Approaching {
+        /// Estimated temperature, in m°C
+        temperature_mc: i32,
+        /// Temperature the estimate is heading for at this operating point, in m°C
+        steady_state_mc: i32,
+    },
+    /// The estimate fell 5 °C below `warning_c` again
+    Cleared {
+        /// Estimated temperature, in m°C
+        temperature_mc: i32,
+    },
+}
+
+/// First-order thermal model of the chip, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DissipationEstimator {
+    config: DissipationConfig,
+    ambient_mc: i32,
+    temperature_mc: i32,
+    last_ms: Option<u32>,
+    warning: bool,
+}
+
+impl DissipationEstimator {
+    /// Estimator starting at the ambient temperature `ambient_c`, in °C.
+    pub fn new(config: DissipationConfig, ambient_c: i32) -> Self {
+        DissipationEstimator {
+            config,
+            ambient_mc: ambient_c * 1_000,
+            temperature_mc: ambient_c * 1_000,
+            last_ms: None,
+            warning: false,
+        }
+    }
+
+    /// Change the ambient temperature, e.g. from an enclosure sensor, in °C.
+    pub fn set_ambient(&mut self, ambient_c: i32) {
+        self.ambient_mc = ambient_c * 1_000;
+    }
+
+    /// Temperature reached if `inputs` stay as they are, in m°C.
+    pub fn steady_state_mc(&self, inputs: &DissipationInputs) -> i32 {
+        let rise = inputs.power_mw(&self.config) as i64 * self.config.rth_c_per_w as i64;
+        (self.ambient_mc as i64 + rise).min(i32::MAX as i64) as i32
+    }
+
+    /// Advance the model to `now_ms` with the operating point of the time
+    /// since the last update, and report a warning raised or cleared.
+    ///
+    /// The first call only sets the time base. Call every few seconds; the
+    /// step is stable for any interval, so irregular intervals are fine.
+    pub fn update(
+        &mut self,
+        inputs: &DissipationInputs,
+        now_ms: u32,
+    ) -> Option<DissipationWarning> {
+        let steady_state_mc = self.steady_state_mc(inputs);
+        if let Some(last) = self.last_ms {
+            let dt = now_ms.wrapping_sub(last) as i64;
+            let tau = self.config.tau_ms.max(1) as i64;
+            // Backward-Euler step of dT/dt = (T_ss − T) / τ: stable for any dt.
+            let delta = (steady_state_mc as i64 - self.temperature_mc as i64) * dt / (tau + dt);
+            self.temperature_mc += delta as i32;
+        }
+        self.last_ms = Some(now_ms);
+
+        let warning_mc = self.config.warning_c * 1_000;
+        if !self.warning && self.temperature_mc >= warning_mc {
+            self.warning = true;
+            Some(DissipationWarning::Approaching {
+                temperature_mc: self.temperature_mc,
+                steady_state_mc,
+            })
+        } else if self.warning && self.temperature_mc < warning_mc - WARNING_HYSTERESIS_MC {
+            self.warning = false;
+            Some(DissipationWarning::Cleared {
+                temperature_mc: self.temperature_mc,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Estimated chip temperature, in m°C.
+    pub fn temperature_mc(&self) -> i32 {
+        self.temperature_mc
+    }
+
+    /// `true` while a warning is raised.
+    pub fn is_warning(&self) -> bool {
+        self.warning
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Operating point for a [`DissipationEstimator`], from the currents
+    /// last written, the configured sense resistor and CHOPCONF.vsense read
+    /// from the chip.
+    ///
+    /// Returns [`TmcError::InvalidState`] if no current has been set yet.
+    pub fn dissipation_inputs(
+        &mut self,
+        supply_mv: u32,
+        duty: Percent,
+    ) -> Result<DissipationInputs, TmcError> {
+        let Some(raw) = self.shadow_register(REG_IHOLD_IRUN) else {
+            return Err(TmcError::InvalidState(self.state()));
+        };
+        let vsense = self.read_register_blocking(REG_CHOPCONF)? & CHOPCONF_VSENSE != 0;
+        Ok(DissipationInputs {
+            supply_mv,
+            irun: Irun::saturating((raw >> 8) as u8 & 0x1F),
+            ihold: Ihold::saturating(raw as u8 & 0x1F),
+            rsense_mohm: self.current_settings().rsense_mohm(),
+            vsense,
+            duty,
+        })
+    }
+}
@@ -0,0 +1,175 @@
+//! Object-safe driver interface.
+//!
+//! The concrete driver types carry one generic parameter per pin plus the UART,
+//! which makes it awkward to keep several of them in one collection. The
+//! [`ErasedTmc2209`] trait exposes the common operations with a fixed error type
+//! so that machines can store `&mut dyn ErasedTmc2209` instead.
+
+use embedded_hal::digital::{InputPin, OutputPin};
+#[cfg(feature = "uart")]
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+#[cfg(feature = "uart")]
+use crate::protocol::Crc8Provider;
+#[cfg(feature = "uart")]
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::tmc2209::{Tmc2209StandaloneLegacy, Tmc2209StandaloneOtpPreconfig};
+use crate::values::Irun;
+
+/// Dyn-compatible view of a TMC2209 driver, independent of its pin and UART types.
+///
+/// Register access is only available on drivers that own a UART; the standalone
+/// drivers return [`TmcError::Unsupported`] from those methods.
+pub trait ErasedTmc2209 {
+    /// Enable the motor driver outputs.
+    fn enable(&mut self) -> Result<(), TmcError>;
+
+    /// Disable the motor driver outputs.
+    fn disable(&mut self) -> Result<(), TmcError>;
+
+    /// Set direction. `true` => DIR pin HIGH.
+    fn set_direction(&mut self, clockwise: bool) -> Result<(), TmcError>;
+
+    /// Issue a single step pulse.
+    fn step_pulse(&mut self) -> Result<(), TmcError>;
+
+    /// Read a 32-bit register.
+    fn read_register(&mut self, reg: u8) -> Result<u32, TmcError>;
+
+    /// Write a 32-bit register.
+    fn write_register(&mut self, reg: u8, value: u32) -> Result<(), TmcError>;
+
+    /// Last value written to a write-only register, if known.
+    fn shadow_register(&self, reg: u8) -> Option<u32>;
+
+    /// Highest IRUN the driver's current limits allow, `None` if unlimited.
+    fn irun_limit(&mut self) -> Result<Option<Irun>, TmcError>;
+}
+
+impl<EN, STEP, DIR, DIAG, INDEX> ErasedTmc2209
+    for Tmc2209StandaloneLegacy<EN, STEP, DIR, DIAG, INDEX>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    DIAG: InputPin,
+    INDEX: InputPin,
+{
+    fn enable(&mut self) -> Result<(), TmcError> {
+        Tmc2209StandaloneLegacy::enable(self)
+    }
+
+    fn disable(&mut self) -> Result<(), TmcError> {
+        Tmc2209StandaloneLegacy::disable(self)
+    }
+
+    fn set_direction(&mut self, clockwise: bool) -> Result<(), TmcError> {
+        Tmc2209StandaloneLegacy::set_direction(self, clockwise)
+    }
+
+    fn step_pulse(&mut self) -> Result<(), TmcError> {
+        Tmc2209StandaloneLegacy::step_pulse(self)
+    }
+
+    fn read_register(&mut self, _reg: u8) -> Result<u32, TmcError> {
+        Err(TmcError::Unsupported)
+    }
+
+    fn write_register(&mut self, _reg: u8, _value: u32) -> Result<(), TmcError> {
+        Err(TmcError::Unsupported)
+    }
+
+    fn shadow_register(&self, _reg: u8) -> Option<u32> {
+        None
+    }
+
+    fn irun_limit(&mut self) -> Result<Option<Irun>, TmcError> {
+        Ok(None)
+    }
+}
+
+impl<EN, STEP, DIR, DIAG, INDEX> ErasedTmc2209
+    for Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR, DIAG, INDEX>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    DIAG: InputPin,
+    INDEX: InputPin,
+{
+    fn enable(&mut self) -> Result<(), TmcError> {
+        Tmc2209StandaloneOtpPreconfig::enable(self)
+    }
+
+    fn disable(&mut self) -> Result<(), TmcError> {
+        Tmc2209StandaloneOtpPreconfig::disable(self)
+    }
+
+    fn set_direction(&mut self, clockwise: bool) -> Result<(), TmcError> {
+        Tmc2209StandaloneOtpPreconfig::set_direction(self, clockwise)
+    }
+
+    fn step_pulse(&mut self) -> Result<(), TmcError> {
+        Tmc2209StandaloneOtpPreconfig::step_pulse(self)
+    }
+
+    fn read_register(&mut self, _reg: u8) -> Result<u32, TmcError> {
+        Err(TmcError::Unsupported)
+    }
+
+    fn write_register(&mut self, _reg: u8, _value: u32) -> Result<(), TmcError> {
+        Err(TmcError::Unsupported)
+    }
+
+    fn shadow_register(&self, _reg: u8) -> Option<u32> {
+        None
+    }
+
+    fn irun_limit(&mut self) -> Result<Option<Irun>, TmcError> {
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "uart")]
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC> ErasedTmc2209
+    for Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    fn enable(&mut self) -> Result<(), TmcError> {
+        Tmc2209FullUartDiagnosticsAndControl::enable(self)
+    }
+
+    fn disable(&mut self) -> Result<(), TmcError> {
+        Tmc2209FullUartDiagnosticsAndControl::disable(self)
+    }
+
+    fn set_direction(&mut self, clockwise: bool) -> Result<(), TmcError> {
+        Tmc2209FullUartDiagnosticsAndControl::set_direction(self, clockwise)
+    }
+
+    fn step_pulse(&mut self) -> Result<(), TmcError> {
+        Tmc2209FullUartDiagnosticsAndControl::step_pulse(self)
+    }
+
+    fn read_register(&mut self, reg: u8) -> Result<u32, TmcError> {
+        Tmc2209FullUartDiagnosticsAndControl::read_register(self, reg)
+    }
+
+    fn write_register(&mut self, reg: u8, value: u32) -> Result<(), TmcError> {
+        Tmc2209FullUartDiagnosticsAndControl::write_register(self, reg, value)
+    }
+
+    fn shadow_register(&self, reg: u8) -> Option<u32> {
+        Tmc2209FullUartDiagnosticsAndControl::shadow_register(self, reg)
+    }
+
+    fn irun_limit(&mut self) -> Result<Option<Irun>, TmcError> {
+        Tmc2209FullUartDiagnosticsAndControl::irun_limit(self)
+    }
+}
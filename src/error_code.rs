@@ -0,0 +1,181 @@
+//! Compact numeric error codes.
+//!
+//! Products that log over a constrained link (a CAN frame, a status byte pair
+//! in a fieldbus, an LED blink pattern) cannot afford strings or the full
+//! [`TmcError`]. [`TmcErrorCode`] packs the part of the driver that failed,
+//! the cause and the register involved into 16 bits:
+//!
+//! ```text
+//! 15      12 11       8 7             0
+//! +---------+----------+---------------+
+//! | module  |  cause   |   register    |
+//! +---------+----------+---------------+
+//! ```
+//!
+//! The register byte is 0xFF when the error does not involve one. Causes are
+//! numbered within their module and never reused, so codes stay stable when
+//! variants are added. [`TMC_ERROR_CODES`] describes every code and is built
+//! from the same list as the conversion, so a host-side tool linking this crate
+//! decodes exactly what the firmware encodes.
+
+use core::fmt;
+
+use crate::errors::TmcError;
+use crate::regmap::{lookup_register, TMC2209_REGISTERS};
+
+/// Register byte of a code that does not involve a register.
+pub const NO_REGISTER: u8 = 0xFF;
+
+/// Part of the driver an error code belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ErrorModule {
+    /// STEP, DIR and EN pin writes
+    Pins = 1,
+    /// UART transport and the register protocol
+    Uart = 2,
+    /// Parameters and configuration
+    Config = 3,
+    /// Motion routines and the driver state machine
+    Motion = 4,
+    /// Queues and formatting helpers
+    Support = 5,
+}
+
+impl ErrorModule {
+    /// Lower-case name, e.g. `"uart"`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            ErrorModule::Pins => "pins",
+            ErrorModule::Uart => "uart",
+            ErrorModule::Config => "config",
+            ErrorModule::Motion => "motion",
+            ErrorModule::Support => "support",
+        }
+    }
+}
+
+/// Description of one module/cause pair, see [`TMC_ERROR_CODES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCodeDef {
+    /// Module the cause belongs to
+    pub module: ErrorModule,
+    /// Cause number within the module, 1..=15
+    pub cause: u8,
+    /// Name of the [`TmcError`] variant
+    pub name: &'static str,
+    /// One-line explanation
+    pub description: &'static str,
+}
+
+/// Define the module and cause of every [`TmcError`] variant, generating both
+/// `TmcError::module_and_cause` and [`TMC_ERROR_CODES`] from one list.
+macro_rules! error_codes {
+    ( $( $(#[$meta:meta])* $variant:ident => $module:ident, $cause:literal, $desc:literal; )* ) => {
+        impl TmcError {
+            fn module_and_cause(&self) -> (ErrorModule, u8) {
+                match self {
+                    $( $(#[$meta])* TmcError::$variant { .. } => (ErrorModule::$module, $cause), )*
+                }
+            }
+        }
+
+        /// Every module/cause pair [`TmcErrorCode`] can hold, for host-side
+        /// decoding.
+        pub static TMC_ERROR_CODES: &[ErrorCodeDef] = &[
+            $( ErrorCodeDef {
+                module: ErrorModule::$module,
+                cause: $cause,
+                name: stringify!($variant),
+                description: $desc,
+            } ),*
+        ];
+    };
+}
+
+error_codes! {
+    PinError => Pins, 1, "a STEP, DIR or EN pin write failed";
+    #[cfg(feature = "uart")]
+    SerialError => Uart, 1, "the serial port reported an error";
+    CrcError => Uart, 2, "a reply failed its CRC check";
+    Timeout => Uart, 3, "a reply did not arrive in time";
+    VerificationError => Uart, 4, "a reply, echo or readback did not match";
+    Busy => Uart, 5, "another non-blocking read was still in flight";
+    CommSuspended => Uart, 6, "UART traffic is suspended after repeated CRC failures";
+    ReplyAddressMismatch => Uart, 7, "a reply was not addressed to the master";
+    ReplyRegisterMismatch => Uart, 8, "a reply was for another register";
+    InvalidArgument => Config, 1, "a parameter is out of range";
+    RateTooHigh => Config, 2, "a velocity exceeds what the chip can represent";
+    Unsupported => Config, 3, "the driver mode has no UART connection";
+    MotionTimeout => Motion, 1, "a motion routine did not finish in time";
+    InvalidState => Motion, 2, "not allowed in the driver's current state";
+    Cancelled => Motion, 3, "aborted by the cancel check";
+    QueueFull => Support, 1, "a fixed-capacity queue is full";
+    FormatError => Support, 2, "writing formatted output failed";
+}
+
+/// A [`TmcError`] packed into 16 bits: module, cause and register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TmcErrorCode(pub u16);
+
+impl TmcErrorCode {
+    /// Module number, bits [15..12].
+    pub const fn module(self) -> u8 {
+        (self.0 >> 12) as u8
+    }
+
+    /// Cause number within the module, bits [11..8].
+    pub const fn cause(self) -> u8 {
+        ((self.0 >> 8) & 0x0F) as u8
+    }
+
+    /// Register involved, `None` if the error does not concern one.
+    pub const fn register(self) -> Option<u8> {
+        match (self.0 & 0xFF) as u8 {
+            NO_REGISTER => None,
+            reg => Some(reg),
+        }
+    }
+
+    /// Description of the code, `None` if it is not one this crate produces.
+    pub fn lookup(self) -> Option<&'static ErrorCodeDef> {
+        TMC_ERROR_CODES
+            .iter()
+            .find(|def| def.module as u8 == self.module() && def.cause == self.cause())
+    }
+}
+
+impl From<TmcError> for TmcErrorCode {
+    fn from(err: TmcError) -> Self {
+        err.code()
+    }
+}
+
+impl TmcError {
+    /// Pack the error into a [`TmcErrorCode`]. Details other than the register,
+    /// such as the serial error kind or driver state, are dropped.
+    pub fn code(&self) -> TmcErrorCode {
+        let (module, cause) = self.module_and_cause();
+        let reg = self.register().unwrap_or(NO_REGISTER);
+        TmcErrorCode(((module as u16) << 12) | ((cause as u16) << 8) | reg as u16)
+    }
+}
+
+/// Decodes the code, e.g. `0x226F uart/CrcError on DRV_STATUS (0x6F): a reply
+/// failed its CRC check`.
+impl fmt::Display for TmcErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:04X}", self.0)?;
+        let Some(def) = self.lookup() else {
+            return f.write_str(" unknown error code");
+        };
+        write!(f, " {}/{}", def.module.name(), def.name)?;
+        if let Some(reg) = self.register() {
+            match lookup_register(TMC2209_REGISTERS, reg) {
+                Some(reg_def) => write!(f, " on {} (0x{:02X})", reg_def.name, reg)?,
+                None => write!(f, " on 0x{:02X}", reg)?,
+            }
+        }
+        write!(f, ": {}", def.description)
+    }
+}
@@ -1,14 +1,181 @@
 //! Errors specific to the TMC2209 driver.
 
+#[cfg(feature = "uart")]
+use embedded_io::ErrorKind;
+
+use crate::state::DriverState;
+
 /// Error type for the TMC2209 driver.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TmcError {
     /// Errors arising from pin operations (e.g., `OutputPin` setting).
     PinError,
-    /// UART read/write errors.
-    SerialError,
+    /// UART read/write errors, with the kind reported by the serial implementation.
+    #[cfg(feature = "uart")]
+    SerialError {
+        /// Kind reported by the serial implementation.
+        kind: ErrorKind,
+        /// Register being accessed.
+        reg: u8,
+        /// Access in progress.
+        op: Operation,
+    },
     /// CRC mismatch in read response
-    CrcError,
-    /// If a register readback check fails.
-    VerificationError,
+    CrcError {
+        /// Register that was being read.
+        reg: u8,
+    },
+    /// A read reply did not arrive in time.
+    Timeout {
+        /// Register that was being read.
+        reg: u8,
+        /// Number of reply bytes received before giving up.
+        bytes_received: u8,
+    },
+    /// A parameter is outside the range accepted by the chip or driver.
+    InvalidArgument,
+    /// A requested velocity exceeds what the chip can represent.
+    RateTooHigh,
+    /// A reply, echo or readback did not match what was expected.
+    VerificationError {
+        /// Register being accessed.
+        reg: u8,
+        /// Access in progress.
+        op: Operation,
+    },
+    /// A reply with a valid CRC was not addressed to the master, e.g. a
+    /// datagram of another node read as a reply on a shared bus.
+    ReplyAddressMismatch {
+        /// Register that was being read.
+        reg: u8,
+        /// Address byte of the reply; the chip always replies with 0xFF.
+        address: u8,
+    },
+    /// A reply with a valid CRC was for another register than the one
+    /// requested, e.g. a late reply to an earlier request.
+    ReplyRegisterMismatch {
+        /// Register that was being read.
+        reg: u8,
+        /// Register in the reply.
+        observed: u8,
+    },
+    /// A non-blocking read for another register is still in flight.
+    Busy,
+    /// A motion routine did not finish within its time limit.
+    MotionTimeout,
+    /// The operation is not allowed in the driver's current state.
+    InvalidState(DriverState),
+    /// A fixed-capacity queue has no room left.
+    QueueFull,
+    /// The operation needs a UART connection this driver mode does not have.
+    Unsupported,
+    /// UART traffic is suspended after repeated CRC failures; see
+    /// `set_comm_quarantine`.
+    CommSuspended,
+    /// Writing formatted output to a `core::fmt::Write` sink failed.
+    FormatError,
+    /// A long-running routine was aborted by the check installed with
+    /// `set_cancel_check`.
+    Cancelled,
+}
+
+/// Register access during which an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Reading a register.
+    Read,
+    /// Writing a register.
+    Write,
+    /// Checking the effect of a write.
+    Verify,
+}
+
+impl TmcError {
+    /// `true` for communication glitches worth retrying (CRC, timeout, bad reply).
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            TmcError::CrcError { .. }
+                | TmcError::Timeout { .. }
+                | TmcError::VerificationError { .. }
+                | TmcError::ReplyAddressMismatch { .. }
+                | TmcError::ReplyRegisterMismatch { .. }
+        )
+    }
+
+    #[cfg(feature = "uart")]
+    /// Collapse a serial error into [`TmcError::SerialError`], keeping its kind.
+    pub(crate) fn serial<E: embedded_io::Error>(err: E, reg: u8, op: Operation) -> Self {
+        TmcError::SerialError {
+            kind: err.kind(),
+            reg,
+            op,
+        }
+    }
+
+    /// Register involved in the failure, for communication errors.
+    pub fn register(&self) -> Option<u8> {
+        match *self {
+            #[cfg(feature = "uart")]
+            TmcError::SerialError { reg, .. } => Some(reg),
+            TmcError::CrcError { reg }
+            | TmcError::Timeout { reg, .. }
+            | TmcError::VerificationError { reg, .. }
+            | TmcError::ReplyAddressMismatch { reg, .. }
+            | TmcError::ReplyRegisterMismatch { reg, .. } => Some(reg),
+            _ => None,
+        }
+    }
+}
+
+/// Per-register outcome of a multi-register read.
+#[cfg(feature = "uart")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiReadStatus {
+    count: usize,
+    failed: u32,
+    first_error: Option<(u8, TmcError)>,
+}
+
+#[cfg(feature = "uart")]
+impl MultiReadStatus {
+    pub(crate) fn new(count: usize) -> Self {
+        MultiReadStatus {
+            count,
+            failed: 0,
+            first_error: None,
+        }
+    }
+
+    pub(crate) fn record_failure(&mut self, index: usize, reg: u8, err: TmcError) {
+        self.failed |= 1 << index;
+        if self.first_error.is_none() {
+            self.first_error = Some((reg, err));
+        }
+    }
+
+    /// Number of registers that were requested.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// `true` if the value at `index` was read successfully.
+    pub fn is_ok(&self, index: usize) -> bool {
+        index < self.count && self.failed & (1 << index) == 0
+    }
+
+    /// `true` if every register was read successfully.
+    pub fn all_ok(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// Number of registers that could not be read.
+    pub fn failed_count(&self) -> u32 {
+        self.failed.count_ones()
+    }
+
+    /// Register and error of the first failure.
+    pub fn first_error(&self) -> Option<(u8, TmcError)> {
+        self.first_error
+    }
 }
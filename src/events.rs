@@ -0,0 +1,116 @@
+//! Asynchronous driver conditions, collected in a small queue.
+//!
+//! Faults, stalls and motion completions are recorded as [`TmcEvent`]s while the
+//! driver runs and drained by the application through `poll_events()`, giving it
+//! a single place to react to everything the driver observed.
+
+/// Something the driver observed that the application may want to react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TmcEvent {
+    /// StallGuard reported a stall at `position`.
+    Stall {
+        /// Position counter when the stall was detected
+        position: i32,
+        /// SG_RESULT that triggered it
+        sg_result: u16,
+    },
+    /// Overtemperature pre-warning became active.
+    Otpw,
+    /// Overtemperature shutdown became active.
+    Overtemperature,
+    /// A short to ground or across a low-side MOSFET was detected.
+    Short,
+    /// Charge pump undervoltage (GSTAT.uv_cp).
+    ChargePumpUndervoltage,
+    /// The chip has been reset since the flag was last cleared (GSTAT.reset).
+    Reset,
+    /// UART communication is failing (CRC errors).
+    CommDegraded,
+    /// UART traffic resumed after a quarantine backoff.
+    CommRestored,
+    /// Homing finished and the position was zeroed.
+    HomingDone,
+    /// A blocking move finished at `position`.
+    MoveComplete {
+        /// Position counter after the move
+        position: i32,
+    },
+    /// The node at this bus address stopped answering and was quarantined.
+    DriverLost(u8),
+    /// A field watched with `RegisterPoll::watch_field` started satisfying
+    /// its predicate.
+    FieldWatch {
+        /// Register address
+        reg: u8,
+        /// Field value, already masked and shifted
+        field: u32,
+    },
+}
+
+/// Fixed-capacity FIFO of events. When full, the oldest event is dropped.
+#[derive(Debug, Clone)]
+pub struct EventQueue<const N: usize> {
+    events: [Option<TmcEvent>; N],
+    head: usize,
+    len: usize,
+    dropped: u32,
+}
+
+impl<const N: usize> EventQueue<N> {
+    /// Create an empty queue.
+    pub const fn new() -> Self {
+        EventQueue {
+            events: [None; N],
+            head: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Append an event, dropping the oldest one if the queue is full.
+    pub fn push(&mut self, event: TmcEvent) {
+        if N == 0 {
+            self.dropped = self.dropped.saturating_add(1);
+            return;
+        }
+        if self.len == N {
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+            self.dropped = self.dropped.saturating_add(1);
+        }
+        self.events[(self.head + self.len) % N] = Some(event);
+        self.len += 1;
+    }
+
+    /// Remove and return the oldest event.
+    pub fn pop(&mut self) -> Option<TmcEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        event
+    }
+
+    /// Number of queued events.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// `true` if no events are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of events lost to overflow since creation.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
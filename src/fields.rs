@@ -0,0 +1,205 @@
+//! Decoding helpers for register fields.
+//!
+//! Several TMC2209 registers pack two's-complement values into sub-fields narrower
+//! than a machine word. Reading them as plain unsigned integers gives wildly wrong
+//! numbers for negative values, so these helpers do the sign extension.
+
+use crate::errors::TmcError;
+
+/// Sign-extend the lowest `bits` bits of `raw` into an `i32`.
+///
+/// `bits` must be in `1..=32`.
+pub const fn sign_extend(raw: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((raw << shift) as i32) >> shift
+}
+
+/// Largest velocity magnitude VACTUAL can hold (24-bit two's complement).
+pub const VACTUAL_MAX: i32 = (1 << 23) - 1;
+
+/// Decode a VACTUAL register value (24-bit two's complement) into a signed velocity.
+pub const fn decode_vactual(raw: u32) -> i32 {
+    sign_extend(raw & 0x00FF_FFFF, 24)
+}
+
+/// Encode a signed velocity into the 24-bit VACTUAL register format.
+///
+/// Values outside ±(2^23 − 1) are truncated to 24 bits; validate them first.
+pub const fn encode_vactual(velocity: i32) -> u32 {
+    (velocity as u32) & 0x00FF_FFFF
+}
+
+/// Encode a microstep resolution (1, 2, 4, ..., 256 per full step) into the
+/// CHOPCONF.mres field value.
+///
+/// Returns [`TmcError::InvalidArgument`] for other resolutions.
+pub const fn encode_mres(microsteps: u16) -> Result<u32, TmcError> {
+    if !microsteps.is_power_of_two() || microsteps > 256 {
+        return Err(TmcError::InvalidArgument);
+    }
+    Ok(8 - microsteps.trailing_zeros())
+}
+
+/// Decoded MSCURACT register: actual microstep currents of both coils.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MsCurAct {
+    /// CUR_A, bits [8..0], signed, range -255..=255
+    pub cur_a: i16,
+    /// CUR_B, bits [24..16], signed, range -255..=255
+    pub cur_b: i16,
+}
+
+impl MsCurAct {
+    /// Decode a raw MSCURACT value.
+    pub const fn from_raw(raw: u32) -> Self {
+        MsCurAct {
+            cur_a: sign_extend(raw & 0x1FF, 9) as i16,
+            cur_b: sign_extend((raw >> 16) & 0x1FF, 9) as i16,
+        }
+    }
+}
+
+/// Decoded PWM_SCALE register: stealthChop amplitude results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PwmScale {
+    /// PWM_SCALE_SUM, bits [7..0], actual PWM duty cycle
+    pub sum: u8,
+    /// PWM_SCALE_AUTO, bits [24..16], signed offset added by the automatic amplitude regulation
+    pub auto: i16,
+}
+
+impl PwmScale {
+    /// Decode a raw PWM_SCALE value.
+    pub const fn from_raw(raw: u32) -> Self {
+        PwmScale {
+            sum: (raw & 0xFF) as u8,
+            auto: sign_extend((raw >> 16) & 0x1FF, 9) as i16,
+        }
+    }
+}
+
+/// Decoded PWM_AUTO register: automatically determined stealthChop parameters.
+///
+/// Unlike PWM_SCALE_AUTO, both fields are unsigned 8-bit values and must not be
+/// sign-extended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PwmAuto {
+    /// PWM_OFS_AUTO, bits [7..0]
+    pub ofs: u8,
+    /// PWM_GRAD_AUTO, bits [23..16]
+    pub grad: u8,
+}
+
+impl PwmAuto {
+    /// Decode a raw PWM_AUTO value.
+    pub const fn from_raw(raw: u32) -> Self {
+        PwmAuto {
+            ofs: (raw & 0xFF) as u8,
+            grad: ((raw >> 16) & 0xFF) as u8,
+        }
+    }
+}
+
+/// IOIN.VERSION of the TMC2209. The TMC2208 reports 0x20.
+pub const TMC2209_VERSION: u8 = 0x21;
+
+/// What `init_uart` found on the chip.
+///
+/// GSTAT is reported twice: as found, which after power-up normally includes
+/// `reset`, and after clearing it, where a flag that is still set means the
+/// condition persists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitReport {
+    /// GSTAT before clearing, bits [2..0]
+    pub gstat: u8,
+    /// GSTAT after clearing, bits [2..0]
+    pub gstat_after_clear: u8,
+    /// IOIN.VERSION
+    pub version: u8,
+    /// IFCNT after initialization
+    pub ifcnt: u8,
+    /// `false` if a check configured to fail did so
+    pub passed: bool,
+}
+
+impl InitReport {
+    /// The chip had been reset since the last GSTAT clear, e.g. by power-up.
+    pub const fn reset(&self) -> bool {
+        self.gstat & (1 << 0) != 0
+    }
+
+    /// The driver has shut down due to overtemperature or a short and the
+    /// condition was still present after clearing.
+    pub const fn drv_err(&self) -> bool {
+        self.gstat_after_clear & (1 << 1) != 0
+    }
+
+    /// The charge pump was still undervolted after clearing; the motor
+    /// supply is likely missing or too low.
+    pub const fn uv_cp(&self) -> bool {
+        self.gstat_after_clear & (1 << 2) != 0
+    }
+}
+
+/// Decoded DRV_STATUS register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DrvStatus {
+    /// Overtemperature pre-warning (bit 0)
+    pub otpw: bool,
+    /// Overtemperature shutdown (bit 1)
+    pub ot: bool,
+    /// Short to ground, phase A (bit 2)
+    pub s2ga: bool,
+    /// Short to ground, phase B (bit 3)
+    pub s2gb: bool,
+    /// Low-side short, phase A (bit 4)
+    pub s2vsa: bool,
+    /// Low-side short, phase B (bit 5)
+    pub s2vsb: bool,
+    /// Open load, phase A (bit 6)
+    pub ola: bool,
+    /// Open load, phase B (bit 7)
+    pub olb: bool,
+    /// 120°C comparator exceeded (bit 8)
+    pub t120: bool,
+    /// 143°C comparator exceeded (bit 9)
+    pub t143: bool,
+    /// 150°C comparator exceeded (bit 10)
+    pub t150: bool,
+    /// 157°C comparator exceeded (bit 11)
+    pub t157: bool,
+    /// Actual current scale, bits [20..16]
+    pub cs_actual: u8,
+    /// Driver is in stealthChop mode (bit 30)
+    pub stealth: bool,
+    /// Standstill detected (bit 31)
+    pub stst: bool,
+}
+
+impl DrvStatus {
+    /// Decode a raw DRV_STATUS value.
+    pub const fn from_raw(raw: u32) -> Self {
+        DrvStatus {
+            otpw: raw & (1 << 0) != 0,
+            ot: raw & (1 << 1) != 0,
+            s2ga: raw & (1 << 2) != 0,
+            s2gb: raw & (1 << 3) != 0,
+            s2vsa: raw & (1 << 4) != 0,
+            s2vsb: raw & (1 << 5) != 0,
+            ola: raw & (1 << 6) != 0,
+            olb: raw & (1 << 7) != 0,
+            t120: raw & (1 << 8) != 0,
+            t143: raw & (1 << 9) != 0,
+            t150: raw & (1 << 10) != 0,
+            t157: raw & (1 << 11) != 0,
+            cs_actual: ((raw >> 16) & 0x1F) as u8,
+            stealth: raw & (1 << 30) != 0,
+            stst: raw & (1 << 31) != 0,
+        }
+    }
+
+    /// `true` if any short-circuit flag is set.
+    pub const fn short(&self) -> bool {
+        self.s2ga || self.s2gb || self.s2vsa || self.s2vsb
+    }
+}
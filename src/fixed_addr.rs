@@ -0,0 +1,105 @@
+//! Full UART driver with the node address fixed at compile time.
+
+use core::ops::{Deref, DerefMut};
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::history::DEFAULT_HISTORY_LEN;
+use crate::protocol::{address_byte, Crc8Provider, SoftwareCrc8};
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// [`Tmc2209FullUartDiagnosticsAndControl`] for the node at address `ADDR`.
+///
+/// For firmwares with a single, hard-wired driver: an address outside 0..=3
+/// fails to compile, and the datagram address byte is computed once up front.
+/// Dereferences to the wrapped driver for everything else.
+pub struct Tmc2209FullUartAt<
+    const ADDR: u8,
+    EN,
+    STEP,
+    DIR,
+    SERIAL,
+    const HISTORY: usize = DEFAULT_HISTORY_LEN,
+    CRC = SoftwareCrc8,
+>(Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>)
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider;
+
+impl<const ADDR: u8, EN, STEP, DIR, SERIAL> Tmc2209FullUartAt<ADDR, EN, STEP, DIR, SERIAL>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+{
+    /// Create the driver for node `ADDR`.
+    pub fn new(en: EN, step: STEP, dir: DIR, serial: SERIAL) -> Self {
+        Self::new_with_crc(en, step, dir, serial, SoftwareCrc8)
+    }
+}
+
+impl<const ADDR: u8, EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartAt<ADDR, EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Address byte (second byte) of every datagram sent to this node.
+    pub const ADDRESS_BYTE: u8 = {
+        assert!(ADDR <= 3, "TMC2209 node addresses are 0..=3");
+        address_byte(ADDR)
+    };
+
+    /// Create the driver for node `ADDR` using `crc` for checksums.
+    pub fn new_with_crc(en: EN, step: STEP, dir: DIR, serial: SERIAL, crc: CRC) -> Self {
+        let _ = Self::ADDRESS_BYTE;
+        Tmc2209FullUartAt(Tmc2209FullUartDiagnosticsAndControl::new_with_crc(
+            en, step, dir, serial, ADDR, crc,
+        ))
+    }
+
+    /// Give back the wrapped driver.
+    pub fn into_inner(
+        self,
+    ) -> Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC> {
+        self.0
+    }
+}
+
+impl<const ADDR: u8, EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC> Deref
+    for Tmc2209FullUartAt<ADDR, EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    type Target = Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const ADDR: u8, EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC> DerefMut
+    for Tmc2209FullUartAt<ADDR, EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
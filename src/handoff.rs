@@ -0,0 +1,104 @@
+//! Handing motion over from the internal step generator to STEP/DIR.
+//!
+//! Writing VACTUAL = 0 at speed stops the rotor within one microstep, which
+//! loses steps under load and jerks the mechanics. `take_over_stepping` ramps
+//! the velocity down first and carries the position estimate across, so an
+//! application can cruise on VACTUAL and finish with its own step generator.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Velocity steps of the VACTUAL ramp-down, the last one being zero.
+const HANDOFF_RAMP_STEPS: i32 = 8;
+
+/// Shortest time spent at each velocity of the ramp-down, in ms.
+const HANDOFF_RAMP_INTERVAL_MS: u32 = 10;
+
+/// TSTEP while the chip sees no steps (20 bits, all set).
+const TSTEP_STANDSTILL: u32 = 0x000F_FFFF;
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Bring VACTUAL motion to a stop and hand the axis to STEP/DIR.
+    ///
+    /// Ramps VACTUAL to zero in eight steps over 70 ms, or longer if the
+    /// [`crate::KinematicLimits`] call for a gentler deceleration, waits for
+    /// DRV_STATUS.stst and leaves the chip listening to the STEP input. If
+    /// TSTEP shows that the chip is not stepping (e.g. it was reset), VACTUAL
+    /// is cleared without a ramp. Motion up to `now_ms` and during the ramp is
+    /// integrated into the position counter as by
+    /// [`Self::update_position_estimate`], which is returned. DIR is set to
+    /// the direction of the VACTUAL motion, so further steps keep counting the
+    /// same way. A running jog is ended.
+    ///
+    /// Returns [`TmcError::Busy`] while a `start_move` move is in progress and
+    /// [`TmcError::MotionTimeout`] if the chip does not report standstill.
+    pub fn take_over_stepping<D: DelayNs>(
+        &mut self,
+        now_ms: u32,
+        delay: &mut D,
+    ) -> Result<i32, TmcError> {
+        if let Some(active) = self.take_active_move() {
+            self.put_active_move(Some(active));
+            return Err(TmcError::Busy);
+        }
+        *self.active_jog_mut() = None;
+        let velocity = self.vactual();
+        if velocity != 0 {
+            let tstep = self.read_register(REG_TSTEP)? & TSTEP_STANDSTILL;
+            self.update_position_estimate(now_ms);
+            let mut now_ms = now_ms;
+            if tstep < TSTEP_STANDSTILL {
+                now_ms = match self.ramp_vactual_down(velocity, now_ms, delay) {
+                    Ok(now_ms) => now_ms,
+                    Err(e) => {
+                        // Never leave the motor running at part speed.
+                        let _ = self.stop_rotation();
+                        return Err(e);
+                    }
+                };
+            }
+            self.rotate_at_tracked(0, now_ms)?;
+            self.set_direction(velocity > 0)?;
+        }
+        self.wait_for_standstill(delay)?;
+        self.end_vactual_tracking();
+        Ok(self.position())
+    }
+
+    /// Step VACTUAL from `velocity` down towards zero, returning the
+    /// timestamp at the end of the ramp.
+    fn ramp_vactual_down<D: DelayNs>(
+        &mut self,
+        velocity: i32,
+        mut now_ms: u32,
+        delay: &mut D,
+    ) -> Result<u32, TmcError> {
+        // VACTUAL is in microsteps per 2^24 clock cycles.
+        let clock_hz = self.clock_source().frequency_hz() as u64;
+        let speed = (velocity.unsigned_abs() as u64 * clock_hz) >> 24;
+        let accel = self.kinematic_limits().acceleration_for(speed as u32) as u64;
+        let intervals = (HANDOFF_RAMP_STEPS - 1) as u64;
+        let needed_ms = (speed * 1000).div_ceil(accel * intervals);
+        let interval_ms = needed_ms.clamp(HANDOFF_RAMP_INTERVAL_MS as u64, u32::MAX as u64) as u32;
+        for i in (1..HANDOFF_RAMP_STEPS).rev() {
+            self.rotate_at_tracked(velocity * i / HANDOFF_RAMP_STEPS, now_ms)?;
+            delay.delay_ms(interval_ms);
+            now_ms = now_ms.wrapping_add(interval_ms);
+        }
+        Ok(now_ms)
+    }
+}
@@ -0,0 +1,85 @@
+//! Diagnostics history kept inside the Full UART driver.
+//!
+//! Capturing a snapshot regularly means that when a fault event fires, the
+//! readings leading up to it are still available for post-mortem analysis.
+
+use crate::fields::DrvStatus;
+
+/// Default number of snapshots kept by the Full UART driver.
+pub const DEFAULT_HISTORY_LEN: usize = 4;
+
+/// Diagnostic registers captured at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiagnosticsSnapshot {
+    /// Application timestamp, in milliseconds
+    pub timestamp_ms: u32,
+    /// Raw GSTAT flags (reset, drv_err, uv_cp)
+    pub gstat: u8,
+    /// Decoded DRV_STATUS
+    pub drv_status: DrvStatus,
+    /// StallGuard result (SG_RESULT)
+    pub sg_result: u16,
+    /// Time between microsteps in 1/fCLK units (TSTEP)
+    pub tstep: u32,
+    /// Driver position counter
+    pub position: i32,
+}
+
+/// Ring buffer of the last `N` snapshots.
+#[derive(Debug, Clone)]
+pub struct DiagnosticsHistory<const N: usize> {
+    snapshots: [DiagnosticsSnapshot; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> DiagnosticsHistory<N> {
+    /// Create an empty history.
+    pub fn new() -> Self {
+        DiagnosticsHistory {
+            snapshots: [DiagnosticsSnapshot::default(); N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Store a snapshot, overwriting the oldest one once full.
+    pub fn push(&mut self, snapshot: DiagnosticsSnapshot) {
+        if N == 0 {
+            return;
+        }
+        self.snapshots[(self.head + self.len) % N] = snapshot;
+        if self.len == N {
+            self.head = (self.head + 1) % N;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Number of stored snapshots.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// `true` if no snapshots are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Forget all snapshots.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Stored snapshots, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &DiagnosticsSnapshot> + '_ {
+        (0..self.len).map(move |i| &self.snapshots[(self.head + i) % N])
+    }
+}
+
+impl<const N: usize> Default for DiagnosticsHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,283 @@
+//! StallGuard-based (sensorless) homing for the Full UART driver.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::events::TmcEvent;
+use crate::motion::MotionPhase;
+use crate::protocol::Crc8Provider;
+use crate::ramp::{ConstantRate, StepDelayNs};
+use crate::registers::*;
+use crate::stall::StallDetector;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::Percent;
+
+/// Steps taken after starting an approach before SG_RESULT is trusted.
+/// StallGuard reads low while the motor is still spinning up.
+pub(crate) const STALL_SPINUP_STEPS: u32 = 16;
+
+/// Parameters for two-speed sensorless homing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HomingConfig {
+    /// Speed of the first approach, in steps/s
+    pub fast_speed: u32,
+    /// Speed of the second, precise approach, in steps/s
+    pub slow_speed: u32,
+    /// Steps to back off between the two approaches
+    pub backoff_steps: u32,
+    /// Direction towards the end stop. `true` => clockwise
+    pub direction: bool,
+    /// Run current during homing, as a percentage of the configured IRUN
+    pub current_percent: Percent,
+    /// Maximum duration of each approach, in milliseconds
+    pub timeout: u32,
+    /// StallGuard threshold (SGTHRS) used while homing
+    pub sgthrs: u8,
+}
+
+impl Default for HomingConfig {
+    fn default() -> Self {
+        HomingConfig {
+            fast_speed: 1_000,
+            slow_speed: 250,
+            backoff_steps: 200,
+            direction: false,
+            current_percent: Percent::new(50),
+            timeout: 10_000,
+            sgthrs: 50,
+        }
+    }
+}
+
+/// Outcome of a successful homing run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HomingResult {
+    /// Position counter at the slow-approach trigger, before it was reset to 0
+    pub trigger_position: i32,
+    /// SG_RESULT that triggered the fast approach
+    pub fast_sg: u16,
+    /// SG_RESULT that triggered the slow approach
+    pub slow_sg: u16,
+}
+
+/// Progress of one stall-seeking approach, shared by `step_until_stall` and
+/// its async version.
+pub(crate) struct StallApproach {
+    period_ns: u32,
+    timeout_ns: u64,
+    elapsed_ns: u64,
+    steps: u32,
+}
+
+impl StallApproach {
+    /// Approach at `speed` steps/s for at most `timeout_ms`.
+    pub(crate) fn new(speed: u32, timeout_ms: u32) -> Self {
+        let StepDelayNs(period_ns) = StepDelayNs::from_speed(speed);
+        StallApproach {
+            period_ns,
+            timeout_ns: timeout_ms as u64 * 1_000_000,
+            elapsed_ns: 0,
+            steps: 0,
+        }
+    }
+
+    /// Step period, in ns.
+    pub(crate) fn period_ns(&self) -> u32 {
+        self.period_ns
+    }
+}
+
+/// Scale the IRUN field of an IHOLD_IRUN value to `percent`.
+pub(crate) fn scale_irun(ihold_irun: u32, percent: Percent) -> u32 {
+    let irun = (ihold_irun >> 8) & 0x1F;
+    let scaled = percent.scale(irun).max(1);
+    (ihold_irun & !(0x1F << 8)) | (scaled << 8)
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Home against a mechanical end stop using StallGuard.
+    ///
+    /// Approaches fast until a stall, backs off, approaches again slowly, then
+    /// sets the position to 0 at the second trigger. The run current is reduced
+    /// to `config.current_percent` of the last value set with `set_current`
+    /// (left untouched if none was set) and restored afterwards.
+    pub fn home<D: DelayNs>(
+        &mut self,
+        config: &HomingConfig,
+        delay: &mut D,
+    ) -> Result<HomingResult, TmcError> {
+        self.ensure_can_move()?;
+        self.set_moving(true);
+        let result =
+            self.with_current_percent(config.current_percent, |drv| drv.home_inner(config, delay));
+        self.set_moving(false);
+        result
+    }
+
+    /// Run `f` with IRUN temporarily scaled to `percent` of its shadowed value,
+    /// restoring it afterwards even if `f` fails.
+    pub(crate) fn with_current_percent<T>(
+        &mut self,
+        percent: Percent,
+        f: impl FnOnce(&mut Self) -> Result<T, TmcError>,
+    ) -> Result<T, TmcError> {
+        let saved_current = self.reduce_current(percent)?;
+        let result = f(self);
+        self.restore_current(saved_current)?;
+        result
+    }
+
+    /// Scale IRUN to `percent` of its shadowed value, returning the value to
+    /// restore with [`Self::restore_current`].
+    pub(crate) fn reduce_current(&mut self, percent: Percent) -> Result<Option<u32>, TmcError> {
+        let saved_current = self.shadow_register(REG_IHOLD_IRUN);
+        if let Some(raw) = saved_current {
+            self.write_register(REG_IHOLD_IRUN, scale_irun(raw, percent))?;
+        }
+        Ok(saved_current)
+    }
+
+    /// Write back the IHOLD_IRUN value saved by [`Self::reduce_current`].
+    pub(crate) fn restore_current(&mut self, saved_current: Option<u32>) -> Result<(), TmcError> {
+        match saved_current {
+            Some(raw) => self.write_register(REG_IHOLD_IRUN, raw),
+            None => Ok(()),
+        }
+    }
+
+    fn home_inner<D: DelayNs>(
+        &mut self,
+        config: &HomingConfig,
+        delay: &mut D,
+    ) -> Result<HomingResult, TmcError> {
+        let detector = self.begin_homing(config)?;
+
+        self.begin_homing_phase(MotionPhase::HomingFast, config.direction, None)?;
+        let fast_sg = self
+            .step_until_stall(
+                config.fast_speed,
+                u32::MAX,
+                config.timeout,
+                &detector,
+                delay,
+            )?
+            .ok_or(TmcError::MotionTimeout)?;
+
+        self.begin_homing_phase(
+            MotionPhase::HomingBackoff,
+            !config.direction,
+            Some(config.backoff_steps),
+        )?;
+        self.run_profile(
+            ConstantRate::new(config.backoff_steps, config.slow_speed),
+            delay,
+        )?;
+
+        self.begin_homing_phase(MotionPhase::HomingSlow, config.direction, None)?;
+        let slow_sg = self
+            .step_until_stall(
+                config.slow_speed,
+                u32::MAX,
+                config.timeout,
+                &detector,
+                delay,
+            )?
+            .ok_or(TmcError::MotionTimeout)?;
+
+        Ok(self.finish_homing(fast_sg, slow_sg))
+    }
+
+    /// Program the homing threshold and return the matching detector.
+    pub(crate) fn begin_homing(
+        &mut self,
+        config: &HomingConfig,
+    ) -> Result<StallDetector, TmcError> {
+        self.set_stallguard_threshold(config.sgthrs)?;
+        Ok(StallDetector::new(config.sgthrs))
+    }
+
+    /// Point the motor in `direction` and report `phase` as started.
+    pub(crate) fn begin_homing_phase(
+        &mut self,
+        phase: MotionPhase,
+        direction: bool,
+        steps_total: Option<u32>,
+    ) -> Result<(), TmcError> {
+        self.set_direction(direction)?;
+        self.progress_mut().begin(phase, steps_total);
+        Ok(())
+    }
+
+    /// Make the slow-approach trigger the new origin.
+    pub(crate) fn finish_homing(&mut self, fast_sg: u16, slow_sg: u16) -> HomingResult {
+        let trigger_position = self.position();
+        self.set_position(0);
+        self.set_homed(true);
+        self.push_event(TmcEvent::HomingDone);
+        HomingResult {
+            trigger_position,
+            fast_sg,
+            slow_sg,
+        }
+    }
+
+    /// Step at `speed` until `detector` reports a stall, returning the SG_RESULT
+    /// that triggered it, or `None` once `max_steps` steps were taken without
+    /// one. Fails with [`TmcError::MotionTimeout`] after `timeout_ms`.
+    pub(crate) fn step_until_stall<D: DelayNs>(
+        &mut self,
+        speed: u32,
+        max_steps: u32,
+        timeout_ms: u32,
+        detector: &StallDetector,
+        delay: &mut D,
+    ) -> Result<Option<u16>, TmcError> {
+        let mut approach = StallApproach::new(speed, timeout_ms);
+        self.settle_direction(delay);
+        for _ in 0..max_steps {
+            self.timed_step(approach.period_ns(), delay)?;
+            if let Some(sg) = self.after_approach_step(&mut approach, detector)? {
+                return Ok(Some(sg));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Account for a step of `approach` and, once past the spin-up, check
+    /// SG_RESULT. Returns the reading `detector` takes for a stall, or fails
+    /// with [`TmcError::MotionTimeout`] once the approach ran out of time.
+    pub(crate) fn after_approach_step(
+        &mut self,
+        approach: &mut StallApproach,
+        detector: &StallDetector,
+    ) -> Result<Option<u16>, TmcError> {
+        approach.steps += 1;
+        approach.elapsed_ns += approach.period_ns as u64;
+
+        if approach.steps > STALL_SPINUP_STEPS {
+            let sg = self.read_sg_result()?;
+            self.progress_mut().record_sg(sg);
+            if detector.is_stall(sg) {
+                self.push_event(TmcEvent::Stall {
+                    position: self.position(),
+                    sg_result: sg,
+                });
+                return Ok(Some(sg));
+            }
+        }
+        if approach.elapsed_ns >= approach.timeout_ns {
+            return Err(TmcError::MotionTimeout);
+        }
+        Ok(None)
+    }
+}
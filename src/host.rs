@@ -0,0 +1,259 @@
+//! Host-side helpers for bench tools, behind the `std` feature.
+//!
+//! A companion binary for bench tuning needs little more than a serial port
+//! and a handful of operations. [`StdSerial`] adapts any `std::io` stream, such
+//! as a port opened with the `serialport` crate, to the `embedded-io` traits the
+//! driver uses, and the functions below cover scanning the bus, dumping
+//! registers, applying a text configuration and reading the OTP memory.
+//!
+//! ```ignore
+//! let port = serialport::new("/dev/ttyUSB0", 115_200)
+//!     .timeout(Duration::from_millis(5))
+//!     .open()?;
+//! let mut bus = tmc2209_driver::host::open(port);
+//! for node in tmc2209_driver::host::scan(&mut bus)? {
+//!     println!("node {} version 0x{:02X}", node.addr, node.version);
+//! }
+//! let drv = bus.driver(0)?;
+//! tmc2209_driver::host::apply_settings(drv, "IHOLD_IRUN.irun = 20\nCHOPCONF.toff = 3")?;
+//! print!("{}", tmc2209_driver::host::dump(drv)?);
+//! ```
+
+use std::fmt::{self, Write as _};
+use std::io;
+use std::string::String;
+use std::vec::Vec;
+
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+
+use crate::bus::Tmc2209Bus;
+use crate::erased::ErasedTmc2209;
+use crate::errors::TmcError;
+use crate::registers::*;
+use crate::regmap::{dump_registers, Access, RegisterDef, TMC2209_REGISTERS};
+
+/// `embedded-io` view of a `std::io` stream.
+///
+/// Give the stream a short read timeout (a few milliseconds), or make it
+/// non-blocking: `read_ready` reads ahead one byte and reports `false` when
+/// that read times out or would block.
+#[derive(Debug)]
+pub struct StdSerial<T> {
+    inner: T,
+    peeked: Option<u8>,
+}
+
+impl<T> StdSerial<T> {
+    /// Wrap `inner`.
+    pub fn new(inner: T) -> Self {
+        StdSerial {
+            inner,
+            peeked: None,
+        }
+    }
+
+    /// Give the stream back. A byte read ahead by `read_ready` is lost.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> ErrorType for StdSerial<T> {
+    type Error = io::Error;
+}
+
+impl<T: io::Read> Read for StdSerial<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(byte) = self.peeked.take() {
+            buf[0] = byte;
+            return Ok(1);
+        }
+        loop {
+            match self.inner.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<T: io::Read> ReadReady for StdSerial<T> {
+    fn read_ready(&mut self) -> Result<bool, io::Error> {
+        if self.peeked.is_some() {
+            return Ok(true);
+        }
+        let mut byte = [0u8; 1];
+        match self.inner.read(&mut byte) {
+            Ok(0) => Ok(false),
+            Ok(_) => {
+                self.peeked = Some(byte[0]);
+                Ok(true)
+            }
+            Err(e) => match e.kind() {
+                io::ErrorKind::TimedOut
+                | io::ErrorKind::WouldBlock
+                | io::ErrorKind::Interrupted => Ok(false),
+                _ => Err(e),
+            },
+        }
+    }
+}
+
+impl<T: io::Write> Write for StdSerial<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Bus manager on `stream`, ready for [`scan`].
+pub fn open<T: io::Read + io::Write>(stream: T) -> Tmc2209Bus<StdSerial<T>> {
+    Tmc2209Bus::new(StdSerial::new(stream))
+}
+
+/// A node found by [`scan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanEntry {
+    /// Node address, in [0..3]
+    pub addr: u8,
+    /// IOIN.VERSION, 0x21 for the TMC2209
+    pub version: u8,
+}
+
+/// Discover the nodes on `bus` and read their silicon version.
+pub fn scan<T: io::Read + io::Write>(
+    bus: &mut Tmc2209Bus<StdSerial<T>>,
+) -> Result<Vec<ScanEntry>, TmcError> {
+    bus.discover()?;
+    let addrs: Vec<u8> = bus.addresses().collect();
+    let mut found = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        let ioin = bus.driver(addr)?.read_register(REG_IOIN)?;
+        found.push(ScanEntry {
+            addr,
+            version: (ioin >> 24) as u8,
+        });
+    }
+    Ok(found)
+}
+
+/// All registers of the TMC2209 with their fields, one per line.
+///
+/// Readable registers are read from the chip; write-only ones show the value
+/// last written through this driver, and are left out if there is none.
+pub fn dump<D: ErasedTmc2209 + ?Sized>(driver: &mut D) -> Result<String, TmcError> {
+    let mut out = String::new();
+    dump_registers(driver, TMC2209_REGISTERS, |def, raw| {
+        let _ = writeln!(out, "{}", def.display(raw));
+    })?;
+    for def in TMC2209_REGISTERS.iter().filter(|d| d.access == Access::W) {
+        if let Some(raw) = driver.shadow_register(def.addr) {
+            let _ = writeln!(out, "{} (last written)", def.display(raw));
+        }
+    }
+    Ok(out)
+}
+
+/// The three OTP bytes (OTP_READ), lowest first.
+pub fn read_otp<D: ErasedTmc2209 + ?Sized>(driver: &mut D) -> Result<[u8; 3], TmcError> {
+    let raw = driver.read_register(REG_OTP_READ)?;
+    Ok([raw as u8, (raw >> 8) as u8, (raw >> 16) as u8])
+}
+
+/// A line of [`apply_settings`] input that could not be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyError {
+    /// Line number, starting at 1
+    pub line: usize,
+    /// [`TmcError::InvalidArgument`] for lines that do not parse or name an
+    /// unknown register or field, otherwise the error of the register access
+    pub error: TmcError,
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error.code())
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+/// Write the settings in `text`, returning the number of register writes.
+///
+/// Each line is `REGISTER = value` or `REGISTER.field = value`, with names as
+/// in [`TMC2209_REGISTERS`] (case-insensitive) and values in decimal or
+/// `0x` hex. Blank lines and lines starting with `#` are skipped. A field
+/// assignment keeps the other fields: readable registers are read first,
+/// write-only ones start from their shadow copy or 0. Stops at the first line
+/// that fails; earlier lines stay applied.
+pub fn apply_settings<D: ErasedTmc2209 + ?Sized>(
+    driver: &mut D,
+    text: &str,
+) -> Result<usize, ApplyError> {
+    let mut writes = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        apply_line(driver, line).map_err(|error| ApplyError {
+            line: index + 1,
+            error,
+        })?;
+        writes += 1;
+    }
+    Ok(writes)
+}
+
+fn apply_line<D: ErasedTmc2209 + ?Sized>(driver: &mut D, line: &str) -> Result<(), TmcError> {
+    let (target, value) = line.split_once('=').ok_or(TmcError::InvalidArgument)?;
+    let value = parse_value(value.trim()).ok_or(TmcError::InvalidArgument)?;
+    let (reg_name, field_name) = match target.trim().split_once('.') {
+        Some((reg, field)) => (reg.trim(), Some(field.trim())),
+        None => (target.trim(), None),
+    };
+    let def = find_register(reg_name).ok_or(TmcError::InvalidArgument)?;
+    if def.access == Access::R {
+        return Err(TmcError::InvalidArgument);
+    }
+
+    let raw = match field_name {
+        None => value,
+        Some(name) => {
+            let field = def
+                .fields
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(name))
+                .ok_or(TmcError::InvalidArgument)?;
+            if value > field.mask() >> field.lsb {
+                return Err(TmcError::InvalidArgument);
+            }
+            let current = if def.access.is_readable() {
+                driver.read_register(def.addr)?
+            } else {
+                driver.shadow_register(def.addr).unwrap_or(0)
+            };
+            field.set(current, value)
+        }
+    };
+    driver.write_register(def.addr, raw)
+}
+
+fn find_register(name: &str) -> Option<&'static RegisterDef> {
+    TMC2209_REGISTERS
+        .iter()
+        .find(|def| def.name.eq_ignore_ascii_case(name))
+}
+
+fn parse_value(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,126 @@
+//! Coherent setup of the stealthChop / spreadCycle / CoolStep speed regions.
+//!
+//! Three settings interact: GCONF.en_spreadcycle disables stealthChop entirely,
+//! TPWMTHRS is the speed above which stealthChop hands over to spreadCycle and
+//! TCOOLTHRS the speed above which CoolStep and StallGuard on DIAG are active.
+//! Both are based on StallGuard4, which only works in stealthChop, so they run
+//! in the band TCOOLTHRS ≥ TSTEP > TPWMTHRS. All thresholds are in TSTEP
+//! units, so faster means smaller.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Chopper mode the driver is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChopperMode {
+    /// Voltage-mode PWM: quiet, best at low speed
+    StealthChop,
+    /// Cycle-by-cycle current control: louder, full torque at speed
+    SpreadCycle,
+}
+
+/// Full steps per revolution assumed by [`Tmc2209FullUartDiagnosticsAndControl::configure_hybrid`].
+pub const HYBRID_FULL_STEPS_PER_REV: u16 = 200;
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Run stealthChop below `stealth_below_rpm`, spreadCycle above it, and
+    /// CoolStep from `coolstep_above_rpm` up to the switch to spreadCycle, for
+    /// a 1.8° motor ([`HYBRID_FULL_STEPS_PER_REV`]) at the configured clock.
+    ///
+    /// `0` disables a region: no stealthChop at all, or no CoolStep. CoolStep
+    /// needs stealthChop, so a non-zero `coolstep_above_rpm` that is not below
+    /// `stealth_below_rpm`, or comes without stealthChop, is rejected with
+    /// [`TmcError::InvalidArgument`]. See [`Self::configure_hybrid_tstep`] for
+    /// other motors.
+    pub fn configure_hybrid(
+        &mut self,
+        stealth_below_rpm: u32,
+        coolstep_above_rpm: u32,
+    ) -> Result<(), TmcError> {
+        let clock = self.clock_source();
+        let to_tstep = |rpm: u32| match rpm {
+            0 => None,
+            rpm => Some(clock.tstep_at_rpm(rpm, HYBRID_FULL_STEPS_PER_REV)),
+        };
+        if coolstep_above_rpm != 0 && coolstep_above_rpm >= stealth_below_rpm {
+            return Err(TmcError::InvalidArgument);
+        }
+        self.configure_hybrid_tstep(to_tstep(stealth_below_rpm), to_tstep(coolstep_above_rpm))
+    }
+
+    /// Chopper mode in effect right now.
+    ///
+    /// With GCONF.en_spreadcycle set, stealthChop can never engage and this
+    /// answers without reading DRV_STATUS. Otherwise the chip switches by
+    /// TPWMTHRS on its own and DRV_STATUS.stealth tells which side of the
+    /// threshold the motor is on.
+    pub fn active_chopper_mode(&mut self) -> Result<ChopperMode, TmcError> {
+        if self.read_register_blocking(REG_GCONF)? & GCONF_EN_SPREADCYCLE != 0 {
+            return Ok(ChopperMode::SpreadCycle);
+        }
+        if self.read_drv_status()?.stealth {
+            Ok(ChopperMode::StealthChop)
+        } else {
+            Ok(ChopperMode::SpreadCycle)
+        }
+    }
+
+    /// [`Self::configure_hybrid`] with thresholds given directly in TSTEP units.
+    ///
+    /// - `tpwmthrs`: `None` for spreadCycle only (en_spreadcycle = 1), otherwise
+    ///   stealthChop while TSTEP ≥ `tpwmthrs`.
+    /// - `tcoolthrs`: `None` to disable CoolStep, otherwise CoolStep while
+    ///   TSTEP ≤ `tcoolthrs` and stealthChop runs. Needs stealthChop and must
+    ///   be above a non-zero `tpwmthrs`, or the CoolStep band is empty.
+    ///
+    /// Values above 20 bits and an empty CoolStep band return
+    /// [`TmcError::InvalidArgument`] before anything is written.
+    pub fn configure_hybrid_tstep(
+        &mut self,
+        tpwmthrs: Option<u32>,
+        tcoolthrs: Option<u32>,
+    ) -> Result<(), TmcError> {
+        const TSTEP_MAX: u32 = 0x000F_FFFF;
+        let pwm = tpwmthrs.unwrap_or(0);
+        let cool = tcoolthrs.unwrap_or(0);
+        if pwm > TSTEP_MAX || cool > TSTEP_MAX {
+            return Err(TmcError::InvalidArgument);
+        }
+        match (tpwmthrs, tcoolthrs) {
+            (None, Some(cool)) if cool != 0 => return Err(TmcError::InvalidArgument),
+            (Some(pwm), Some(cool)) if cool != 0 && pwm != 0 && cool <= pwm => {
+                return Err(TmcError::InvalidArgument)
+            }
+            _ => {}
+        }
+
+        // Program the thresholds before switching modes so the chip never runs
+        // with a stale threshold in the new mode.
+        self.write_register(REG_TPWMTHRS, pwm)?;
+        self.write_register(REG_TCOOLTHRS, cool)?;
+
+        let gconf = self.read_register_blocking(REG_GCONF)?;
+        let new_gconf = if tpwmthrs.is_some() {
+            gconf & !GCONF_EN_SPREADCYCLE
+        } else {
+            gconf | GCONF_EN_SPREADCYCLE
+        };
+        if new_gconf != gconf {
+            self.write_register(REG_GCONF, new_gconf)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! Revolution counting from the INDEX output.
+//!
+//! With GCONF.index_otpw and GCONF.index_step both clear, INDEX pulses once
+//! per electrical period, i.e. every four full steps, when the microstep
+//! sequencer passes its first position. Counting those pulses gives a
+//! revolution count kept by the chip rather than by the step counter, and
+//! comparing the two catches steps the chip never saw: pulses too short for
+//! the STEP input, noise, or a reset. Rotor slip is not visible this way,
+//! since the sequencer follows the steps whether the rotor does or not.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Full steps per electrical period, i.e. per INDEX pulse.
+const FULL_STEPS_PER_PERIOD: i32 = 4;
+
+/// Electrical periods counted from INDEX pulses, see `enable_index_counter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexCounter {
+    periods: i32,
+    full_steps_per_rev: u16,
+    microsteps: u16,
+    start_position: i32,
+}
+
+impl IndexCounter {
+    /// Signed INDEX pulses since the count started.
+    pub fn periods(&self) -> i32 {
+        self.periods
+    }
+
+    /// Whole revolutions since the count started, going by INDEX pulses.
+    pub fn revolutions(&self) -> i32 {
+        self.periods * FULL_STEPS_PER_PERIOD / self.full_steps_per_rev as i32
+    }
+
+    /// Microsteps commanded since the count started minus those accounted
+    /// for by INDEX pulses, at `position`.
+    ///
+    /// Depending on where in the electrical period the count started, this
+    /// lies strictly between minus and plus one period; anything beyond means
+    /// lost or extra steps.
+    pub fn step_deviation(&self, position: i32) -> i32 {
+        let commanded = position.wrapping_sub(self.start_position);
+        commanded.wrapping_sub(self.periods.wrapping_mul(self.period_len()))
+    }
+
+    /// Microsteps per electrical period.
+    fn period_len(&self) -> i32 {
+        FULL_STEPS_PER_PERIOD * self.microsteps as i32
+    }
+
+    /// Start counting again from `position`.
+    pub(crate) fn restart(&mut self, position: i32) {
+        self.periods = 0;
+        self.start_position = position;
+    }
+
+    /// Follow a microstep resolution change by `num / den`.
+    pub(crate) fn rescale(&mut self, num: u32, den: u32) {
+        self.start_position = (self.start_position as i64 * num as i64 / den as i64) as i32;
+        self.microsteps = (self.microsteps as u32 * num / den) as u16;
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Configure INDEX for one pulse per electrical period and start counting
+    /// revolutions from the current position.
+    ///
+    /// Call [`Self::on_index_pulse`] on every rising edge of INDEX. Returns
+    /// [`TmcError::InvalidArgument`] unless `full_steps_per_rev` is a non-zero
+    /// multiple of four.
+    pub fn enable_index_counter(&mut self, full_steps_per_rev: u16) -> Result<(), TmcError> {
+        if full_steps_per_rev == 0 || full_steps_per_rev as i32 % FULL_STEPS_PER_PERIOD != 0 {
+            return Err(TmcError::InvalidArgument);
+        }
+        let gconf = self.read_register_blocking(REG_GCONF)?;
+        let wanted = gconf & !(GCONF_INDEX_OTPW | GCONF_INDEX_STEP);
+        if wanted != gconf {
+            self.write_register(REG_GCONF, wanted)?;
+        }
+        let microsteps = self.microsteps()?;
+        let start_position = self.position();
+        *self.index_counter_mut() = Some(IndexCounter {
+            periods: 0,
+            full_steps_per_rev,
+            microsteps,
+            start_position,
+        });
+        Ok(())
+    }
+
+    /// Stop counting INDEX pulses. GCONF is left as it is.
+    pub fn disable_index_counter(&mut self) {
+        *self.index_counter_mut() = None;
+    }
+
+    /// Count one INDEX pulse, in the direction of the current motion: the
+    /// sign of VACTUAL while the internal generator runs, otherwise DIR.
+    /// Does nothing unless [`Self::enable_index_counter`] was called.
+    pub fn on_index_pulse(&mut self) {
+        let forward = match self.vactual() {
+            0 => self.direction(),
+            velocity => velocity > 0,
+        };
+        if let Some(counter) = self.index_counter_mut() {
+            counter.periods = counter.periods.wrapping_add(if forward { 1 } else { -1 });
+        }
+    }
+
+    /// `true` if the position counter and the INDEX pulses disagree by at
+    /// least one electrical period, i.e. the chip missed or gained steps.
+    /// Always `false` while the counter is disabled.
+    ///
+    /// Only meaningful while the position counter follows the chip: steps
+    /// issued through a split motion handle are not seen here until `unsplit`,
+    /// which restarts the count.
+    pub fn index_steps_lost(&self) -> bool {
+        self.index_counter().is_some_and(|counter| {
+            counter.step_deviation(self.position()).abs() >= counter.period_len()
+        })
+    }
+}
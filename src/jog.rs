@@ -0,0 +1,87 @@
+//! Manual jogging with a dead-man timeout.
+//!
+//! A jog runs on the chip's internal step generator (VACTUAL) and only keeps
+//! going while the application confirms it with `jog_keepalive`, e.g. on every
+//! scan of a held button. If the UI stalls or the button handler is lost, the
+//! next `poll_jog` stops the motor.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// A jog started with `start_jog`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ActiveJog {
+    /// Timestamp of the last keepalive, in ms
+    last_keepalive_ms: u32,
+    /// Dead-man timeout captured at start, in ms
+    timeout_ms: u32,
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Start jogging at `speed` (VACTUAL units) in direction `clockwise`,
+    /// clamped to the configured [`crate::JogConfig::max_velocity`] and the
+    /// kinematic limits. Returns the speed applied.
+    ///
+    /// Call [`Self::jog_keepalive`] more often than the configured timeout and
+    /// [`Self::poll_jog`] regularly, or the jog never times out. Calling this
+    /// again while jogging changes direction or speed.
+    pub fn start_jog(&mut self, clockwise: bool, speed: u32, now_ms: u32) -> Result<u32, TmcError> {
+        let config = self.jog_config();
+        let speed = speed.min(config.max_velocity).min(self.max_vactual());
+        let velocity = speed as i32;
+        self.rotate_at(if clockwise { velocity } else { -velocity })?;
+        *self.active_jog_mut() = Some(ActiveJog {
+            last_keepalive_ms: now_ms,
+            timeout_ms: config.keepalive_timeout_ms,
+        });
+        Ok(speed)
+    }
+
+    /// Confirm that the jog should continue. Does nothing when not jogging.
+    pub fn jog_keepalive(&mut self, now_ms: u32) {
+        if let Some(jog) = self.active_jog_mut() {
+            jog.last_keepalive_ms = now_ms;
+        }
+    }
+
+    /// Stop the jog if no keepalive arrived within the timeout. Returns `true`
+    /// if it was stopped by this call.
+    pub fn poll_jog(&mut self, now_ms: u32) -> Result<bool, TmcError> {
+        let Some(jog) = self.active_jog() else {
+            return Ok(false);
+        };
+        if self.vactual() == 0 {
+            // Stopped by other means, e.g. `stop_rotation` or an emergency stop.
+            *self.active_jog_mut() = None;
+            return Ok(false);
+        }
+        if now_ms.wrapping_sub(jog.last_keepalive_ms) < jog.timeout_ms {
+            return Ok(false);
+        }
+        self.stop_jog()?;
+        Ok(true)
+    }
+
+    /// End the jog and stop the motor.
+    pub fn stop_jog(&mut self) -> Result<(), TmcError> {
+        *self.active_jog_mut() = None;
+        self.stop_rotation()
+    }
+
+    /// `true` while a jog is running.
+    pub fn is_jogging(&self) -> bool {
+        self.active_jog().is_some() && self.vactual() != 0
+    }
+}
@@ -0,0 +1,186 @@
+//! Worst-case UART latency, checked once and carried in the type.
+//!
+//! Every register access blocks until its bytes have been sent and the reply
+//! has arrived or timed out. How long that can take follows from the baud
+//! rate, the per-byte read timeout, echo handling and the retry count. For
+//! hard-real-time code, [`BoundedRead`] and [`BoundedWrite`] borrow the driver
+//! after checking that bound against a budget given as a const parameter, so a
+//! function taking `BoundedRead<'_, D, 200>` documents in its signature that
+//! no read through it blocks for more than 200 µs. The driver settings cannot
+//! change while the borrow lasts.
+//!
+//! The bound rests on [`LatencyModel::poll_ns`], the time one `read_ready()`
+//! or `read()` call on the serial port takes at most; measure it on target.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::{Crc8Provider, READ_REPLY_LEN};
+use crate::tmc2209::{Tmc2209FullUartDiagnosticsAndControl, MAX_DISCARD_BYTES, MAX_RESYNC_SKIP};
+
+/// Length of a read request datagram.
+const READ_REQUEST_LEN: u64 = 4;
+/// Length of a write datagram.
+const WRITE_LEN: u64 = 8;
+/// UART bits per byte: start, 8 data, stop.
+const BITS_PER_BYTE: u64 = 10;
+
+/// Timing of the serial port, for worst-case latency bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyModel {
+    /// UART baud rate
+    pub baud: u32,
+    /// Longest duration of one `read_ready()` or `read()` call, in ns
+    pub poll_ns: u32,
+}
+
+impl LatencyModel {
+    /// Time to transmit `bytes` bytes, in ns.
+    fn tx_ns(&self, bytes: u64) -> u64 {
+        bytes * BITS_PER_BYTE * 1_000_000_000 / self.baud.max(1) as u64
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Longest time a blocking register read can take with the current
+    /// timeout, echo and retry settings, in ns.
+    pub fn worst_case_read_ns(&self, model: &LatencyModel) -> u64 {
+        let byte_ns = self.read_timeout() as u64 * model.poll_ns as u64;
+        let echo = if self.echo_handling() {
+            READ_REQUEST_LEN
+        } else {
+            0
+        };
+        let received = echo + (MAX_RESYNC_SKIP + READ_REPLY_LEN) as u64;
+        // RX is drained before the request and again after a failed reply.
+        let drain_ns = 2 * MAX_DISCARD_BYTES as u64 * 2 * model.poll_ns as u64;
+        let attempt_ns = model.tx_ns(READ_REQUEST_LEN) + received * byte_ns + drain_ns;
+        attempt_ns * (self.retries() as u64 + 1)
+    }
+
+    /// Longest time a register write can take with the current echo setting,
+    /// in ns. Writes are not retried.
+    pub fn worst_case_write_ns(&self, model: &LatencyModel) -> u64 {
+        let echo_ns = if self.echo_handling() {
+            WRITE_LEN * self.read_timeout() as u64 * model.poll_ns as u64
+        } else {
+            0
+        };
+        model.tx_ns(WRITE_LEN) + echo_ns
+    }
+
+    /// Borrow the driver for reads guaranteed to finish within `MAX_US`
+    /// microseconds. Returns [`TmcError::InvalidArgument`] if the current
+    /// settings cannot guarantee that; lower the read timeout or retries.
+    pub fn bounded_read<const MAX_US: u32>(
+        &mut self,
+        model: &LatencyModel,
+    ) -> Result<BoundedRead<'_, Self, MAX_US>, TmcError> {
+        let worst_case_ns = self.worst_case_read_ns(model);
+        if worst_case_ns > MAX_US as u64 * 1_000 {
+            return Err(TmcError::InvalidArgument);
+        }
+        Ok(BoundedRead {
+            driver: self,
+            worst_case_ns,
+        })
+    }
+
+    /// Borrow the driver for writes guaranteed to finish within `MAX_US`
+    /// microseconds. Returns [`TmcError::InvalidArgument`] if the current
+    /// settings cannot guarantee that.
+    pub fn bounded_write<const MAX_US: u32>(
+        &mut self,
+        model: &LatencyModel,
+    ) -> Result<BoundedWrite<'_, Self, MAX_US>, TmcError> {
+        let worst_case_ns = self.worst_case_write_ns(model);
+        if worst_case_ns > MAX_US as u64 * 1_000 {
+            return Err(TmcError::InvalidArgument);
+        }
+        Ok(BoundedWrite {
+            driver: self,
+            worst_case_ns,
+        })
+    }
+}
+
+/// Register reads that block for at most `MAX_US` microseconds, see
+/// `bounded_read`.
+pub struct BoundedRead<'a, D, const MAX_US: u32> {
+    driver: &'a mut D,
+    worst_case_ns: u64,
+}
+
+impl<D, const MAX_US: u32> BoundedRead<'_, D, MAX_US> {
+    /// Latency budget guaranteed by this type, in µs.
+    pub const MAX_US: u32 = MAX_US;
+
+    /// Computed worst case, at most [`Self::MAX_US`], in ns.
+    pub fn worst_case_ns(&self) -> u64 {
+        self.worst_case_ns
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC, const MAX_US: u32>
+    BoundedRead<
+        '_,
+        Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>,
+        MAX_US,
+    >
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Read a register within the latency budget.
+    pub fn read(&mut self, reg: u8) -> Result<u32, TmcError> {
+        self.driver.read_register_blocking(reg)
+    }
+}
+
+/// Register writes that block for at most `MAX_US` microseconds, see
+/// `bounded_write`.
+pub struct BoundedWrite<'a, D, const MAX_US: u32> {
+    driver: &'a mut D,
+    worst_case_ns: u64,
+}
+
+impl<D, const MAX_US: u32> BoundedWrite<'_, D, MAX_US> {
+    /// Latency budget guaranteed by this type, in µs.
+    pub const MAX_US: u32 = MAX_US;
+
+    /// Computed worst case, at most [`Self::MAX_US`], in ns.
+    pub fn worst_case_ns(&self) -> u64 {
+        self.worst_case_ns
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC, const MAX_US: u32>
+    BoundedWrite<
+        '_,
+        Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>,
+        MAX_US,
+    >
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Write a register within the latency budget.
+    pub fn write(&mut self, reg: u8, value: u32) -> Result<(), TmcError> {
+        self.driver.write_register(reg, value)
+    }
+}
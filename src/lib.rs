@@ -15,21 +15,238 @@
 //! - Control step/dir pins
 //! - Configurable microstepping, current, stealthChop, etc.
 //!
+//! # Cargo features
+//! - `uart` (default): the Full UART driver and everything that needs register
+//!   access. Disable it for step/dir-only firmware to drop the `embedded-io`
+//!   dependency. The `protocol` module (datagrams, CRC, register metadata)
+//!   stays available without it, for host tools that only speak the protocol.
+//! - `machine`: the `machine` module with millimetre-based `Axis`
+//!   objects. Implies `uart`.
+//! - `critical-section`: `SharedSerial`, for sharing one UART between
+//!   interrupt and thread context without long interrupt latencies.
+//! - `test-support`: `MockTmc2209`, a simulated chip with fault injection,
+//!   and the `test_support` module with datagram generators and property
+//!   checks for host-side tests. Implies `uart`.
+//! - `async`: `home_async`, `probe_towards_async`,
+//!   `autotune_stealthchop_async` and `run_profile_async`, which wait on an
+//!   `embedded-hal-async` delay so executors such as Embassy can run other
+//!   tasks between steps. Implies `uart`.
+//! - `std`: the `host` module, with a `std::io` serial adapter and helpers
+//!   to scan, dump, configure and read OTP from a PC, for companion tools.
+//!   Implies `uart`.
+//! - `remote`: the `remote` module, a small framed command set (move, home,
+//!   set current, query status) for driving an axis from a host over a
+//!   second link. Encoding and decoding work without `uart`; executing
+//!   commands on the driver needs it.
+//!
+
+#[cfg(feature = "std")]
+extern crate std;
 
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "uart")]
+mod audit;
+#[cfg(feature = "uart")]
+mod autotune;
+mod batch;
+#[cfg(feature = "uart")]
+mod boost;
+#[cfg(feature = "uart")]
+mod bus;
 mod config;
+mod const_validate;
+#[cfg(feature = "uart")]
+mod coolstep;
+#[cfg(feature = "uart")]
+mod current;
+#[cfg(feature = "uart")]
+mod debug_step;
+#[cfg(feature = "uart")]
+mod dissipation;
+mod erased;
+mod error_code;
 mod errors;
-mod packet;
-mod registers;
+mod events;
+mod fields;
+#[cfg(feature = "uart")]
+mod fixed_addr;
+#[cfg(feature = "uart")]
+mod handoff;
+#[cfg(feature = "uart")]
+mod history;
+#[cfg(feature = "uart")]
+mod homing;
+#[cfg(feature = "std")]
+pub mod host;
+#[cfg(feature = "uart")]
+mod hybrid;
+#[cfg(feature = "uart")]
+mod index_counter;
+#[cfg(feature = "uart")]
+mod jog;
+#[cfg(feature = "uart")]
+mod latency;
+#[cfg(feature = "machine")]
+pub mod machine;
+#[cfg(feature = "uart")]
+mod microsteps;
+#[cfg(feature = "test-support")]
+mod mock;
+#[cfg(feature = "uart")]
+mod motion;
+#[cfg(feature = "uart")]
+mod odometer;
+#[cfg(feature = "uart")]
+mod persist;
+#[cfg(feature = "uart")]
+mod phase;
+mod pins;
+mod planner;
+#[cfg(feature = "uart")]
+mod probe;
+pub mod protocol;
+mod pwm_step;
+mod ramp;
+pub mod registers;
+mod regmap;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "uart")]
+mod safe_mode;
+#[cfg(feature = "uart")]
+mod self_test;
+#[cfg(feature = "uart")]
+mod service;
+#[cfg(feature = "uart")]
+mod settings;
+#[cfg(feature = "uart")]
+mod shadow;
+#[cfg(feature = "uart")]
+mod shared_position;
+#[cfg(feature = "uart")]
+mod snapshot;
+#[cfg(feature = "uart")]
+mod split;
+mod stall;
+mod state;
+#[cfg(feature = "uart")]
+mod sweep;
+#[cfg(feature = "uart")]
+mod telemetry;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+#[cfg(feature = "uart")]
+mod thermal;
 mod tmc2209;
+#[cfg(feature = "uart")]
+mod transport;
+mod values;
+#[cfg(feature = "uart")]
+mod watcher;
 
+#[cfg(feature = "uart")]
+pub use audit::{ConfigAudit, ConfigWarning, TMC2209_MAX_RMS_MA};
+#[cfg(feature = "uart")]
+pub use autotune::{AutotuneConfig, AutotuneResult};
+pub use batch::StepBatch;
+#[cfg(feature = "uart")]
+pub use boost::{BoostEvent, StallBoost, StallBoostConfig};
+#[cfg(feature = "uart")]
+pub use bus::{BusDriver, BusResults, Tmc2209Bus, BUS_ADDRESSES, ENABLE_SETTLE_US};
 pub use config::*;
+#[cfg(feature = "uart")]
+pub use coolstep::CurrentHistogram;
+#[cfg(feature = "uart")]
+pub use current::{DeratingPoint, RsenseOverload};
+#[cfg(feature = "uart")]
+pub use debug_step::StepCapture;
+#[cfg(feature = "uart")]
+pub use dissipation::{
+    DissipationConfig, DissipationEstimator, DissipationInputs, DissipationWarning, OTPW_C,
+};
+pub use erased::ErasedTmc2209;
+pub use error_code::{ErrorCodeDef, ErrorModule, TmcErrorCode, NO_REGISTER, TMC_ERROR_CODES};
 pub use errors::*;
+pub use events::{EventQueue, TmcEvent};
+pub use fields::*;
+#[cfg(feature = "uart")]
+pub use fixed_addr::Tmc2209FullUartAt;
+#[cfg(feature = "uart")]
+pub use history::{DiagnosticsHistory, DiagnosticsSnapshot, DEFAULT_HISTORY_LEN};
+#[cfg(feature = "uart")]
+pub use homing::{HomingConfig, HomingResult};
+#[cfg(feature = "uart")]
+pub use hybrid::{ChopperMode, HYBRID_FULL_STEPS_PER_REV};
+#[cfg(feature = "uart")]
+pub use index_counter::IndexCounter;
+#[cfg(feature = "uart")]
+pub use latency::{BoundedRead, BoundedWrite, LatencyModel};
+#[cfg(feature = "test-support")]
+pub use mock::{Fault, MockTmc2209, MOCK_FAULT_QUEUE_LEN};
+#[cfg(feature = "uart")]
+pub use motion::{MotionPhase, MotionProgress, MoveCurrent, StepsRemaining};
+#[cfg(feature = "uart")]
+pub use odometer::Odometer;
+#[cfg(feature = "uart")]
+pub use persist::{STATE_BLOB_LEN, STATE_BLOB_VERSION};
+#[cfg(feature = "uart")]
+pub use phase::ParkRecord;
+pub use pins::{NoPin, Tmc2209Pins};
+pub use planner::{PlannedSegment, Planner, Segment};
+#[cfg(feature = "uart")]
+pub use probe::{ProbeConfig, ProbeContact};
+pub use protocol::{
+    address_byte, build_read_packet, build_read_packet_with, build_write_packet,
+    build_write_packet_with, calc_crc8, Crc8Provider, SoftwareCrc8, TableCrc8,
+};
+pub use pwm_step::{StepFrequency, Tmc2209PwmStep};
+pub use ramp::{ConstantRate, RampConfig, StepDelayNs, TrapezoidRamp};
+pub use regmap::{
+    dump_registers, lookup_register, Access, FieldDef, RegisterDef, RegisterValue,
+    TMC2209_REGISTERS,
+};
+#[cfg(feature = "uart")]
+pub use safe_mode::SafeModeConfig;
+#[cfg(feature = "uart")]
+pub use self_test::{SelfTestReport, StageResult};
+#[cfg(feature = "uart")]
+pub use service::{
+    MachineService, MachineTickReport, RegisterPoll, ServiceConfig, TickReport, Tmc2209Service,
+};
+#[cfg(feature = "uart")]
+pub use settings::EffectiveSettings;
+#[cfg(feature = "uart")]
+pub use shared_position::SharedPosition;
+#[cfg(feature = "uart")]
+pub use snapshot::{Tmc2209Snapshot, SNAPSHOT_BLOB_LEN, SNAPSHOT_BLOB_VERSION, SNAPSHOT_REGISTERS};
+#[cfg(feature = "uart")]
+pub use split::{MotionHandle, StepTiming, UartHandle};
+pub use stall::{DiagEvent, DiagOutput, SgTemperatureCurve, StallDetector};
+pub use state::{DriverState, FaultCause, FaultKind, LatchedFault};
+#[cfg(feature = "uart")]
+pub use sweep::SweepPoint;
+#[cfg(feature = "uart")]
+pub use telemetry::{Telemetry, TelemetrySample};
+#[cfg(feature = "uart")]
+pub use thermal::{ThermalConfig, ThermalManager, ThermalState};
+#[cfg(feature = "uart")]
 pub use tmc2209::Tmc2209FullUartDiagnosticsAndControl;
 pub use tmc2209::Tmc2209StandaloneLegacy;
 pub use tmc2209::Tmc2209StandaloneOtpPreconfig;
+#[cfg(feature = "uart")]
+pub use transport::{BusTap, HalfDuplex, HalfDuplexError, Tapped};
+#[cfg(all(feature = "uart", feature = "critical-section"))]
+pub use transport::{SharedSerial, TxIdle};
+pub use values::{Ihold, IholdDelay, Irun, Percent, Semax, Semin, Sgthrs, Toff};
+#[cfg(feature = "uart")]
+pub use watcher::{FieldChange, RegisterChange, RegisterWatcher};
 
 pub mod prelude {
+    pub use crate::ErasedTmc2209;
+    #[cfg(feature = "uart")]
     pub use crate::Tmc2209FullUartDiagnosticsAndControl;
+    pub use crate::Tmc2209PwmStep;
     pub use crate::Tmc2209StandaloneLegacy;
     pub use crate::Tmc2209StandaloneOtpPreconfig;
 }
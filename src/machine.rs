@@ -0,0 +1,239 @@
+//! Axis-level facade for hobby CNC machines and printers.
+//!
+//! An [`Axis`] bundles a Full UART driver with its mechanical units, soft
+//! limits and homing parameters, so applications can think in millimetres and
+//! feed rates instead of steps:
+//!
+//! ```ignore
+//! let mut x = Axis::new(driver, AxisConfig { steps_per_mm: 80.0, ..Default::default() })?;
+//! x.home(&mut delay)?;
+//! x.goto_mm(120.0, 3_000.0, &mut delay)?; // G1 X120 F3000
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::homing::{HomingConfig, HomingResult};
+use crate::motion::StepsRemaining;
+use crate::protocol::Crc8Provider;
+use crate::ramp::RampConfig;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Mechanical description of one axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisConfig {
+    /// Microsteps per millimetre of travel; negative to invert the axis
+    pub steps_per_mm: f32,
+    /// Lowest reachable position, in mm
+    pub min_mm: f32,
+    /// Highest reachable position, in mm
+    pub max_mm: f32,
+    /// Position of the end stop found by homing, in mm
+    pub home_mm: f32,
+    /// Acceleration and deceleration, in mm/s²
+    pub acceleration: f32,
+    /// Feed rate at which moves start and end, in mm/min
+    pub start_feed: f32,
+    /// Sensorless homing parameters, in steps
+    pub homing: HomingConfig,
+}
+
+impl Default for AxisConfig {
+    fn default() -> Self {
+        AxisConfig {
+            steps_per_mm: 80.0,
+            min_mm: 0.0,
+            max_mm: 200.0,
+            home_mm: 0.0,
+            acceleration: 500.0,
+            start_feed: 300.0,
+            homing: HomingConfig::default(),
+        }
+    }
+}
+
+impl AxisConfig {
+    /// Step count for a position in mm, rounded to the nearest step.
+    pub fn mm_to_steps(&self, mm: f32) -> i32 {
+        let steps = mm * self.steps_per_mm;
+        (if steps < 0.0 {
+            steps - 0.5
+        } else {
+            steps + 0.5
+        }) as i32
+    }
+
+    /// Position in mm for a step count.
+    pub fn steps_to_mm(&self, steps: i32) -> f32 {
+        steps as f32 / self.steps_per_mm
+    }
+
+    /// Step rate for a feed in mm/min, at least 1 step/s.
+    fn feed_to_speed(&self, feed_mm_min: f32) -> u32 {
+        let speed = feed_mm_min * abs(self.steps_per_mm) / 60.0;
+        if speed < 1.0 {
+            1
+        } else {
+            speed as u32
+        }
+    }
+
+    fn ramp(&self, feed_mm_min: f32) -> RampConfig {
+        let max_speed = self.feed_to_speed(feed_mm_min);
+        RampConfig {
+            start_speed: self.feed_to_speed(self.start_feed).min(max_speed),
+            max_speed,
+            acceleration: self.feed_to_speed(self.acceleration * 60.0),
+        }
+    }
+}
+
+/// `false` for zero, negative and NaN values.
+fn is_positive(x: f32) -> bool {
+    x > 0.0
+}
+
+fn abs(x: f32) -> f32 {
+    if x < 0.0 {
+        -x
+    } else {
+        x
+    }
+}
+
+/// One machine axis driven by a TMC2209, see the [module docs](self).
+pub struct Axis<DRV> {
+    driver: DRV,
+    config: AxisConfig,
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Axis<Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Wrap a configured driver. Returns [`TmcError::InvalidArgument`] for a
+    /// zero or non-finite scale, empty travel range or non-positive
+    /// acceleration.
+    pub fn new(
+        driver: Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>,
+        config: AxisConfig,
+    ) -> Result<Self, TmcError> {
+        let scale_ok = config.steps_per_mm.is_finite() && config.steps_per_mm != 0.0;
+        let range_ok = config.min_mm < config.max_mm;
+        let accel_ok = config.acceleration > 0.0;
+        if !scale_ok
+            || !range_ok
+            || !accel_ok
+            || !(config.min_mm..=config.max_mm).contains(&config.home_mm)
+        {
+            return Err(TmcError::InvalidArgument);
+        }
+        Ok(Axis { driver, config })
+    }
+
+    /// Axis parameters.
+    pub fn config(&self) -> &AxisConfig {
+        &self.config
+    }
+
+    /// The underlying driver.
+    pub fn driver(
+        &mut self,
+    ) -> &mut Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC> {
+        &mut self.driver
+    }
+
+    /// Give the driver back.
+    pub fn into_inner(
+        self,
+    ) -> Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC> {
+        self.driver
+    }
+
+    /// Current position, in mm.
+    pub fn position_mm(&self) -> f32 {
+        self.config.steps_to_mm(self.driver.position())
+    }
+
+    /// `true` once the axis has been homed, so positions are meaningful.
+    pub fn is_homed(&self) -> bool {
+        self.driver.is_homed()
+    }
+
+    /// Home against the end stop and set the position to `home_mm`.
+    pub fn home<D: DelayNs>(&mut self, delay: &mut D) -> Result<HomingResult, TmcError> {
+        let result = self.driver.home(&self.config.homing, delay)?;
+        let home = self.config.mm_to_steps(self.config.home_mm);
+        self.driver.set_position(home);
+        Ok(result)
+    }
+
+    /// Move to `x` mm at `feed` mm/min, blocking until done (`G1 X.. F..`).
+    ///
+    /// Refused with [`TmcError::InvalidState`] before homing and with
+    /// [`TmcError::InvalidArgument`] for targets outside the soft limits or a
+    /// non-positive feed.
+    pub fn goto_mm<D: DelayNs>(
+        &mut self,
+        x: f32,
+        feed: f32,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        self.check_homed()?;
+        if !(self.config.min_mm..=self.config.max_mm).contains(&x) || !is_positive(feed) {
+            return Err(TmcError::InvalidArgument);
+        }
+        let target = self.config.mm_to_steps(x);
+        let ramp = self.config.ramp(feed);
+        self.driver.move_to(target, &ramp, delay)
+    }
+
+    /// Start moving towards the soft limit in direction `positive` at `feed`
+    /// mm/min. Keep calling [`Self::run`] to make progress and [`Self::stop`]
+    /// when the jog button is released; the axis stops by itself at the limit.
+    /// Returns [`TmcError::InvalidArgument`] if the limit is more than
+    /// `i32::MAX` steps from the current position.
+    pub fn jog(&mut self, positive: bool, feed: f32) -> Result<(), TmcError> {
+        self.check_homed()?;
+        if !is_positive(feed) {
+            return Err(TmcError::InvalidArgument);
+        }
+        let limit = if positive {
+            self.config.max_mm
+        } else {
+            self.config.min_mm
+        };
+        let steps = self.driver.steps_to(self.config.mm_to_steps(limit))?;
+        let ramp = self.config.ramp(feed);
+        self.driver.start_move(steps, &ramp)
+    }
+
+    /// Advance a jog for at most `budget_us` microseconds.
+    pub fn run<D: DelayNs>(
+        &mut self,
+        budget_us: u32,
+        delay: &mut D,
+    ) -> Result<StepsRemaining, TmcError> {
+        self.driver.step_some(budget_us, delay)
+    }
+
+    /// Stop a jog at once, without deceleration.
+    pub fn stop(&mut self) {
+        self.driver.cancel_move();
+    }
+
+    fn check_homed(&self) -> Result<(), TmcError> {
+        if self.driver.is_homed() {
+            Ok(())
+        } else {
+            Err(TmcError::InvalidState(self.driver.state()))
+        }
+    }
+}
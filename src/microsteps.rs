@@ -0,0 +1,83 @@
+//! Microstep resolution changes that keep the physical speed.
+//!
+//! VACTUAL, step rates and the position counter are all in microsteps, so
+//! changing MRES alone makes a running motor jump to twice or half its speed.
+//! `set_microsteps` rescales everything in flight along with it.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::fields::{encode_mres, encode_vactual, VACTUAL_MAX};
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Microsteps per full step in effect: from CHOPCONF.mres if
+    /// GCONF.mstep_reg_select is set, otherwise from the MS1/MS2 pins.
+    pub fn microsteps(&mut self) -> Result<u16, TmcError> {
+        let gconf = self.read_register_blocking(REG_GCONF)?;
+        if gconf & GCONF_MSTEP_REG_SELECT != 0 {
+            let chopconf = self.read_register_blocking(REG_CHOPCONF)?;
+            let mres = (chopconf & CHOPCONF_MRES_MASK) >> CHOPCONF_MRES_SHIFT;
+            return Ok(256 >> mres.min(8));
+        }
+        let ioin = self.read_register_blocking(REG_IOIN)?;
+        Ok(match (ioin & IOIN_MS2 != 0, ioin & IOIN_MS1 != 0) {
+            (false, false) => 8,
+            (false, true) => 32,
+            (true, false) => 64,
+            (true, true) => 16,
+        })
+    }
+
+    /// Set the microstep resolution (1, 2, 4, ..., 256 per full step) via
+    /// CHOPCONF.mres, taking it over from the MS1/MS2 pins.
+    ///
+    /// Whatever is in motion keeps its physical speed: VACTUAL and a move
+    /// started with `start_move` are rescaled to the new resolution, and so is
+    /// the position counter. VACTUAL is rewritten right after MRES, so the
+    /// speed is off only for the length of one datagram. When coarsening, a
+    /// position or remaining move that is not a whole number of new steps is
+    /// rounded towards zero.
+    ///
+    /// Returns [`TmcError::InvalidArgument`] for other values and
+    /// [`TmcError::RateTooHigh`] if the rescaled VACTUAL would not fit, both
+    /// before anything is written.
+    pub fn set_microsteps(&mut self, microsteps: u16) -> Result<(), TmcError> {
+        let mres = encode_mres(microsteps)?;
+        let old = self.microsteps()? as u32;
+        let new = microsteps as u32;
+        let vactual = self.vactual() as i64 * new as i64 / old as i64;
+        if vactual.unsigned_abs() > VACTUAL_MAX as u64 {
+            return Err(TmcError::RateTooHigh);
+        }
+        let vactual = vactual as i32;
+
+        let gconf = self.read_register_blocking(REG_GCONF)?;
+        if gconf & GCONF_MSTEP_REG_SELECT == 0 {
+            self.write_register(REG_GCONF, gconf | GCONF_MSTEP_REG_SELECT)?;
+        }
+        let chopconf = self.read_register_blocking(REG_CHOPCONF)?;
+        self.write_register(
+            REG_CHOPCONF,
+            (chopconf & !CHOPCONF_MRES_MASK) | (mres << CHOPCONF_MRES_SHIFT),
+        )?;
+        if vactual != self.vactual() {
+            self.write_register(REG_VACTUAL, encode_vactual(vactual))?;
+        }
+        if new != old {
+            self.rescale_microsteps(vactual, new, old);
+        }
+        Ok(())
+    }
+}
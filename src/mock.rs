@@ -0,0 +1,268 @@
+//! Simulated TMC2209 on the far side of a UART, for tests.
+//!
+//! [`MockTmc2209`] implements the same `embedded-io` traits as a serial port
+//! and answers datagrams from a register file, so the Full UART driver can be
+//! exercised on the host without hardware. Faults queued with
+//! [`MockTmc2209::inject`] corrupt the following replies in the ways a noisy
+//! line does, to test retry, resync and quarantine handling:
+//!
+//! ```ignore
+//! let mut chip = MockTmc2209::new(0);
+//! chip.inject(Fault::CorruptCrc)?;
+//! let mut driver = Tmc2209FullUartDiagnosticsAndControl::new(pin, pin, pin, chip, 0);
+//! driver.set_retries(1);
+//! driver.read_register(REG_IOIN)?; // first reply fails its CRC, the retry succeeds
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::fields::TMC2209_VERSION;
+use crate::protocol::{address_byte, calc_crc8, is_sync_byte, READ_REQUEST_LEN, WRITE_REQUEST_LEN};
+use crate::registers::*;
+use crate::test_support::reply_frame;
+
+/// Capacity of the fault queue.
+pub const MOCK_FAULT_QUEUE_LEN: usize = 8;
+
+/// Receive buffer size: echo of a write plus a reply, with room to spare.
+const RX_LEN: usize = 32;
+
+/// A failure applied to one reply of the mock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Leave out byte `n` (0-based) of the reply
+    DropByte(usize),
+    /// Send the reply with a wrong CRC byte
+    CorruptCrc,
+    /// Report no data for this many `read_ready` polls before the reply
+    DelayReply(u32),
+    /// Answer with the node's own address instead of the master's
+    WrongAddress,
+    /// Answer with the next register's address
+    WrongRegister,
+    /// Do not answer at all
+    NoReply,
+}
+
+/// Simulated TMC2209 node answering datagrams from a register file.
+///
+/// Registers hold whatever was last written, except for IFCNT, which counts
+/// accepted writes, and GSTAT, which is write-1-to-clear. Datagrams for other
+/// nodes or with a bad CRC are ignored, like on the chip.
+pub struct MockTmc2209 {
+    node: u8,
+    regs: [u32; 128],
+    request: [u8; WRITE_REQUEST_LEN],
+    request_len: usize,
+    rx: [u8; RX_LEN],
+    rx_head: usize,
+    rx_len: usize,
+    delay_polls: u32,
+    echo: bool,
+    faults: [Option<Fault>; MOCK_FAULT_QUEUE_LEN],
+    faults_applied: u32,
+}
+
+impl MockTmc2209 {
+    /// A freshly powered-up chip at node address `node`: GSTAT.reset set and
+    /// IOIN reporting the TMC2209 version.
+    pub fn new(node: u8) -> Self {
+        let mut regs = [0u32; 128];
+        regs[REG_GSTAT as usize] = GSTAT_RESET;
+        regs[REG_IOIN as usize] = (TMC2209_VERSION as u32) << 24;
+        MockTmc2209 {
+            node: node & 0x03,
+            regs,
+            request: [0; WRITE_REQUEST_LEN],
+            request_len: 0,
+            rx: [0; RX_LEN],
+            rx_head: 0,
+            rx_len: 0,
+            delay_polls: 0,
+            echo: false,
+            faults: [None; MOCK_FAULT_QUEUE_LEN],
+            faults_applied: 0,
+        }
+    }
+
+    /// Echo every received byte back, like a single-wire connection does.
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Current value of `reg`.
+    pub fn register(&self, reg: u8) -> u32 {
+        self.regs[(reg & 0x7F) as usize]
+    }
+
+    /// Overwrite `reg`, e.g. to simulate a status flag or StallGuard reading.
+    pub fn set_register(&mut self, reg: u8, value: u32) {
+        self.regs[(reg & 0x7F) as usize] = value;
+    }
+
+    /// Apply `fault` to the next reply that has no fault queued yet. Returns
+    /// [`TmcError::QueueFull`] if [`MOCK_FAULT_QUEUE_LEN`] faults are pending.
+    pub fn inject(&mut self, fault: Fault) -> Result<(), TmcError> {
+        let slot = self
+            .faults
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(TmcError::QueueFull)?;
+        *slot = Some(fault);
+        Ok(())
+    }
+
+    /// Faults still waiting for a reply.
+    pub fn pending_faults(&self) -> usize {
+        self.faults.iter().flatten().count()
+    }
+
+    /// Faults applied so far.
+    pub fn faults_applied(&self) -> u32 {
+        self.faults_applied
+    }
+
+    /// Take the fault for the next reply, if any.
+    fn next_fault(&mut self) -> Option<Fault> {
+        let fault = self.faults[0].take();
+        self.faults.rotate_left(1);
+        if fault.is_some() {
+            self.faults_applied += 1;
+        }
+        fault
+    }
+
+    fn push_rx(&mut self, byte: u8) {
+        if self.rx_len < RX_LEN {
+            self.rx[(self.rx_head + self.rx_len) % RX_LEN] = byte;
+            self.rx_len += 1;
+        }
+    }
+
+    fn pop_rx(&mut self) -> Option<u8> {
+        if self.rx_len == 0 {
+            return None;
+        }
+        let byte = self.rx[self.rx_head];
+        self.rx_head = (self.rx_head + 1) % RX_LEN;
+        self.rx_len -= 1;
+        Some(byte)
+    }
+
+    /// Collect one datagram byte and act on complete datagrams.
+    fn receive(&mut self, byte: u8) {
+        if self.echo {
+            self.push_rx(byte);
+        }
+        if self.request_len == 0 && !is_sync_byte(byte) {
+            return;
+        }
+        self.request[self.request_len] = byte;
+        self.request_len += 1;
+
+        let is_read = self.request_len >= 3 && self.request[2] & 0x80 == 0;
+        let expected = if is_read {
+            READ_REQUEST_LEN
+        } else {
+            WRITE_REQUEST_LEN
+        };
+        if self.request_len < expected {
+            return;
+        }
+        self.request_len = 0;
+
+        let request = self.request;
+        if request[1] != self.node {
+            return;
+        }
+        let reg = request[2] & 0x7F;
+        if is_read {
+            if calc_crc8(&request[..3]) == request[3] {
+                self.reply(reg);
+            }
+        } else if calc_crc8(&request[..7]) == request[7] {
+            let value = u32::from_be_bytes([request[3], request[4], request[5], request[6]]);
+            self.write(reg, value);
+        }
+    }
+
+    fn write(&mut self, reg: u8, value: u32) {
+        match reg {
+            REG_GSTAT => self.regs[reg as usize] &= !value,
+            REG_IFCNT => {}
+            _ => self.regs[reg as usize] = value,
+        }
+        let ifcnt = &mut self.regs[REG_IFCNT as usize];
+        *ifcnt = (*ifcnt + 1) & 0xFF;
+    }
+
+    fn reply(&mut self, reg: u8) {
+        let mut frame = reply_frame(reg, self.regs[reg as usize]);
+
+        let mut drop = None;
+        match self.next_fault() {
+            None => {}
+            Some(Fault::DropByte(n)) => drop = Some(n),
+            Some(Fault::CorruptCrc) => frame[7] ^= 0xFF,
+            Some(Fault::DelayReply(polls)) => self.delay_polls = polls,
+            Some(Fault::WrongAddress) => {
+                frame[1] = address_byte(self.node);
+                frame[7] = calc_crc8(&frame[..7]);
+            }
+            Some(Fault::WrongRegister) => {
+                frame[2] = (reg + 1) & 0x7F;
+                frame[7] = calc_crc8(&frame[..7]);
+            }
+            Some(Fault::NoReply) => return,
+        }
+        for (i, &byte) in frame.iter().enumerate() {
+            if drop != Some(i) {
+                self.push_rx(byte);
+            }
+        }
+    }
+}
+
+impl ErrorType for MockTmc2209 {
+    type Error = Infallible;
+}
+
+impl Write for MockTmc2209 {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        for &byte in buf {
+            self.receive(byte);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl Read for MockTmc2209 {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.pop_rx() {
+                Some(byte) => buf[n] = byte,
+                None => break,
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl ReadReady for MockTmc2209 {
+    fn read_ready(&mut self) -> Result<bool, Infallible> {
+        if self.delay_polls > 0 {
+            self.delay_polls -= 1;
+            return Ok(false);
+        }
+        Ok(self.rx_len > 0)
+    }
+}
@@ -0,0 +1,463 @@
+//! Blocking motion helpers for the Full UART driver.
+//!
+//! These consume the ramp iterators from [`crate::ramp`], issuing one step
+//! pulse per item and waiting the yielded interval with an `embedded-hal` delay.
+//!
+//! Blocking routines report their progress to the sink installed with
+//! `set_progress_sink`, so a UI can show a progress bar without keeping its
+//! own step count.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::events::TmcEvent;
+use crate::planner::PlannedSegment;
+use crate::protocol::Crc8Provider;
+use crate::ramp::{RampConfig, StepDelayNs, TrapezoidRamp};
+use crate::registers::REG_IHOLD_IRUN;
+use crate::state::DriverState;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::{Ihold, Irun};
+
+/// Steps left in the move driven by `step_some`; 0 once it has completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct StepsRemaining(pub u32);
+
+impl StepsRemaining {
+    /// `true` once every step of the move has been issued.
+    pub fn is_done(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Part of a routine a [`MotionProgress`] report belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionPhase {
+    /// A blocking move: `move_by`, `move_to`, `run_planned` or `run_profile`
+    Move,
+    /// Homing, first approach towards the end stop
+    HomingFast,
+    /// Homing, backing off from the end stop
+    HomingBackoff,
+    /// Homing, second and slow approach
+    HomingSlow,
+    /// Probing move of `probe_towards`
+    Probe,
+    /// Tuning move of `autotune_stealthchop`
+    Autotune,
+}
+
+/// Progress of a blocking routine, passed to the progress sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotionProgress {
+    /// Routine part in progress
+    pub phase: MotionPhase,
+    /// Steps issued in this phase so far
+    pub steps_done: u32,
+    /// Steps this phase will take, `None` if it runs until a stall
+    pub steps_total: Option<u32>,
+    /// Latest SG_RESULT read by the routine, if it reads StallGuard
+    pub sg_result: Option<u16>,
+}
+
+/// Progress sink and the report being built.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ProgressTracker {
+    sink: Option<fn(&MotionProgress)>,
+    every_steps: u32,
+    report: MotionProgress,
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        ProgressTracker {
+            sink: None,
+            every_steps: 1,
+            report: MotionProgress {
+                phase: MotionPhase::Move,
+                steps_done: 0,
+                steps_total: None,
+                sg_result: None,
+            },
+        }
+    }
+}
+
+impl ProgressTracker {
+    /// Start reporting a new phase.
+    pub(crate) fn begin(&mut self, phase: MotionPhase, steps_total: Option<u32>) {
+        self.report = MotionProgress {
+            phase,
+            steps_done: 0,
+            steps_total,
+            sg_result: None,
+        };
+    }
+
+    /// Remember the latest SG_RESULT for the next report.
+    pub(crate) fn record_sg(&mut self, sg_result: u16) {
+        self.report.sg_result = Some(sg_result);
+    }
+
+    /// Count a step, reporting every `every_steps` steps and at the last one.
+    #[inline]
+    pub(crate) fn step(&mut self) {
+        let Some(sink) = self.sink else {
+            return;
+        };
+        self.report.steps_done += 1;
+        let done = self.report.steps_done;
+        if done.is_multiple_of(self.every_steps) || Some(done) == self.report.steps_total {
+            sink(&self.report);
+        }
+    }
+}
+
+/// Run and hold current for a single move, e.g. a low current for probing or
+/// a high one for cutting. IHOLDDELAY is kept as configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveCurrent {
+    /// Run current while the move steps
+    pub irun: Irun,
+    /// Standstill current, reached if the move pauses long enough
+    pub ihold: Ihold,
+}
+
+impl MoveCurrent {
+    /// IHOLD_IRUN value `raw` with IHOLD and IRUN replaced by this override.
+    fn apply_to(self, raw: u32) -> u32 {
+        (raw & !0x1F1F) | self.ihold.get() as u32 | ((self.irun.get() as u32) << 8)
+    }
+}
+
+/// A move being executed piecewise by `step_some`.
+#[derive(Debug, Clone)]
+pub(crate) struct ActiveMove {
+    ramp: TrapezoidRamp,
+    /// Part of the current step interval still to wait, in ns
+    wait_ns: u32,
+}
+
+impl ActiveMove {
+    /// Convert the rest of the move to a microstep resolution `num / den`
+    /// times the current one, keeping its physical speed.
+    pub(crate) fn rescale(&mut self, num: u32, den: u32) {
+        self.ramp.rescale(num, den);
+        self.wait_ns = (self.wait_ns as u64 * den as u64 / num as u64) as u32;
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Report the progress of blocking moves, homing and probing to `sink`
+    /// every `every_steps` steps (at least 1) and at the last step of a phase
+    /// of known length. `None` removes the sink.
+    ///
+    /// The sink runs between two steps, so keep it short, e.g. store the
+    /// report for the UI task; a slow sink lowers the step rate.
+    pub fn set_progress_sink(&mut self, sink: Option<fn(&MotionProgress)>, every_steps: u32) {
+        let progress = self.progress_mut();
+        progress.sink = sink;
+        progress.every_steps = every_steps.max(1);
+    }
+
+    /// Issue one step per item of `profile`, waiting the yielded delay after each.
+    ///
+    /// Steps go in the direction last set with `set_direction`, no faster than
+    /// the kinematic limits allow. Refused with [`TmcError::InvalidState`]
+    /// while faulted or emergency-stopped.
+    pub fn run_profile<I, D>(&mut self, profile: I, delay: &mut D) -> Result<(), TmcError>
+    where
+        I: IntoIterator<Item = StepDelayNs>,
+        D: DelayNs,
+    {
+        self.ensure_can_move()?;
+        // Homing and probing run profiles as part of a larger motion.
+        let nested = self.state() == DriverState::Moving;
+        let mut profile = profile.into_iter();
+        if !nested {
+            let total = profile
+                .size_hint()
+                .1
+                .map(|n| n.min(u32::MAX as usize) as u32);
+            self.progress_mut().begin(MotionPhase::Move, total);
+        }
+        self.set_moving(true);
+        self.settle_direction(delay);
+        let result = profile.try_for_each(|StepDelayNs(ns)| self.timed_step(ns, delay));
+        if !nested {
+            self.set_moving(false);
+        }
+        result
+    }
+
+    /// Move `steps` steps (negative => counter-clockwise) along a trapezoidal ramp.
+    pub fn move_by<D: DelayNs>(
+        &mut self,
+        steps: i32,
+        ramp: &RampConfig,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        self.move_by_with_current(steps, ramp, None, delay)
+    }
+
+    /// Like [`Self::move_by`], running the move at `current` if given.
+    ///
+    /// The override is written to IHOLD_IRUN before the first step and the
+    /// previous value is restored afterwards, also if the move fails. Both
+    /// writes go through the shadow copy and are skipped when the override
+    /// matches the configured current. Refused with [`TmcError::InvalidState`]
+    /// if no current has been set yet, as there would be nothing to restore.
+    pub fn move_by_with_current<D: DelayNs>(
+        &mut self,
+        steps: i32,
+        ramp: &RampConfig,
+        current: Option<MoveCurrent>,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        if steps == 0 {
+            return Ok(());
+        }
+        let ramp = self.kinematic_limits().limit_ramp(ramp);
+        self.with_move_current(current, |drv| {
+            drv.set_direction(steps > 0)?;
+            drv.run_profile(TrapezoidRamp::new(steps.unsigned_abs(), &ramp), delay)
+        })?;
+        self.push_event(TmcEvent::MoveComplete {
+            position: self.position(),
+        });
+        Ok(())
+    }
+
+    /// Move to the absolute position `target` along a trapezoidal ramp.
+    ///
+    /// Returns [`TmcError::InvalidArgument`] if `target` is more than
+    /// `i32::MAX` steps away.
+    pub fn move_to<D: DelayNs>(
+        &mut self,
+        target: i32,
+        ramp: &RampConfig,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        self.move_to_with_current(target, ramp, None, delay)
+    }
+
+    /// Like [`Self::move_to`], running the move at `current` if given; see
+    /// [`Self::move_by_with_current`].
+    pub fn move_to_with_current<D: DelayNs>(
+        &mut self,
+        target: i32,
+        ramp: &RampConfig,
+        current: Option<MoveCurrent>,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        let steps = self.steps_to(target)?;
+        self.move_by_with_current(steps, ramp, current, delay)
+    }
+
+    /// Signed steps from the current position to `target`.
+    ///
+    /// [`TmcError::InvalidArgument`] if the distance does not fit in an `i32`,
+    /// e.g. after the position counter wrapped.
+    pub(crate) fn steps_to(&self, target: i32) -> Result<i32, TmcError> {
+        target
+            .checked_sub(self.position())
+            .ok_or(TmcError::InvalidArgument)
+    }
+
+    /// Start a move of `steps` steps (negative => counter-clockwise) that is
+    /// executed piecewise by [`Self::step_some`], replacing any move in
+    /// progress.
+    pub fn start_move(&mut self, steps: i32, ramp: &RampConfig) -> Result<(), TmcError> {
+        self.ensure_can_move()?;
+        self.put_active_move(None);
+        if steps == 0 {
+            return Ok(());
+        }
+        self.set_direction(steps > 0)?;
+        self.set_moving(true);
+        self.put_active_move(Some(ActiveMove {
+            ramp: TrapezoidRamp::new(
+                steps.unsigned_abs(),
+                &self.kinematic_limits().limit_ramp(ramp),
+            ),
+            wait_ns: 0,
+        }));
+        Ok(())
+    }
+
+    /// Advance the move started with [`Self::start_move`], blocking for at
+    /// most `budget_us` microseconds.
+    ///
+    /// Issues as many steps as fit in the budget. A step interval that does
+    /// not fit is finished by the next call, so call this again promptly (at
+    /// least once per step interval) to keep the speed accurate. Pushes
+    /// [`TmcEvent::MoveComplete`] when the last step has been issued.
+    pub fn step_some<D: DelayNs>(
+        &mut self,
+        budget_us: u32,
+        delay: &mut D,
+    ) -> Result<StepsRemaining, TmcError> {
+        let Some(mut active) = self.take_active_move() else {
+            return Ok(StepsRemaining(0));
+        };
+        if let Err(e) = self.ensure_can_move() {
+            self.put_active_move(Some(active));
+            return Err(e);
+        }
+        self.settle_direction(delay);
+
+        let mut budget_ns = budget_us as u64 * 1_000;
+        let result = loop {
+            if active.wait_ns > 0 {
+                let wait = (active.wait_ns as u64).min(budget_ns) as u32;
+                delay.delay_ns(wait);
+                active.wait_ns -= wait;
+                budget_ns -= wait as u64;
+                if active.wait_ns > 0 {
+                    break Ok(());
+                }
+            }
+            if budget_ns == 0 {
+                break Ok(());
+            }
+            let Some(StepDelayNs(period)) = active.ramp.next() else {
+                break Ok(());
+            };
+            match self.shaped_step(delay) {
+                Ok(spent) => {
+                    budget_ns = budget_ns.saturating_sub(spent as u64);
+                    active.wait_ns = self.step_gap_ns(period, spent);
+                    if active.ramp.remaining() == 0 {
+                        // No need to wait out the last interval.
+                        break Ok(());
+                    }
+                }
+                Err(e) => break Err(e),
+            }
+        };
+
+        let remaining = active.ramp.remaining();
+        if remaining == 0 {
+            self.set_moving(false);
+            self.push_event(TmcEvent::MoveComplete {
+                position: self.position(),
+            });
+        } else {
+            self.put_active_move(Some(active));
+        }
+        result.map(|()| StepsRemaining(remaining))
+    }
+
+    /// Abandon the move started with [`Self::start_move`], returning the
+    /// number of steps that were not issued.
+    pub fn cancel_move(&mut self) -> StepsRemaining {
+        match self.take_active_move() {
+            Some(active) => {
+                self.set_moving(false);
+                StepsRemaining(active.ramp.remaining())
+            }
+            None => StepsRemaining(0),
+        }
+    }
+
+    /// Hand the STEP pin over to a hardware pulse generator for a move in
+    /// direction `clockwise`, e.g. one playing a [`crate::StepBatch`].
+    ///
+    /// Sets DIR and marks the driver as moving; wait the DIR setup time before
+    /// the first pulse. Report finished steps with
+    /// [`Self::record_batch_steps`] as the hardware completes them, and call
+    /// [`Self::end_batch`] afterwards. Returns [`TmcError::Busy`] while a
+    /// `start_move` move is in progress.
+    pub fn begin_batch(&mut self, clockwise: bool) -> Result<(), TmcError> {
+        if let Some(active) = self.take_active_move() {
+            self.put_active_move(Some(active));
+            return Err(TmcError::Busy);
+        }
+        self.ensure_can_move()?;
+        self.wake()?;
+        self.set_direction(clockwise)?;
+        self.set_moving(true);
+        Ok(())
+    }
+
+    /// Count `steps` pulses issued by the hardware since the last report, in
+    /// the direction set by [`Self::begin_batch`].
+    pub fn record_batch_steps(&mut self, steps: u32) {
+        self.count_steps(steps);
+    }
+
+    /// End a batch started with [`Self::begin_batch`].
+    pub fn end_batch(&mut self) {
+        if self.state() == DriverState::Moving {
+            self.set_moving(false);
+            self.push_event(TmcEvent::MoveComplete {
+                position: self.position(),
+            });
+        }
+    }
+
+    /// Execute a segment handed out by a [`crate::Planner`].
+    pub fn run_planned<D: DelayNs>(
+        &mut self,
+        segment: &PlannedSegment,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        self.run_planned_with_current(segment, None, delay)
+    }
+
+    /// Like [`Self::run_planned`], running the segment at `current` if given;
+    /// see [`Self::move_by_with_current`].
+    pub fn run_planned_with_current<D: DelayNs>(
+        &mut self,
+        segment: &PlannedSegment,
+        current: Option<MoveCurrent>,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        if segment.steps == 0 {
+            return Ok(());
+        }
+        let segment = self.kinematic_limits().limit_segment(segment);
+        self.with_move_current(current, |drv| {
+            drv.set_direction(segment.steps > 0)?;
+            drv.run_profile(segment.ramp(), delay)
+        })?;
+        self.push_event(TmcEvent::MoveComplete {
+            position: self.position(),
+        });
+        Ok(())
+    }
+
+    /// Run `f` with IHOLD/IRUN overridden by `current`, restoring the shadowed
+    /// IHOLD_IRUN afterwards even if `f` fails.
+    fn with_move_current<T>(
+        &mut self,
+        current: Option<MoveCurrent>,
+        f: impl FnOnce(&mut Self) -> Result<T, TmcError>,
+    ) -> Result<T, TmcError> {
+        let Some(current) = current else {
+            return f(self);
+        };
+        self.ensure_can_move()?;
+        let saved = self
+            .shadow_register(REG_IHOLD_IRUN)
+            .ok_or(TmcError::InvalidState(self.state()))?;
+        self.write_register_if_changed(REG_IHOLD_IRUN, current.apply_to(saved))?;
+
+        let result = f(self);
+
+        let restored = self.write_register_if_changed(REG_IHOLD_IRUN, saved);
+        let result = result?;
+        restored?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,57 @@
+//! Usage counters for maintenance schedules.
+//!
+//! Belts, lead screws and bearings wear with distance travelled and with every
+//! reversal of direction. The [`Odometer`] counts both for the steps a driver
+//! issues, and is kept in the state blob so the totals survive power cycles.
+
+/// Steps and direction reversals issued by a driver.
+///
+/// Only steps issued through the STEP pin are counted; motion under VACTUAL is
+/// not. Steps are counted at the microstep resolution in effect when they were
+/// issued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Odometer {
+    /// Total steps in either direction
+    pub steps: u64,
+    /// Times a step went the other way than the one before
+    pub reversals: u32,
+    last_clockwise: Option<bool>,
+}
+
+impl Odometer {
+    /// Counters restored from storage.
+    pub const fn new(steps: u64, reversals: u32) -> Self {
+        Odometer {
+            steps,
+            reversals,
+            last_clockwise: None,
+        }
+    }
+
+    /// Complete revolutions for a motor taking `steps_per_rev` steps per
+    /// revolution (full steps times microsteps), 0 if that is 0.
+    pub const fn revolutions(&self, steps_per_rev: u32) -> u64 {
+        match self.steps.checked_div(steps_per_rev as u64) {
+            Some(revs) => revs,
+            None => 0,
+        }
+    }
+
+    /// Count one step in direction `clockwise`.
+    #[inline]
+    pub(crate) fn record_step(&mut self, clockwise: bool) {
+        self.steps = self.steps.wrapping_add(1);
+        if self.last_clockwise == Some(!clockwise) {
+            self.reversals = self.reversals.wrapping_add(1);
+        }
+        self.last_clockwise = Some(clockwise);
+    }
+
+    /// Count `count` steps in direction `clockwise`.
+    pub(crate) fn record_steps(&mut self, clockwise: bool, count: u32) {
+        if count > 0 {
+            self.record_step(clockwise);
+            self.steps = self.steps.wrapping_add(count as u64 - 1);
+        }
+    }
+}
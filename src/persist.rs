@@ -0,0 +1,98 @@
+//! Keeping the position across power cycles.
+//!
+//! [`Tmc2209FullUartDiagnosticsAndControl::export_state`] packs the position,
+//! a fingerprint of the register configuration, the homing status and the
+//! odometer into a small blob the application can store in EEPROM or flash.
+//! Importing it after a restart only succeeds if the blob is intact and the
+//! driver has been configured the same way again.
+//!
+//! Layout (little-endian):
+//! `[version, flags, position: i32, config: u32, steps: u64, reversals: u32, crc]`.
+//! Version 1 blobs, which end after `config` with the CRC, are still accepted
+//! and leave the odometer untouched.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::odometer::Odometer;
+use crate::protocol::{calc_crc8, Crc8Provider};
+use crate::state::DriverState;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Length of an exported state blob.
+pub const STATE_BLOB_LEN: usize = 23;
+
+/// Format version written to byte 0 of the blob.
+pub const STATE_BLOB_VERSION: u8 = 2;
+
+/// Length of a version 1 blob, without the odometer.
+const STATE_BLOB_V1_LEN: usize = 11;
+
+/// Flag bit: the position was referenced by homing.
+const FLAG_HOMED: u8 = 1 << 0;
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Pack position, configuration fingerprint and homing status into a
+    /// CRC-protected blob for non-volatile storage.
+    ///
+    /// Export while the motor is stopped; steps taken afterwards are not
+    /// reflected in the blob.
+    pub fn export_state(&self) -> [u8; STATE_BLOB_LEN] {
+        let mut blob = [0u8; STATE_BLOB_LEN];
+        blob[0] = STATE_BLOB_VERSION;
+        blob[1] = if self.is_homed() { FLAG_HOMED } else { 0 };
+        blob[2..6].copy_from_slice(&self.position().to_le_bytes());
+        blob[6..10].copy_from_slice(&self.config_fingerprint().to_le_bytes());
+        let odometer = self.odometer();
+        blob[10..18].copy_from_slice(&odometer.steps.to_le_bytes());
+        blob[18..22].copy_from_slice(&odometer.reversals.to_le_bytes());
+        blob[22] = calc_crc8(&blob[..22]);
+        blob
+    }
+
+    /// Restore position, homing status and odometer from a blob made by
+    /// [`Self::export_state`].
+    ///
+    /// Configure the driver (currents, thresholds, ...) before importing: the
+    /// blob is only accepted if the configuration matches the one it was
+    /// exported with. Returns [`TmcError::InvalidArgument`] for a blob that is
+    /// truncated, from another format version, corrupted or from a different
+    /// configuration, and [`TmcError::InvalidState`] while moving. Nothing is
+    /// changed on error.
+    pub fn import_state(&mut self, blob: &[u8]) -> Result<(), TmcError> {
+        let len = match blob.first() {
+            Some(&STATE_BLOB_VERSION) => STATE_BLOB_LEN,
+            Some(1) => STATE_BLOB_V1_LEN,
+            _ => return Err(TmcError::InvalidArgument),
+        };
+        let blob = blob.get(..len).ok_or(TmcError::InvalidArgument)?;
+        if calc_crc8(&blob[..len - 1]) != blob[len - 1] {
+            return Err(TmcError::InvalidArgument);
+        }
+        let config = u32::from_le_bytes([blob[6], blob[7], blob[8], blob[9]]);
+        if config != self.config_fingerprint() {
+            return Err(TmcError::InvalidArgument);
+        }
+        if self.state() == DriverState::Moving {
+            return Err(TmcError::InvalidState(DriverState::Moving));
+        }
+        self.set_position(i32::from_le_bytes([blob[2], blob[3], blob[4], blob[5]]));
+        self.set_homed(blob[1] & FLAG_HOMED != 0);
+        if len == STATE_BLOB_LEN {
+            let mut steps = [0u8; 8];
+            steps.copy_from_slice(&blob[10..18]);
+            let reversals = u32::from_le_bytes([blob[18], blob[19], blob[20], blob[21]]);
+            self.set_odometer(Odometer::new(u64::from_le_bytes(steps), reversals));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+//! Microstep phase handling for the Full UART driver.
+//!
+//! While the outputs are off the rotor falls to the nearest full-step detent,
+//! and a chip reset also returns MSCNT to its power-on value. Re-energising
+//! the coils at a different point of the microstep table makes the rotor jump.
+//! These routines walk the table back to a known point with ordinary steps.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::{Operation, TmcError};
+use crate::protocol::Crc8Provider;
+use crate::ramp::ConstantRate;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Step rate used while re-aligning the phase, in steps/s.
+const PHASE_ALIGN_SPEED: u32 = 1_000;
+
+/// Longest wait for DRV_STATUS.stst in `park_and_disable` and
+/// `take_over_stepping`, in ms. The chip flags standstill 2^20 clocks (about
+/// 87 ms) after the last step.
+const PARK_STANDSTILL_TIMEOUT_MS: u32 = 500;
+
+/// Interval between standstill polls, in ms.
+const PARK_POLL_INTERVAL_MS: u32 = 10;
+
+/// Where the axis was parked by `park_and_disable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParkRecord {
+    /// Position counter at power-down
+    pub position: i32,
+    /// MSCNT at power-down, restored by `restore_phase`
+    pub mscnt: u16,
+}
+
+/// Wrap an MSCNT difference into [-512, 512).
+pub(crate) fn wrap_mscnt(delta: i32) -> i32 {
+    (delta + MSCNT_TABLE_LEN / 2).rem_euclid(MSCNT_TABLE_LEN) - MSCNT_TABLE_LEN / 2
+}
+
+/// Full-step MSCNT closest to `mscnt`.
+fn nearest_full_step(mscnt: i32) -> i32 {
+    let from_offset = mscnt - MSCNT_FULL_STEP_OFFSET + MSCNT_FULL_STEP_SPAN / 2;
+    let k = from_offset.div_euclid(MSCNT_FULL_STEP_SPAN);
+    (MSCNT_FULL_STEP_OFFSET + k * MSCNT_FULL_STEP_SPAN).rem_euclid(MSCNT_TABLE_LEN)
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Step MSCNT back to the value recorded by the last `disable`.
+    ///
+    /// Call after `enable`. The steps taken only re-align the coils, so the
+    /// position counter and direction are left as they were. Returns the
+    /// number of steps issued; 0 if MSCNT already matches or nothing was
+    /// recorded.
+    pub fn restore_phase<D: DelayNs>(&mut self, delay: &mut D) -> Result<u32, TmcError> {
+        match self.take_saved_mscnt() {
+            Some(target) => self.step_to_mscnt(|_| target as i32, delay),
+            None => Ok(0),
+        }
+    }
+
+    /// Step to the nearest full-step position of the microstep table.
+    ///
+    /// Full steps sit at MSCNT 128, 384, 640 and 896, where both coils carry
+    /// equal current. Aligning there before homing or power-down makes the
+    /// rotor position reproducible across resets, e.g. for machines that keep
+    /// their position in flash. Position counter and direction are preserved.
+    /// Returns the number of steps issued.
+    pub fn align_to_full_step<D: DelayNs>(&mut self, delay: &mut D) -> Result<u32, TmcError> {
+        self.step_to_mscnt(nearest_full_step, delay)
+    }
+
+    /// Stop, wait for standstill, optionally align to a full step, record
+    /// MSCNT and position, then disable the outputs.
+    ///
+    /// With the motor at rest at a recorded phase, [`Self::restore_phase`]
+    /// after the next `enable` brings the coils back exactly where they were,
+    /// so the position counter stays valid. Aligning to a full step first
+    /// also keeps the position if the chip is reset meanwhile, since the
+    /// rotor does not move when a full-step detent is de-energised.
+    ///
+    /// Returns [`TmcError::Busy`] while a `start_move` move is in progress and
+    /// [`TmcError::MotionTimeout`] if the chip does not report standstill.
+    pub fn park_and_disable<D: DelayNs>(
+        &mut self,
+        align: bool,
+        delay: &mut D,
+    ) -> Result<ParkRecord, TmcError> {
+        if let Some(active) = self.take_active_move() {
+            self.put_active_move(Some(active));
+            return Err(TmcError::Busy);
+        }
+        if self.vactual() != 0 {
+            self.stop_rotation()?;
+        }
+        self.wait_for_standstill(delay)?;
+        if align {
+            self.align_to_full_step(delay)?;
+        }
+        let mscnt = self.read_mscnt()? as u16;
+        let position = self.position();
+        self.disable()?;
+        Ok(ParkRecord { position, mscnt })
+    }
+
+    pub(crate) fn wait_for_standstill<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        let mut waited = 0;
+        while !self.read_drv_status()?.stst {
+            self.check_cancel()?;
+            if waited >= PARK_STANDSTILL_TIMEOUT_MS {
+                return Err(TmcError::MotionTimeout);
+            }
+            delay.delay_ms(PARK_POLL_INTERVAL_MS);
+            waited += PARK_POLL_INTERVAL_MS;
+        }
+        Ok(())
+    }
+
+    /// Issue the fewest steps that bring MSCNT to `target(mscnt)`.
+    ///
+    /// The step size and its sign are measured by taking one step, so this
+    /// works for any microstep resolution and GCONF.shaft setting.
+    pub(crate) fn step_to_mscnt<D: DelayNs>(
+        &mut self,
+        target: impl Fn(i32) -> i32,
+        delay: &mut D,
+    ) -> Result<u32, TmcError> {
+        self.ensure_can_move()?;
+        let before = self.read_mscnt()?;
+        if wrap_mscnt(target(before) - before) == 0 {
+            return Ok(0);
+        }
+
+        let position = self.position();
+        let clockwise = self.direction();
+        let result = self.step_to_mscnt_inner(before, &target, delay);
+        self.set_position(position);
+        self.set_direction(clockwise)?;
+        result
+    }
+
+    fn step_to_mscnt_inner<D: DelayNs>(
+        &mut self,
+        before: i32,
+        target: &impl Fn(i32) -> i32,
+        delay: &mut D,
+    ) -> Result<u32, TmcError> {
+        let clockwise = self.direction();
+        self.run_profile(ConstantRate::new(1, PHASE_ALIGN_SPEED), delay)?;
+        let after = self.read_mscnt()?;
+        let increment = wrap_mscnt(after - before);
+        if increment == 0 {
+            // Outputs not following the STEP input.
+            return Err(TmcError::VerificationError {
+                reg: REG_MSCNT,
+                op: Operation::Verify,
+            });
+        }
+
+        let remaining = wrap_mscnt(target(after) - after) / increment;
+        if remaining != 0 {
+            self.set_direction(clockwise == (remaining > 0))?;
+            self.run_profile(
+                ConstantRate::new(remaining.unsigned_abs(), PHASE_ALIGN_SPEED),
+                delay,
+            )?;
+        }
+        Ok(1 + remaining.unsigned_abs())
+    }
+
+    fn read_mscnt(&mut self) -> Result<i32, TmcError> {
+        Ok((self.read_register(REG_MSCNT)? & MSCNT_MASK) as i32)
+    }
+}
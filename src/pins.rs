@@ -0,0 +1,91 @@
+//! Pin bundles and a placeholder for optional connections.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+/// A pin that is not connected.
+///
+/// Used as the default type of optional pins such as DIAG and INDEX. Writes
+/// are ignored and reads return low.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoPin;
+
+impl ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl InputPin for NoPin {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(false)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(true)
+    }
+}
+
+/// The GPIOs connected to a TMC2209, for the `with_pins` constructors.
+///
+/// DIAG and INDEX are optional and default to [`NoPin`]; the Full UART driver
+/// does not use them.
+#[derive(Debug)]
+pub struct Tmc2209Pins<EN, STEP, DIR, DIAG = NoPin, INDEX = NoPin> {
+    /// Enable input (active low)
+    pub en: EN,
+    /// Step input
+    pub step: STEP,
+    /// Direction input
+    pub dir: DIR,
+    /// Diagnostics output, if connected
+    pub diag: Option<DIAG>,
+    /// Index output, if connected
+    pub index: Option<INDEX>,
+}
+
+impl<EN, STEP, DIR> Tmc2209Pins<EN, STEP, DIR> {
+    /// EN, STEP and DIR only.
+    pub fn new(en: EN, step: STEP, dir: DIR) -> Self {
+        Tmc2209Pins {
+            en,
+            step,
+            dir,
+            diag: None,
+            index: None,
+        }
+    }
+}
+
+impl<EN, STEP, DIR, DIAG, INDEX> Tmc2209Pins<EN, STEP, DIR, DIAG, INDEX> {
+    /// Add a DIAG pin.
+    pub fn with_diag<D>(self, diag: D) -> Tmc2209Pins<EN, STEP, DIR, D, INDEX> {
+        Tmc2209Pins {
+            en: self.en,
+            step: self.step,
+            dir: self.dir,
+            diag: Some(diag),
+            index: self.index,
+        }
+    }
+
+    /// Add an INDEX pin.
+    pub fn with_index<I>(self, index: I) -> Tmc2209Pins<EN, STEP, DIR, DIAG, I> {
+        Tmc2209Pins {
+            en: self.en,
+            step: self.step,
+            dir: self.dir,
+            diag: self.diag,
+            index: Some(index),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Motion planner with look-ahead.
+//!
+//! Streaming many short moves through [`crate::TrapezoidRamp`] one at a time
+//! stops the axis between every move. The planner keeps a small queue of
+//! segments and chooses junction speeds so that consecutive moves in the same
+//! direction flow into each other, while still being able to stop at the end of
+//! the queue.
+
+use crate::errors::TmcError;
+use crate::ramp::{isqrt, TrapezoidRamp};
+
+/// A move queued in the planner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Segment {
+    /// Distance in steps, negative => counter-clockwise
+    pub steps: i32,
+    /// Highest speed allowed within the segment, in steps/s
+    pub max_speed: u32,
+}
+
+/// A segment with its speeds resolved by the planner, ready to execute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlannedSegment {
+    /// Distance in steps, negative => counter-clockwise
+    pub steps: i32,
+    /// Speed when entering the segment, in steps/s
+    pub entry_speed: u32,
+    /// Cruise speed, in steps/s
+    pub cruise_speed: u32,
+    /// Speed when leaving the segment, in steps/s
+    pub exit_speed: u32,
+    /// Acceleration, in steps/s²
+    pub acceleration: u32,
+}
+
+impl PlannedSegment {
+    /// Step-interval profile for this segment.
+    pub fn ramp(&self) -> TrapezoidRamp {
+        TrapezoidRamp::with_speeds(
+            self.steps.unsigned_abs(),
+            self.entry_speed,
+            self.cruise_speed,
+            self.exit_speed,
+            self.acceleration,
+        )
+    }
+}
+
+/// Look-ahead planner holding up to `N` queued segments.
+pub struct Planner<const N: usize> {
+    segments: [Segment; N],
+    entry_speeds: [u32; N],
+    head: usize,
+    len: usize,
+    start_speed: u32,
+    acceleration: u32,
+    // Exit speed of the last segment handed out; the next one must enter at it.
+    committed_speed: u32,
+}
+
+impl<const N: usize> Planner<N> {
+    /// Create a planner. `start_speed` (steps/s) is the speed the axis can start
+    /// and stop at instantly; `acceleration` is in steps/s².
+    pub fn new(start_speed: u32, acceleration: u32) -> Self {
+        Planner {
+            segments: [Segment::default(); N],
+            entry_speeds: [0; N],
+            head: 0,
+            len: 0,
+            start_speed,
+            acceleration,
+            committed_speed: start_speed,
+        }
+    }
+
+    /// Number of queued segments.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// `true` if no segments are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// `true` if no more segments can be queued.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Queue a segment and re-plan junction speeds.
+    ///
+    /// Returns [`TmcError::QueueFull`] if the queue has no room left.
+    pub fn push(&mut self, segment: Segment) -> Result<(), TmcError> {
+        if self.is_full() {
+            return Err(TmcError::QueueFull);
+        }
+        if segment.steps == 0 {
+            return Ok(());
+        }
+        let idx = (self.head + self.len) % N;
+        self.segments[idx] = segment;
+        self.len += 1;
+        self.replan();
+        Ok(())
+    }
+
+    /// Take the next segment for execution.
+    ///
+    /// Its exit speed assumes the rest of the queue follows, so keep the queue
+    /// topped up, or the axis ends the last segment at the start speed.
+    pub fn pop(&mut self) -> Option<PlannedSegment> {
+        if self.is_empty() {
+            return None;
+        }
+        let idx = self.head;
+        let segment = self.segments[idx];
+        let exit_speed = if self.len > 1 {
+            self.entry_speeds[(idx + 1) % N]
+        } else {
+            self.start_speed
+        };
+        let planned = PlannedSegment {
+            steps: segment.steps,
+            entry_speed: self.entry_speeds[idx],
+            cruise_speed: segment.max_speed,
+            exit_speed,
+            acceleration: self.acceleration,
+        };
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        self.committed_speed = exit_speed;
+        Some(planned)
+    }
+
+    /// Highest speed at which segment `prev` may hand over to segment `next`.
+    fn junction_limit(&self, prev: &Segment, next: &Segment) -> u32 {
+        if (prev.steps > 0) != (next.steps > 0) {
+            // Reversal: the axis has to stop in between.
+            self.start_speed
+        } else {
+            prev.max_speed.min(next.max_speed).max(self.start_speed)
+        }
+    }
+
+    /// Fastest speed reachable from `speed` over `steps` steps.
+    fn reachable(&self, speed: u32, steps: i32) -> u32 {
+        let v = speed as u64;
+        let reach = isqrt(v * v + 2 * self.acceleration as u64 * steps.unsigned_abs() as u64);
+        reach.min(u32::MAX as u64) as u32
+    }
+
+    /// Recompute all entry speeds with a backward and a forward pass.
+    fn replan(&mut self) {
+        if self.is_empty() {
+            return;
+        }
+        let last = self.len - 1;
+
+        // Backward pass: every segment must be able to slow down to the next
+        // entry speed, and the queue must end at the start speed.
+        let mut next_entry = self.start_speed;
+        for i in (0..=last).rev() {
+            let idx = (self.head + i) % N;
+            let segment = self.segments[idx];
+            let entry = if i == 0 {
+                self.committed_speed
+            } else {
+                let prev = self.segments[(self.head + i - 1) % N];
+                self.junction_limit(&prev, &segment)
+                    .min(self.reachable(next_entry, segment.steps))
+            };
+            self.entry_speeds[idx] = entry;
+            next_entry = entry;
+        }
+
+        // Forward pass: no segment may enter faster than the previous one can
+        // accelerate to.
+        for i in 1..=last {
+            let prev_idx = (self.head + i - 1) % N;
+            let idx = (self.head + i) % N;
+            let limit = self.reachable(self.entry_speeds[prev_idx], self.segments[prev_idx].steps);
+            self.entry_speeds[idx] = self.entry_speeds[idx].min(limit);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Stall-based touch probing for the Full UART driver.
+//!
+//! The motor itself acts as the probe: it creeps towards a target with low
+//! current and a sensitive StallGuard threshold, and the position at which it
+//! stalls is the contact point.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::motion::MotionPhase;
+use crate::protocol::Crc8Provider;
+use crate::stall::StallDetector;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::Percent;
+
+/// Parameters for a probing move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeConfig {
+    /// Probing speed, in steps/s
+    pub speed: u32,
+    /// Run current while probing, as a percentage of the configured IRUN
+    pub current_percent: Percent,
+    /// StallGuard threshold (SGTHRS); higher values trigger on lighter contact
+    pub sgthrs: u8,
+    /// Maximum duration of the probing move, in milliseconds
+    pub timeout: u32,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        ProbeConfig {
+            speed: 150,
+            current_percent: Percent::new(30),
+            sgthrs: 100,
+            timeout: 20_000,
+        }
+    }
+}
+
+/// Where a probing move made contact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeContact {
+    /// Position counter at the step where the stall was detected
+    pub position: i32,
+    /// SG_RESULT that triggered the contact
+    pub sg_result: u16,
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Move towards `target` until contact is detected through StallGuard.
+    ///
+    /// Returns `Ok(None)` if `target` was reached without contact, and
+    /// [`TmcError::InvalidArgument`] if it is more than `i32::MAX` steps away.
+    /// The run current is scaled as for homing and restored afterwards.
+    pub fn probe_towards<D: DelayNs>(
+        &mut self,
+        target: i32,
+        config: &ProbeConfig,
+        delay: &mut D,
+    ) -> Result<Option<ProbeContact>, TmcError> {
+        let steps = self.steps_to(target)?;
+        if steps == 0 {
+            return Ok(None);
+        }
+
+        self.ensure_can_move()?;
+        self.set_moving(true);
+        let result = self.with_current_percent(config.current_percent, |drv| {
+            let detector = drv.begin_probe(steps, config)?;
+            let hit = drv.step_until_stall(
+                config.speed,
+                steps.unsigned_abs(),
+                config.timeout,
+                &detector,
+                delay,
+            )?;
+            Ok(drv.probe_contact(hit))
+        });
+        self.set_moving(false);
+        result
+    }
+
+    /// Program the probing threshold and direction for a move of `steps`
+    /// and return the matching detector.
+    pub(crate) fn begin_probe(
+        &mut self,
+        steps: i32,
+        config: &ProbeConfig,
+    ) -> Result<StallDetector, TmcError> {
+        self.set_stallguard_threshold(config.sgthrs)?;
+        self.set_direction(steps > 0)?;
+        self.progress_mut()
+            .begin(MotionPhase::Probe, Some(steps.unsigned_abs()));
+        Ok(StallDetector::new(config.sgthrs))
+    }
+
+    /// Contact at the current position, if the probing move stalled.
+    pub(crate) fn probe_contact(&self, hit: Option<u16>) -> Option<ProbeContact> {
+        hit.map(|sg_result| ProbeContact {
+            position: self.position(),
+            sg_result,
+        })
+    }
+}
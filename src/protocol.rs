@@ -0,0 +1,319 @@
+//! The TMC2209 UART protocol: datagram layouts, CRC, encoding and parsing.
+//!
+//! Everything here is plain byte manipulation with no driver, pin or serial
+//! types involved, and it is available with default features disabled. Host
+//! tools such as USB-UART configurators and test jigs can build on this module
+//! alone, together with the register addresses in [`registers`] and the field
+//! descriptions in [`TMC2209_REGISTERS`].
+//!
+//! Three datagrams exist, all starting with a sync byte and an address byte
+//! (the node address, or 0xFF for the master) and ending in a CRC over the
+//! preceding bytes. Data words are sent most significant byte first:
+//!
+//! | Datagram      | Bytes                                         |
+//! |---------------|-----------------------------------------------|
+//! | write request | 0x05, node, reg \| 0x80, data3..data0, crc    |
+//! | read request  | 0x05, node, reg, crc                          |
+//! | read reply    | 0x05, 0xFF, reg, data3..data0, crc            |
+//!
+//! The items of this module are a stable API; the driver itself uses them
+//! for every transaction.
+
+pub use crate::registers;
+pub use crate::regmap::{lookup_register, Access, FieldDef, RegisterDef, TMC2209_REGISTERS};
+
+/// Length of the reply frame the TMC2209 sends back for a read request.
+///
+/// Layout: [sync, 0xFF, reg, data3, data2, data1, data0, crc]
+pub const READ_REPLY_LEN: usize = 8;
+
+/// Length of a write request, as built by [`build_write_packet`].
+pub const WRITE_REQUEST_LEN: usize = 8;
+
+/// Length of a read request, as built by [`build_read_packet`].
+pub const READ_REQUEST_LEN: usize = 4;
+
+/// Calculate the 8-bit CRC for TMC2209 packets.
+///
+/// Polynomial is x^8 + x^2 + x + 1, initial value 0. The bits of each byte
+/// are fed in LSB-first, as they appear on the wire, while the CRC register
+/// shifts left, exactly as in the datasheet's reference code.
+pub const fn calc_crc8(bytes: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let mut current = bytes[i];
+        let mut bit = 0;
+        while bit < 8 {
+            if (crc >> 7) ^ (current & 0x01) != 0 {
+                crc = (crc << 1) ^ 0x07;
+            } else {
+                crc <<= 1;
+            }
+            current >>= 1;
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// Source of the 8-bit CRC used in TMC2209 datagrams.
+///
+/// The default is the bit-wise [`SoftwareCrc8`]. Implement this to use a
+/// hardware CRC unit, or use [`TableCrc8`] to trade 256 bytes for speed.
+/// A hardware unit computing the common MSB-first CRC-8 (polynomial 0x07)
+/// matches when it is configured to reverse the bits of each input byte.
+pub trait Crc8Provider {
+    /// CRC over `bytes`, with the same result as [`calc_crc8`].
+    fn crc8(&mut self, bytes: &[u8]) -> u8;
+}
+
+/// Bit-wise software CRC, see [`calc_crc8`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SoftwareCrc8;
+
+impl Crc8Provider for SoftwareCrc8 {
+    fn crc8(&mut self, bytes: &[u8]) -> u8 {
+        calc_crc8(bytes)
+    }
+}
+
+/// Table-driven software CRC: one lookup per byte instead of eight shifts.
+#[derive(Debug, Clone)]
+pub struct TableCrc8 {
+    /// MSB-first CRC of each byte value
+    table: [u8; 256],
+}
+
+impl TableCrc8 {
+    /// Build the lookup table. Usable in `const`/`static` initializers.
+    pub const fn new() -> Self {
+        let mut table = [0u8; 256];
+        let mut i = 0;
+        while i < 256 {
+            // calc_crc8 consumes bytes LSB-first; reversing the input turns
+            // it into the plain MSB-first CRC of `i`.
+            table[i] = calc_crc8(&[(i as u8).reverse_bits()]);
+            i += 1;
+        }
+        TableCrc8 { table }
+    }
+}
+
+impl Default for TableCrc8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc8Provider for TableCrc8 {
+    fn crc8(&mut self, bytes: &[u8]) -> u8 {
+        bytes
+            .iter()
+            .fold(0u8, |crc, &b| self.table[(crc ^ b.reverse_bits()) as usize])
+    }
+}
+
+/// Low nibble of the first byte of every datagram. The upper nibble is
+/// reserved; the chip ignores it, but it is covered by the CRC.
+pub const SYNC_NIBBLE: u8 = 0x05;
+
+/// First byte of every datagram sent by this driver.
+pub const SYNC_BYTE: u8 = SYNC_NIBBLE;
+
+/// Address byte of every reply: replies are addressed to the master.
+pub const MASTER_ADDRESS: u8 = 0xFF;
+
+/// Second datagram byte for node `slave`. The TMC2209 answers to node
+/// addresses 0..=3, selected with MS1/MS2.
+pub const fn address_byte(slave: u8) -> u8 {
+    slave & 0x03
+}
+
+/// `true` if `byte` can start a datagram.
+pub const fn is_sync_byte(byte: u8) -> bool {
+    byte & 0x0F == SYNC_NIBBLE
+}
+
+/// Build an 8-byte write packet for a 32-bit register write.
+///
+/// Layout: [sync, slave, reg|0x80, data3, data2, data1, data0, crc]
+pub fn build_write_packet(slave: u8, reg_addr: u8, value: u32) -> [u8; WRITE_REQUEST_LEN] {
+    build_write_packet_with(&mut SoftwareCrc8, slave, reg_addr, value)
+}
+
+/// [`build_write_packet`] using the given CRC implementation.
+pub fn build_write_packet_with<C: Crc8Provider + ?Sized>(
+    crc: &mut C,
+    slave: u8,
+    reg_addr: u8,
+    value: u32,
+) -> [u8; WRITE_REQUEST_LEN] {
+    write_packet_for(crc, address_byte(slave), reg_addr, value)
+}
+
+/// Write packet for an address byte from [`address_byte`].
+pub(crate) fn write_packet_for<C: Crc8Provider + ?Sized>(
+    crc: &mut C,
+    adr_byte: u8,
+    reg_addr: u8,
+    value: u32,
+) -> [u8; WRITE_REQUEST_LEN] {
+    // For a write, the register's top bit (bit7) must be 1
+    let reg_byte = (reg_addr & 0x7F) | 0x80;
+
+    let mut packet = [0u8; WRITE_REQUEST_LEN];
+    packet[0] = SYNC_BYTE;
+    packet[1] = adr_byte;
+    packet[2] = reg_byte;
+    // Data is sent most significant byte first
+    packet[3..7].copy_from_slice(&value.to_be_bytes());
+    // CRC covers bytes 0..6
+    packet[7] = crc.crc8(&packet[..7]);
+    packet
+}
+
+/// Build a 4-byte read packet to request data from a TMC2209 register.
+///
+/// Layout: [sync, slave, reg, crc]
+pub fn build_read_packet(slave: u8, reg_addr: u8) -> [u8; READ_REQUEST_LEN] {
+    build_read_packet_with(&mut SoftwareCrc8, slave, reg_addr)
+}
+
+/// [`build_read_packet`] using the given CRC implementation.
+pub fn build_read_packet_with<C: Crc8Provider + ?Sized>(
+    crc: &mut C,
+    slave: u8,
+    reg_addr: u8,
+) -> [u8; READ_REQUEST_LEN] {
+    read_packet_for(crc, address_byte(slave), reg_addr)
+}
+
+/// Read packet for an address byte from [`address_byte`].
+pub(crate) fn read_packet_for<C: Crc8Provider + ?Sized>(
+    crc: &mut C,
+    adr_byte: u8,
+    reg_addr: u8,
+) -> [u8; READ_REQUEST_LEN] {
+    // For a read, bit7 = 0
+    let reg_byte = reg_addr & 0x7F;
+
+    let mut packet = [0u8; READ_REQUEST_LEN];
+    packet[0] = SYNC_BYTE;
+    packet[1] = adr_byte;
+    packet[2] = reg_byte;
+    // CRC covers bytes 0..2
+    packet[3] = crc.crc8(&packet[..3]);
+    packet
+}
+
+/// A host-to-chip datagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Datagram {
+    /// Read request
+    Read {
+        /// Node address, in [0..3]
+        slave: u8,
+        /// Register address
+        reg: u8,
+    },
+    /// Register write
+    Write {
+        /// Node address, in [0..3]
+        slave: u8,
+        /// Register address
+        reg: u8,
+        /// Data word
+        value: u32,
+    },
+}
+
+impl Datagram {
+    /// Wire bytes, as built by [`build_read_packet`] or [`build_write_packet`].
+    pub fn encode(&self) -> EncodedDatagram {
+        match *self {
+            Datagram::Read { slave, reg } => {
+                let mut bytes = [0u8; WRITE_REQUEST_LEN];
+                bytes[..READ_REQUEST_LEN].copy_from_slice(&build_read_packet(slave, reg));
+                EncodedDatagram {
+                    bytes,
+                    len: READ_REQUEST_LEN,
+                }
+            }
+            Datagram::Write { slave, reg, value } => EncodedDatagram {
+                bytes: build_write_packet(slave, reg, value),
+                len: WRITE_REQUEST_LEN,
+            },
+        }
+    }
+
+    /// Parse wire bytes, checking sync nibble and CRC. Trailing bytes are
+    /// ignored.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (&first, &slave, &reg_byte) = (bytes.first()?, bytes.get(1)?, bytes.get(2)?);
+        if !is_sync_byte(first) {
+            return None;
+        }
+        let reg = reg_byte & 0x7F;
+        if reg_byte & 0x80 == 0 {
+            let crc = *bytes.get(3)?;
+            (calc_crc8(&bytes[..3]) == crc).then_some(Datagram::Read { slave, reg })
+        } else {
+            let frame = bytes.get(..WRITE_REQUEST_LEN)?;
+            if calc_crc8(&frame[..7]) != frame[7] {
+                return None;
+            }
+            let value = u32::from_be_bytes([frame[3], frame[4], frame[5], frame[6]]);
+            Some(Datagram::Write { slave, reg, value })
+        }
+    }
+}
+
+/// Datagram bytes returned by [`Datagram::encode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodedDatagram {
+    bytes: [u8; WRITE_REQUEST_LEN],
+    len: usize,
+}
+
+impl EncodedDatagram {
+    /// The datagram's bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Contents of a read reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadReply {
+    /// Address byte, [`MASTER_ADDRESS`] in replies from the chip
+    pub address: u8,
+    /// Register address
+    pub reg: u8,
+    /// Data word
+    pub value: u32,
+}
+
+/// Reply frame for a read of `reg`, as the chip sends it.
+pub fn build_read_reply(reg: u8, value: u32) -> [u8; READ_REPLY_LEN] {
+    let mut frame = [0u8; READ_REPLY_LEN];
+    frame[0] = SYNC_BYTE;
+    frame[1] = MASTER_ADDRESS;
+    frame[2] = reg & 0x7F;
+    frame[3..7].copy_from_slice(&value.to_be_bytes());
+    frame[7] = calc_crc8(&frame[..7]);
+    frame
+}
+
+/// Parse a reply frame, `None` if its sync nibble or CRC is wrong.
+pub fn parse_read_reply(frame: &[u8; READ_REPLY_LEN]) -> Option<ReadReply> {
+    if !is_sync_byte(frame[0]) || calc_crc8(&frame[..7]) != frame[7] {
+        return None;
+    }
+    Some(ReadReply {
+        address: frame[1],
+        reg: frame[2] & 0x7F,
+        value: u32::from_be_bytes([frame[3], frame[4], frame[5], frame[6]]),
+    })
+}
@@ -0,0 +1,147 @@
+//! TMC2209 with STEP driven by a PWM peripheral.
+//!
+//! For continuous motion at high step rates, bit-banging STEP is wasteful. This
+//! variant lets a PWM channel generate the pulse train and keeps an approximate
+//! position by integrating the step frequency over time.
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal::pwm::SetDutyCycle;
+
+use crate::errors::TmcError;
+
+/// PWM channel whose output frequency can be changed at run time.
+///
+/// `SetDutyCycle` only covers the duty cycle; HALs configure the period through
+/// their own timer APIs, so implement this on a small wrapper around your channel.
+pub trait StepFrequency {
+    /// Set the PWM output frequency in Hz.
+    fn set_frequency_hz(&mut self, hz: u32) -> Result<(), TmcError>;
+}
+
+/// TMC2209 with STEP generated by a PWM channel and DIR/EN as GPIO.
+///
+/// Position is estimated from elapsed time, not counted from pulses, so it
+/// drifts with any mismatch between the requested and actual PWM frequency.
+pub struct Tmc2209PwmStep<EN, STEP, DIR>
+where
+    EN: OutputPin,
+    STEP: SetDutyCycle + StepFrequency,
+    DIR: OutputPin,
+{
+    en: EN,
+    step: STEP,
+    dir: DIR,
+    clockwise: bool,
+    step_hz: u32,
+    position: i64,
+    // Step-frequency × elapsed-µs not yet folded into `position`.
+    remainder: u64,
+    last_update_us: u64,
+}
+
+impl<EN, STEP, DIR> Tmc2209PwmStep<EN, STEP, DIR>
+where
+    EN: OutputPin,
+    STEP: SetDutyCycle + StepFrequency,
+    DIR: OutputPin,
+{
+    /// Create a driver using a PWM channel for STEP.
+    pub fn new(en: EN, step: STEP, dir: DIR) -> Self {
+        Self {
+            en,
+            step,
+            dir,
+            clockwise: true,
+            step_hz: 0,
+            position: 0,
+            remainder: 0,
+            last_update_us: 0,
+        }
+    }
+
+    /// Enable the motor driver (active-low => EN pin LOW).
+    pub fn enable(&mut self) -> Result<(), TmcError> {
+        self.en.set_low().map_err(|_| TmcError::PinError)
+    }
+
+    /// Disable the motor driver (active-low => EN pin HIGH).
+    pub fn disable(&mut self) -> Result<(), TmcError> {
+        self.en.set_high().map_err(|_| TmcError::PinError)
+    }
+
+    /// Set direction. `true` => DIR pin HIGH.
+    ///
+    /// Returns [`TmcError::Busy`] while step output is running, since the
+    /// position estimate could not tell which direction the elapsed steps went.
+    pub fn set_direction(&mut self, clockwise: bool) -> Result<(), TmcError> {
+        if self.step_hz != 0 {
+            return Err(TmcError::Busy);
+        }
+        if clockwise {
+            self.dir.set_high().map_err(|_| TmcError::PinError)?;
+        } else {
+            self.dir.set_low().map_err(|_| TmcError::PinError)?;
+        }
+        self.clockwise = clockwise;
+        Ok(())
+    }
+
+    /// Start (or retune) continuous step output at `freq` steps per second.
+    ///
+    /// `now_us` is the current time from the application's clock; it is the
+    /// reference point for position integration.
+    pub fn start_step_output(&mut self, freq: u32, now_us: u64) -> Result<(), TmcError> {
+        if freq == 0 {
+            return self.stop_step_output(now_us);
+        }
+        self.update_position(now_us);
+        self.step.set_frequency_hz(freq)?;
+        self.step
+            .set_duty_cycle_percent(50)
+            .map_err(|_| TmcError::PinError)?;
+        self.step_hz = freq;
+        Ok(())
+    }
+
+    /// Stop step output, folding the elapsed time into the position estimate.
+    pub fn stop_step_output(&mut self, now_us: u64) -> Result<(), TmcError> {
+        self.update_position(now_us);
+        self.step
+            .set_duty_cycle_fully_off()
+            .map_err(|_| TmcError::PinError)?;
+        self.step_hz = 0;
+        Ok(())
+    }
+
+    /// Integrate the running step frequency up to `now_us`.
+    pub fn update_position(&mut self, now_us: u64) {
+        let elapsed = now_us.saturating_sub(self.last_update_us);
+        self.last_update_us = now_us;
+
+        self.remainder += self.step_hz as u64 * elapsed;
+        let steps = (self.remainder / 1_000_000) as i64;
+        self.remainder %= 1_000_000;
+
+        if self.clockwise {
+            self.position += steps;
+        } else {
+            self.position -= steps;
+        }
+    }
+
+    /// Estimated position in steps as of the last update.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// Overwrite the position estimate, e.g. after homing.
+    pub fn set_position(&mut self, position: i64) {
+        self.position = position;
+        self.remainder = 0;
+    }
+
+    /// Current step output frequency, `0` when stopped.
+    pub fn step_frequency(&self) -> u32 {
+        self.step_hz
+    }
+}
@@ -0,0 +1,186 @@
+//! Step-rate ramp generators.
+//!
+//! Ramps are plain iterators yielding the delay to wait after each step, so
+//! they can be consumed by the blocking motion helpers in this crate or by an
+//! application's own timer infrastructure.
+
+/// Delay after a step pulse before the next one, in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StepDelayNs(pub u32);
+
+impl StepDelayNs {
+    /// Delay corresponding to a step rate in steps per second.
+    #[inline]
+    pub fn from_speed(steps_per_sec: u32) -> Self {
+        StepDelayNs(1_000_000_000 / steps_per_sec.max(1))
+    }
+}
+
+/// Acceleration-limited motion parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RampConfig {
+    /// Speed at the start and end of a move, in steps/s
+    pub start_speed: u32,
+    /// Cruise speed, in steps/s
+    pub max_speed: u32,
+    /// Acceleration and deceleration, in steps/s²
+    pub acceleration: u32,
+}
+
+impl Default for RampConfig {
+    fn default() -> Self {
+        RampConfig {
+            start_speed: 200,
+            max_speed: 2_000,
+            acceleration: 4_000,
+        }
+    }
+}
+
+/// Trapezoidal speed profile over a fixed number of steps.
+///
+/// Speeds up from the entry speed, cruises, and slows to the exit speed. If the
+/// move is too short to reach cruise speed the profile becomes triangular.
+#[derive(Debug, Clone)]
+pub struct TrapezoidRamp {
+    steps: u32,
+    done: u32,
+    entry_sq: u64,
+    exit_sq: u64,
+    cruise: u32,
+    accel: u64,
+}
+
+impl TrapezoidRamp {
+    /// Profile for `steps` steps, starting and ending at `config.start_speed`.
+    pub fn new(steps: u32, config: &RampConfig) -> Self {
+        Self::with_speeds(
+            steps,
+            config.start_speed,
+            config.max_speed,
+            config.start_speed,
+            config.acceleration,
+        )
+    }
+
+    /// Profile with distinct entry and exit speeds, all in steps/s.
+    pub fn with_speeds(steps: u32, entry: u32, cruise: u32, exit: u32, acceleration: u32) -> Self {
+        let cruise = cruise.max(1);
+        let entry = entry.min(cruise) as u64;
+        let exit = exit.min(cruise) as u64;
+        TrapezoidRamp {
+            steps,
+            done: 0,
+            entry_sq: entry * entry,
+            exit_sq: exit * exit,
+            cruise,
+            accel: acceleration as u64,
+        }
+    }
+
+    /// Steps not yet yielded.
+    pub fn remaining(&self) -> u32 {
+        self.steps - self.done
+    }
+
+    /// Continue the remaining steps at a microstep resolution `num / den`
+    /// times the current one: steps, speeds and acceleration are scaled so
+    /// the physical motion is unchanged. A fraction of a coarser step left
+    /// over is dropped.
+    #[cfg(feature = "uart")]
+    pub(crate) fn rescale(&mut self, num: u32, den: u32) {
+        let scale = |v: u64| (v * num as u64 / den as u64).min(u32::MAX as u64) as u32;
+        let current = if self.remaining() > 0 {
+            self.speed_at(self.done) as u64
+        } else {
+            isqrt(self.entry_sq)
+        };
+        *self = TrapezoidRamp::with_speeds(
+            scale(self.remaining() as u64),
+            scale(current),
+            scale(self.cruise as u64),
+            scale(isqrt(self.exit_sq)),
+            scale(self.accel),
+        );
+    }
+
+    /// Speed for step `index`, limited by cruise speed and by how fast we can
+    /// get there from the entry speed or back down to the exit speed.
+    fn speed_at(&self, index: u32) -> u32 {
+        let from_entry = isqrt(self.entry_sq + 2 * self.accel * index as u64);
+        let to_exit = isqrt(self.exit_sq + 2 * self.accel * (self.steps - 1 - index) as u64);
+        let speed = from_entry.min(to_exit).min(self.cruise as u64);
+        speed.max(1) as u32
+    }
+}
+
+impl Iterator for TrapezoidRamp {
+    type Item = StepDelayNs;
+
+    #[inline]
+    fn next(&mut self) -> Option<StepDelayNs> {
+        if self.done >= self.steps {
+            return None;
+        }
+        let speed = self.speed_at(self.done);
+        self.done += 1;
+        Some(StepDelayNs::from_speed(speed))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining() as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for TrapezoidRamp {}
+
+/// Fixed step rate for a fixed number of steps.
+#[derive(Debug, Clone)]
+pub struct ConstantRate {
+    remaining: u32,
+    delay: StepDelayNs,
+}
+
+impl ConstantRate {
+    /// `steps` steps at `speed` steps/s.
+    pub fn new(steps: u32, speed: u32) -> Self {
+        ConstantRate {
+            remaining: steps,
+            delay: StepDelayNs::from_speed(speed),
+        }
+    }
+}
+
+impl Iterator for ConstantRate {
+    type Item = StepDelayNs;
+
+    #[inline]
+    fn next(&mut self) -> Option<StepDelayNs> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(self.delay)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl ExactSizeIterator for ConstantRate {}
+
+/// Integer square root (floor).
+pub(crate) fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
@@ -9,6 +9,8 @@ pub const REG_GCONF: u8 = 0x00;
 pub const REG_GSTAT: u8 = 0x01;
 pub const REG_IFCNT: u8 = 0x02;
 pub const REG_SLAVECONF: u8 = 0x03;
+pub const REG_OTP_PROG: u8 = 0x04;
+pub const REG_OTP_READ: u8 = 0x05;
 pub const REG_IOIN: u8 = 0x06;
 pub const REG_FACTORY_CONF: u8 = 0x07;
 
@@ -33,8 +35,42 @@ pub const REG_MSCURACT: u8 = 0x6B;
 pub const REG_CHOPCONF: u8 = 0x6C;
 pub const REG_DRVSTATUS: u8 = 0x6F;
 pub const REG_PWMCONF: u8 = 0x70;
-pub const REG_PWMSTATUS: u8 = 0x71;
-pub const REG_ENCM_CTRL: u8 = 0x72;
+pub const REG_PWM_SCALE: u8 = 0x71;
+pub const REG_PWM_AUTO: u8 = 0x72;
+#[deprecated(note = "the TMC2209 register at 0x71 is PWM_SCALE, use REG_PWM_SCALE")]
+pub const REG_PWMSTATUS: u8 = REG_PWM_SCALE;
+
+/// Every register address of the TMC2209, in address order.
+pub const TMC2209_REGISTER_ADDRS: [u8; 24] = [
+    REG_GCONF,
+    REG_GSTAT,
+    REG_IFCNT,
+    REG_SLAVECONF,
+    REG_OTP_PROG,
+    REG_OTP_READ,
+    REG_IOIN,
+    REG_FACTORY_CONF,
+    REG_IHOLD_IRUN,
+    REG_TPOWERDOWN,
+    REG_TSTEP,
+    REG_TPWMTHRS,
+    REG_TCOOLTHRS,
+    REG_VACTUAL,
+    REG_SGTHRS,
+    REG_SG_RESULT,
+    REG_COOLCONF,
+    REG_MSCNT,
+    REG_MSCURACT,
+    REG_CHOPCONF,
+    REG_DRVSTATUS,
+    REG_PWMCONF,
+    REG_PWM_SCALE,
+    REG_PWM_AUTO,
+];
+
+/// StallGuard/CoolStep registers the TMC2208/TMC2225 do not have.
+pub const STALLGUARD_REGISTER_ADDRS: [u8; 4] =
+    [REG_TCOOLTHRS, REG_SGTHRS, REG_SG_RESULT, REG_COOLCONF];
 
 // --- GCONF bits ---
 pub const GCONF_I_SCALE_ANALOG: u32 = 1 << 0; // 0 => internal reference, 1 => VREF pin
@@ -48,7 +84,36 @@ pub const GCONF_MSTEP_REG_SELECT: u32 = 1 << 7; // 1 => microstep resolution by
 pub const GCONF_MULTISTEP_FILT: u32 = 1 << 8;
 pub const GCONF_TEST_MODE: u32 = 1 << 9; // not for normal use
 
+// --- GSTAT bits (write 1 to clear) ---
+pub const GSTAT_RESET: u32 = 1 << 0;
+pub const GSTAT_DRV_ERR: u32 = 1 << 1;
+pub const GSTAT_UV_CP: u32 = 1 << 2;
+
 // --- IHOLD_IRUN bits ---
 // Bits [4..0]: IHOLD
 // Bits [12..8]: IRUN
 // Bits [19..16]: IHOLDDELAY
+
+// --- MSCNT ---
+// Bits [9..0]: position in the 1024-entry microstep table
+pub const MSCNT_MASK: u32 = 0x3FF;
+pub const MSCNT_TABLE_LEN: i32 = 1024;
+// Full-step positions are MSCNT_FULL_STEP_OFFSET + k * MSCNT_FULL_STEP_SPAN
+pub const MSCNT_FULL_STEP_OFFSET: i32 = 128;
+pub const MSCNT_FULL_STEP_SPAN: i32 = 256;
+
+// --- CHOPCONF bits ---
+pub const CHOPCONF_TOFF_MASK: u32 = 0x0F; // Bits [3..0]: TOFF, 0 => outputs off
+pub const CHOPCONF_VSENSE: u32 = 1 << 17; // 1 => high sensitivity, low full-scale current
+pub const CHOPCONF_INTPOL: u32 = 1 << 28; // 1 => interpolate to 256 microsteps
+                                          // Bits [27..24]: MRES, microsteps = 256 >> MRES
+pub const CHOPCONF_MRES_SHIFT: u32 = 24;
+pub const CHOPCONF_MRES_MASK: u32 = 0x0F << CHOPCONF_MRES_SHIFT;
+
+// --- PWMCONF bits ---
+pub const PWMCONF_PWM_AUTOSCALE: u32 = 1 << 18; // 1 => automatic amplitude regulation
+pub const PWMCONF_PWM_AUTOGRAD: u32 = 1 << 19; // 1 => automatic gradient tuning
+
+// --- IOIN bits ---
+pub const IOIN_MS1: u32 = 1 << 2;
+pub const IOIN_MS2: u32 = 1 << 3;
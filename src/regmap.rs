@@ -0,0 +1,272 @@
+//! Register metadata: names, access kinds and bitfields.
+//!
+//! [`TMC2209_REGISTERS`] describes the registers of this chip. Related Trinamic
+//! parts and later silicon revisions can describe their own registers with the
+//! [`tmc_registers!`](crate::tmc_registers) macro and use them with the same
+//! [`dump_registers`] and pretty-printing helpers.
+
+use core::fmt;
+
+use crate::erased::ErasedTmc2209;
+use crate::errors::TmcError;
+
+/// How a register may be accessed over UART.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Read only
+    R,
+    /// Write only; reads return garbage or zero
+    W,
+    /// Read and write
+    RW,
+    /// Read, and write 1 to clear flags
+    RWC,
+}
+
+impl Access {
+    /// `true` if reading the register returns its contents.
+    pub const fn is_readable(self) -> bool {
+        !matches!(self, Access::W)
+    }
+
+    /// `true` if the register can be written.
+    pub const fn is_writable(self) -> bool {
+        !matches!(self, Access::R)
+    }
+}
+
+/// One bitfield of a register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldDef {
+    /// Datasheet name of the field
+    pub name: &'static str,
+    /// Lowest bit of the field
+    pub lsb: u8,
+    /// Width of the field in bits, in [1..32]
+    pub width: u8,
+}
+
+impl FieldDef {
+    /// Mask of the field in register position.
+    pub const fn mask(&self) -> u32 {
+        (u32::MAX >> (32 - self.width as u32)) << self.lsb
+    }
+
+    /// Extract the field from a raw register value.
+    pub const fn get(&self, raw: u32) -> u32 {
+        (raw & self.mask()) >> self.lsb
+    }
+
+    /// Replace the field in `raw` with `value`, truncated to the field width.
+    pub const fn set(&self, raw: u32, value: u32) -> u32 {
+        (raw & !self.mask()) | ((value << self.lsb) & self.mask())
+    }
+}
+
+/// Description of one register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterDef {
+    /// Datasheet name of the register
+    pub name: &'static str,
+    /// Register address
+    pub addr: u8,
+    /// Access kind
+    pub access: Access,
+    /// Bitfields, lowest bit first
+    pub fields: &'static [FieldDef],
+}
+
+impl RegisterDef {
+    /// Field called `name`, if any.
+    pub fn field(&self, name: &str) -> Option<&FieldDef> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Pretty-printable view of `raw` split into fields, e.g.
+    /// `IHOLD_IRUN (0x10) = 0x00081008 { ihold: 8, irun: 16, iholddelay: 8 }`.
+    pub fn display(&self, raw: u32) -> RegisterValue<'_> {
+        RegisterValue { def: self, raw }
+    }
+}
+
+/// A register value together with its description, see [`RegisterDef::display`].
+#[derive(Debug, Clone, Copy)]
+pub struct RegisterValue<'a> {
+    def: &'a RegisterDef,
+    raw: u32,
+}
+
+impl fmt::Display for RegisterValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (0x{:02X}) = 0x{:08X}",
+            self.def.name, self.def.addr, self.raw
+        )?;
+        if self.def.fields.is_empty() {
+            return Ok(());
+        }
+        f.write_str(" {")?;
+        for (i, field) in self.def.fields.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}: {}", sep, field.name, field.get(self.raw))?;
+        }
+        f.write_str(" }")
+    }
+}
+
+/// Description of register `addr` in `defs`.
+pub fn lookup_register(defs: &[RegisterDef], addr: u8) -> Option<&RegisterDef> {
+    defs.iter().find(|d| d.addr == addr)
+}
+
+/// Read every readable register in `defs` and pass it to `f` with its value.
+///
+/// Write-only registers are skipped. Stops at the first failed read.
+pub fn dump_registers<D, F>(driver: &mut D, defs: &[RegisterDef], mut f: F) -> Result<(), TmcError>
+where
+    D: ErasedTmc2209 + ?Sized,
+    F: FnMut(&RegisterDef, u32),
+{
+    for def in defs.iter().filter(|d| d.access.is_readable()) {
+        let raw = driver.read_register(def.addr)?;
+        f(def, raw);
+    }
+    Ok(())
+}
+
+/// Define a static register table for [`dump_registers`] and
+/// [`RegisterDef::display`].
+///
+/// Each register is `NAME = address, access { field: lsb..=msb, ... }`, where
+/// access is one of the [`Access`] variants. Fields are listed lowest bit first.
+///
+/// ```ignore
+/// tmc2209_driver::tmc_registers! {
+///     /// Extra registers of a related chip.
+///     pub static MY_REGISTERS = [
+///         XTARGET = 0x2D, RW {},
+///         SW_MODE = 0x34, RW { stop_l_enable: 0..=0, stop_r_enable: 1..=1 },
+///     ];
+/// }
+/// ```
+#[macro_export]
+macro_rules! tmc_registers {
+    (
+        $(#[$meta:meta])*
+        $vis:vis static $table:ident = [
+            $( $reg:ident = $addr:literal, $access:ident {
+                $( $field:ident : $lsb:literal ..= $msb:literal ),* $(,)?
+            } ),* $(,)?
+        ];
+    ) => {
+        $(#[$meta])*
+        $vis static $table: &[$crate::RegisterDef] = &[
+            $( $crate::RegisterDef {
+                name: stringify!($reg),
+                addr: $addr,
+                access: $crate::Access::$access,
+                fields: &[
+                    $( $crate::FieldDef {
+                        name: stringify!($field),
+                        lsb: $lsb,
+                        width: $msb - $lsb + 1,
+                    } ),*
+                ],
+            } ),*
+        ];
+    };
+}
+
+tmc_registers! {
+    /// Registers of the TMC2209, in address order.
+    pub static TMC2209_REGISTERS = [
+        GCONF = 0x00, RW {
+            i_scale_analog: 0..=0,
+            internal_rsense: 1..=1,
+            en_spreadcycle: 2..=2,
+            shaft: 3..=3,
+            index_otpw: 4..=4,
+            index_step: 5..=5,
+            pdn_disable: 6..=6,
+            mstep_reg_select: 7..=7,
+            multistep_filt: 8..=8,
+            test_mode: 9..=9,
+        },
+        GSTAT = 0x01, RWC { reset: 0..=0, drv_err: 1..=1, uv_cp: 2..=2 },
+        IFCNT = 0x02, R { ifcnt: 0..=7 },
+        SLAVECONF = 0x03, W { senddelay: 8..=11 },
+        OTP_PROG = 0x04, W { otpbit: 0..=2, otpbyte: 4..=5, otpmagic: 8..=15 },
+        OTP_READ = 0x05, R { otp0: 0..=7, otp1: 8..=15, otp2: 16..=23 },
+        IOIN = 0x06, R {
+            enn: 0..=0,
+            ms1: 2..=2,
+            ms2: 3..=3,
+            diag: 4..=4,
+            pdn_uart: 6..=6,
+            step: 7..=7,
+            spread_en: 8..=8,
+            dir: 9..=9,
+            version: 24..=31,
+        },
+        FACTORY_CONF = 0x07, RW { fclktrim: 0..=4, ottrim: 8..=9 },
+        IHOLD_IRUN = 0x10, W { ihold: 0..=4, irun: 8..=12, iholddelay: 16..=19 },
+        TPOWERDOWN = 0x11, W { tpowerdown: 0..=7 },
+        TSTEP = 0x12, R { tstep: 0..=19 },
+        TPWMTHRS = 0x13, W { tpwmthrs: 0..=19 },
+        TCOOLTHRS = 0x14, W { tcoolthrs: 0..=19 },
+        VACTUAL = 0x22, W { vactual: 0..=23 },
+        SGTHRS = 0x40, W { sgthrs: 0..=7 },
+        SG_RESULT = 0x41, R { sg_result: 0..=9 },
+        COOLCONF = 0x42, W {
+            semin: 0..=3,
+            seup: 5..=6,
+            semax: 8..=11,
+            sedn: 13..=14,
+            seimin: 15..=15,
+        },
+        MSCNT = 0x6A, R { mscnt: 0..=9 },
+        MSCURACT = 0x6B, R { cur_a: 0..=8, cur_b: 16..=24 },
+        CHOPCONF = 0x6C, RW {
+            toff: 0..=3,
+            hstrt: 4..=6,
+            hend: 7..=10,
+            tbl: 15..=16,
+            vsense: 17..=17,
+            mres: 24..=27,
+            intpol: 28..=28,
+            dedge: 29..=29,
+            diss2g: 30..=30,
+            diss2vs: 31..=31,
+        },
+        DRV_STATUS = 0x6F, R {
+            otpw: 0..=0,
+            ot: 1..=1,
+            s2ga: 2..=2,
+            s2gb: 3..=3,
+            s2vsa: 4..=4,
+            s2vsb: 5..=5,
+            ola: 6..=6,
+            olb: 7..=7,
+            t120: 8..=8,
+            t143: 9..=9,
+            t150: 10..=10,
+            t157: 11..=11,
+            cs_actual: 16..=20,
+            stealth: 30..=30,
+            stst: 31..=31,
+        },
+        PWMCONF = 0x70, RW {
+            pwm_ofs: 0..=7,
+            pwm_grad: 8..=15,
+            pwm_freq: 16..=17,
+            pwm_autoscale: 18..=18,
+            pwm_autograd: 19..=19,
+            freewheel: 20..=21,
+            pwm_reg: 24..=27,
+            pwm_lim: 28..=31,
+        },
+        PWM_SCALE = 0x71, R { pwm_scale_sum: 0..=7, pwm_scale_auto: 16..=24 },
+        PWM_AUTO = 0x72, R { pwm_ofs_auto: 0..=7, pwm_grad_auto: 16..=23 },
+    ];
+}
@@ -0,0 +1,422 @@
+//! A small framed command set for driving an axis over a second link.
+//!
+//! A host MCU or PC sends [`Command`]s, the MCU running the driver answers
+//! each with one [`Response`]. Both ends use this module: the host encodes
+//! commands and decodes responses, the device does the opposite and hands the
+//! commands to `execute_remote` on the driver.
+//! Encoding and decoding need no driver, so host tools can enable the `remote`
+//! feature without `uart`.
+//!
+//! Every frame is `[0xA5, len, tag, payload..., crc]`, where `len` counts the
+//! tag and payload, the CRC is [`calc_crc8`] over everything before it, and
+//! multi-byte values are little-endian. Frames never exceed [`MAX_FRAME_LEN`]
+//! bytes, so both ends work without allocation.
+//!
+//! ```ignore
+//! let mut reader = FrameReader::new();
+//! loop {
+//!     if let Some(frame) = reader.push(link.read_byte()?) {
+//!         if let Some(command) = Command::decode(frame.as_bytes()) {
+//!             let response = driver.execute_remote(command, &config, &mut delay);
+//!             link.write_all(response.encode().as_bytes())?;
+//!         }
+//!     }
+//! }
+//! ```
+
+#[cfg(feature = "uart")]
+use embedded_hal::delay::DelayNs;
+#[cfg(feature = "uart")]
+use embedded_hal::digital::OutputPin;
+#[cfg(feature = "uart")]
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::error_code::TmcErrorCode;
+#[cfg(feature = "uart")]
+use crate::homing::HomingConfig;
+use crate::protocol::calc_crc8;
+#[cfg(feature = "uart")]
+use crate::protocol::Crc8Provider;
+#[cfg(feature = "uart")]
+use crate::ramp::RampConfig;
+#[cfg(feature = "uart")]
+use crate::registers::REG_DRVSTATUS;
+use crate::state::{DriverState, FaultKind};
+#[cfg(feature = "uart")]
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// First byte of every frame.
+pub const FRAME_SYNC: u8 = 0xA5;
+
+/// Longest frame: sync, length, tag, 10 payload bytes and the CRC.
+pub const MAX_FRAME_LEN: usize = 14;
+
+/// Bytes of a frame that are not tag or payload: sync, length and CRC.
+const FRAME_OVERHEAD: usize = 3;
+
+const TAG_MOVE_TO: u8 = 0x01;
+const TAG_MOVE_BY: u8 = 0x02;
+const TAG_HOME: u8 = 0x03;
+const TAG_SET_CURRENT: u8 = 0x04;
+const TAG_QUERY_STATUS: u8 = 0x05;
+const TAG_DONE: u8 = 0x81;
+const TAG_ERROR: u8 = 0x82;
+const TAG_STATUS: u8 = 0x83;
+
+/// Status flag bit: the position was referenced by homing.
+const FLAG_HOMED: u8 = 1 << 0;
+
+/// Request from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Move to an absolute position, in steps
+    MoveTo {
+        /// Target position
+        position: i32,
+    },
+    /// Move by a number of steps; negative => counter-clockwise
+    MoveBy {
+        /// Relative distance
+        steps: i32,
+    },
+    /// Run sensorless homing
+    Home,
+    /// Set run and hold current, in mA RMS
+    SetCurrent {
+        /// Run current
+        run_ma: u16,
+        /// Hold current
+        hold_ma: u16,
+    },
+    /// Report the axis status
+    QueryStatus,
+}
+
+/// Answer from the device, one per command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Response {
+    /// The command completed
+    Done {
+        /// Position counter afterwards, in steps
+        position: i32,
+    },
+    /// The command failed
+    Error {
+        /// The driver error, see [`TmcErrorCode`]
+        code: TmcErrorCode,
+    },
+    /// Answer to [`Command::QueryStatus`]
+    Status(Status),
+}
+
+/// Axis status reported for [`Command::QueryStatus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// Position counter, in steps
+    pub position: i32,
+    /// Driver lifecycle state
+    pub state: DriverState,
+    /// `true` if the position was referenced by homing
+    pub homed: bool,
+    /// Raw DRV_STATUS, see `DrvStatus::from_raw`
+    pub drv_status: u32,
+}
+
+/// Bytes of one frame, as returned by `encode` and [`FrameReader::push`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    bytes: [u8; MAX_FRAME_LEN],
+    len: usize,
+}
+
+impl Frame {
+    /// Frame with tag `tag` and `payload`, sync, length and CRC added.
+    fn new(tag: u8, payload: &[u8]) -> Self {
+        let mut bytes = [0u8; MAX_FRAME_LEN];
+        let len = payload.len() + 1;
+        bytes[0] = FRAME_SYNC;
+        bytes[1] = len as u8;
+        bytes[2] = tag;
+        bytes[3..2 + len].copy_from_slice(payload);
+        bytes[2 + len] = calc_crc8(&bytes[..2 + len]);
+        Frame {
+            bytes,
+            len: len + FRAME_OVERHEAD,
+        }
+    }
+
+    /// The frame's bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Tag and payload of a complete frame, `None` if its sync byte, length or
+/// CRC is wrong. Trailing bytes are ignored.
+fn unframe(bytes: &[u8]) -> Option<(u8, &[u8])> {
+    if *bytes.first()? != FRAME_SYNC {
+        return None;
+    }
+    let len = *bytes.get(1)? as usize;
+    if len == 0 || len + FRAME_OVERHEAD > MAX_FRAME_LEN {
+        return None;
+    }
+    let frame = bytes.get(..len + FRAME_OVERHEAD)?;
+    if calc_crc8(&frame[..len + 2]) != frame[len + 2] {
+        return None;
+    }
+    Some((frame[2], &frame[3..len + 2]))
+}
+
+fn i32_at(payload: &[u8], at: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(
+        payload.get(at..at + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(payload: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        payload.get(at..at + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u16_at(payload: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        payload.get(at..at + 2)?.try_into().ok()?,
+    ))
+}
+
+impl Command {
+    /// Wire bytes of this command.
+    pub fn encode(&self) -> Frame {
+        match *self {
+            Command::MoveTo { position } => Frame::new(TAG_MOVE_TO, &position.to_le_bytes()),
+            Command::MoveBy { steps } => Frame::new(TAG_MOVE_BY, &steps.to_le_bytes()),
+            Command::Home => Frame::new(TAG_HOME, &[]),
+            Command::SetCurrent { run_ma, hold_ma } => {
+                let mut payload = [0u8; 4];
+                payload[..2].copy_from_slice(&run_ma.to_le_bytes());
+                payload[2..].copy_from_slice(&hold_ma.to_le_bytes());
+                Frame::new(TAG_SET_CURRENT, &payload)
+            }
+            Command::QueryStatus => Frame::new(TAG_QUERY_STATUS, &[]),
+        }
+    }
+
+    /// Parse a frame, `None` if it is corrupt or not a known command.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (tag, payload) = unframe(bytes)?;
+        match (tag, payload.len()) {
+            (TAG_MOVE_TO, 4) => Some(Command::MoveTo {
+                position: i32_at(payload, 0)?,
+            }),
+            (TAG_MOVE_BY, 4) => Some(Command::MoveBy {
+                steps: i32_at(payload, 0)?,
+            }),
+            (TAG_HOME, 0) => Some(Command::Home),
+            (TAG_SET_CURRENT, 4) => Some(Command::SetCurrent {
+                run_ma: u16_at(payload, 0)?,
+                hold_ma: u16_at(payload, 2)?,
+            }),
+            (TAG_QUERY_STATUS, 0) => Some(Command::QueryStatus),
+            _ => None,
+        }
+    }
+}
+
+/// Wire code of a driver state.
+fn state_code(state: DriverState) -> u8 {
+    match state {
+        DriverState::PoweredDown => 0,
+        DriverState::Configured => 1,
+        DriverState::Enabled => 2,
+        DriverState::Moving => 3,
+        DriverState::Fault(FaultKind::Overtemperature) => 4,
+        DriverState::Fault(FaultKind::Short) => 5,
+        DriverState::Fault(FaultKind::ChargePumpUndervoltage) => 6,
+        DriverState::EStopped => 7,
+    }
+}
+
+/// Driver state for a code from [`state_code`].
+fn state_from_code(code: u8) -> Option<DriverState> {
+    Some(match code {
+        0 => DriverState::PoweredDown,
+        1 => DriverState::Configured,
+        2 => DriverState::Enabled,
+        3 => DriverState::Moving,
+        4 => DriverState::Fault(FaultKind::Overtemperature),
+        5 => DriverState::Fault(FaultKind::Short),
+        6 => DriverState::Fault(FaultKind::ChargePumpUndervoltage),
+        7 => DriverState::EStopped,
+        _ => return None,
+    })
+}
+
+impl Response {
+    /// Wire bytes of this response.
+    pub fn encode(&self) -> Frame {
+        match *self {
+            Response::Done { position } => Frame::new(TAG_DONE, &position.to_le_bytes()),
+            Response::Error { code } => Frame::new(TAG_ERROR, &code.0.to_le_bytes()),
+            Response::Status(status) => {
+                let mut payload = [0u8; 10];
+                payload[..4].copy_from_slice(&status.position.to_le_bytes());
+                payload[4] = state_code(status.state);
+                payload[5] = if status.homed { FLAG_HOMED } else { 0 };
+                payload[6..].copy_from_slice(&status.drv_status.to_le_bytes());
+                Frame::new(TAG_STATUS, &payload)
+            }
+        }
+    }
+
+    /// Parse a frame, `None` if it is corrupt or not a known response.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (tag, payload) = unframe(bytes)?;
+        match (tag, payload.len()) {
+            (TAG_DONE, 4) => Some(Response::Done {
+                position: i32_at(payload, 0)?,
+            }),
+            (TAG_ERROR, 2) => Some(Response::Error {
+                code: TmcErrorCode(u16_at(payload, 0)?),
+            }),
+            (TAG_STATUS, 10) => Some(Response::Status(Status {
+                position: i32_at(payload, 0)?,
+                state: state_from_code(payload[4])?,
+                homed: payload[5] & FLAG_HOMED != 0,
+                drv_status: u32_at(payload, 6)?,
+            })),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a byte stream into frames.
+///
+/// Bytes before a sync byte are skipped, and a frame with a bad length or CRC
+/// is dropped before looking for the next sync byte, so the reader recovers
+/// from line noise and from joining the stream mid-frame. A dropped command
+/// gets no response; the host should time out and resend.
+#[derive(Debug, Clone)]
+pub struct FrameReader {
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    rejected: u32,
+}
+
+impl FrameReader {
+    /// Reader waiting for the first sync byte.
+    pub const fn new() -> Self {
+        FrameReader {
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            rejected: 0,
+        }
+    }
+
+    /// Feed one received byte; returns a frame once one is complete and
+    /// intact. Decode it with [`Command::decode`] or [`Response::decode`].
+    pub fn push(&mut self, byte: u8) -> Option<Frame> {
+        if self.len == 0 && byte != FRAME_SYNC {
+            return None;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if self.len < 2 {
+            return None;
+        }
+        let len = self.buf[1] as usize;
+        if len == 0 || len + FRAME_OVERHEAD > MAX_FRAME_LEN {
+            self.reject();
+            return None;
+        }
+        if self.len < len + FRAME_OVERHEAD {
+            return None;
+        }
+        if unframe(&self.buf[..self.len]).is_none() {
+            self.reject();
+            return None;
+        }
+        let frame = Frame {
+            bytes: self.buf,
+            len: self.len,
+        };
+        self.len = 0;
+        Some(frame)
+    }
+
+    /// Frames dropped for a bad length or CRC.
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+
+    fn reject(&mut self) {
+        self.rejected = self.rejected.wrapping_add(1);
+        self.len = 0;
+    }
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Motion parameters for remote commands.
+#[cfg(feature = "uart")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RemoteConfig {
+    /// Ramp for [`Command::MoveTo`] and [`Command::MoveBy`]
+    pub ramp: RampConfig,
+    /// Parameters for [`Command::Home`]
+    pub homing: HomingConfig,
+}
+
+#[cfg(feature = "uart")]
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Carry out a remote command and build its response.
+    ///
+    /// Moves and homing block until they finish, like `move_to` and `home`;
+    /// the response reports the position afterwards. Errors are returned as
+    /// [`Response::Error`] with the error's [`TmcErrorCode`].
+    pub fn execute_remote<D: DelayNs>(
+        &mut self,
+        command: Command,
+        config: &RemoteConfig,
+        delay: &mut D,
+    ) -> Response {
+        let result = match command {
+            Command::MoveTo { position } => self.move_to(position, &config.ramp, delay),
+            Command::MoveBy { steps } => self.move_by(steps, &config.ramp, delay),
+            Command::Home => self.home(&config.homing, delay).map(|_| ()),
+            Command::SetCurrent { run_ma, hold_ma } => self
+                .set_current_ma(run_ma as u32, hold_ma as u32)
+                .map(|_| ()),
+            Command::QueryStatus => {
+                return match self.read_register(REG_DRVSTATUS) {
+                    Ok(drv_status) => Response::Status(Status {
+                        position: self.position(),
+                        state: self.state(),
+                        homed: self.is_homed(),
+                        drv_status,
+                    }),
+                    Err(e) => Response::Error { code: e.code() },
+                }
+            }
+        };
+        match result {
+            Ok(()) => Response::Done {
+                position: self.position(),
+            },
+            Err(e) => Response::Error { code: e.code() },
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! A conservative fallback configuration for fault handlers.
+//!
+//! After an error the machine often still has to move, e.g. to park the head
+//! or to reach a position where an operator can service it. [`SafeModeConfig`]
+//! describes a setup that trades torque and speed for the least stress on
+//! motor, driver and mechanics, and
+//! [`Tmc2209FullUartDiagnosticsAndControl::enter_safe_mode`] programs it in
+//! one call.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::config::KinematicLimits;
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::{Ihold, IholdDelay, Irun};
+
+/// Settings applied by `enter_safe_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeModeConfig {
+    /// Highest run current; a lower IRUN already set is kept
+    pub irun: Irun,
+    /// Highest hold current; a lower IHOLD already set is kept
+    pub ihold: Ihold,
+    /// Speed and acceleration limits for all motion afterwards
+    pub limits: KinematicLimits,
+}
+
+impl Default for SafeModeConfig {
+    fn default() -> Self {
+        SafeModeConfig {
+            irun: Irun::saturating(8),
+            ihold: Ihold::saturating(4),
+            limits: KinematicLimits {
+                max_v: 400,
+                max_a: 800,
+                max_jerk: u32::MAX,
+            },
+        }
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Switch to a conservative configuration so the machine can still limp
+    /// to a serviceable position after an error.
+    ///
+    /// In this order:
+    ///
+    /// 1. installs `config.limits` as the kinematic limits, which needs no
+    ///    UART and so holds even if the link is down;
+    /// 2. slows a VACTUAL rotation down to the new speed limit;
+    /// 3. lowers IRUN and IHOLD to at most `config.irun` / `config.ihold`,
+    ///    keeping IHOLDDELAY;
+    /// 4. selects stealthChop at all speeds, with CoolStep off.
+    ///
+    /// Meant to be called from fault handlers, so a failing step does not stop
+    /// the ones after it; the first error is returned at the end.
+    pub fn enter_safe_mode(&mut self, config: &SafeModeConfig) -> Result<(), TmcError> {
+        self.set_kinematic_limits(config.limits);
+
+        let mut first_error = None;
+        let mut note = |result: Result<(), TmcError>| {
+            if let Err(e) = result {
+                first_error.get_or_insert(e);
+            }
+        };
+        if self.vactual().unsigned_abs() > self.max_vactual() {
+            note(self.rotate_at_clamped(self.vactual()).map(|_| ()));
+        }
+        let (irun, ihold, ihold_delay) = match self.shadow_register(REG_IHOLD_IRUN) {
+            Some(raw) => (
+                config.irun.min(Irun::saturating((raw >> 8) as u8 & 0x1F)),
+                config.ihold.min(Ihold::saturating(raw as u8 & 0x1F)),
+                IholdDelay::saturating((raw >> 16) as u8 & 0x0F),
+            ),
+            None => (config.irun, config.ihold, IholdDelay::saturating(0)),
+        };
+        note(self.set_run_hold_current(irun, ihold, ihold_delay));
+        note(self.configure_hybrid_tstep(Some(0), None));
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
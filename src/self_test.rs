@@ -0,0 +1,213 @@
+//! Scripted end-of-line test for the Full UART driver.
+//!
+//! `self_test` checks, in order: that the chip answers and accepts writes,
+//! its version, the global status after clearing it, that the outputs follow
+//! STEP during a brief low-current move, and the open-load and short flags
+//! seen while the coils are energised. Each stage gets a [`StageResult`];
+//! stages that depend on a failed one are skipped.
+//!
+//! The UART driver has no INDEX pin, so motion is verified on MSCNT, which the
+//! INDEX output is derived from.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::config::{InitChecks, SelfTestConfig};
+use crate::errors::TmcError;
+use crate::fields::{DrvStatus, InitReport};
+use crate::phase::wrap_mscnt;
+use crate::protocol::Crc8Provider;
+use crate::ramp::ConstantRate;
+use crate::registers::*;
+use crate::state::DriverState;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::{Ihold, IholdDelay};
+
+/// Outcome of one self-test stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageResult {
+    /// The check ran and found nothing wrong.
+    Passed,
+    /// The check ran and failed.
+    Failed,
+    /// The check did not run because an earlier stage failed.
+    Skipped,
+}
+
+/// What `self_test` found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// The chip answered reads and counted a write in IFCNT
+    pub uart: StageResult,
+    /// IOIN.VERSION matched `SelfTestConfig::expected_version`
+    pub version: StageResult,
+    /// GSTAT showed neither drv_err nor uv_cp after clearing
+    pub gstat: StageResult,
+    /// MSCNT advanced by the same amount for every step and came back to its
+    /// start after stepping back
+    pub motion: StageResult,
+    /// DRV_STATUS showed no open load or short while energised
+    pub open_load: StageResult,
+    /// Communication error that failed the UART stage
+    pub uart_error: Option<TmcError>,
+    /// Version and GSTAT as read during the UART stage
+    pub init: Option<InitReport>,
+    /// MSCNT change per step measured in the motion stage, 0 if none
+    pub mscnt_per_step: i32,
+    /// DRV_STATUS read at the end of the forward move
+    pub drv_status: Option<DrvStatus>,
+}
+
+impl SelfTestReport {
+    fn new() -> Self {
+        SelfTestReport {
+            uart: StageResult::Skipped,
+            version: StageResult::Skipped,
+            gstat: StageResult::Skipped,
+            motion: StageResult::Skipped,
+            open_load: StageResult::Skipped,
+            uart_error: None,
+            init: None,
+            mscnt_per_step: 0,
+            drv_status: None,
+        }
+    }
+
+    /// `true` if every stage ran and passed.
+    pub fn passed(&self) -> bool {
+        [
+            self.uart,
+            self.version,
+            self.gstat,
+            self.motion,
+            self.open_load,
+        ]
+        .iter()
+        .all(|&stage| stage == StageResult::Passed)
+    }
+}
+
+fn stage(ok: bool) -> StageResult {
+    if ok {
+        StageResult::Passed
+    } else {
+        StageResult::Failed
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Run the self-test with [`SelfTestConfig::default`].
+    pub fn self_test<D: DelayNs>(&mut self, delay: &mut D) -> Result<SelfTestReport, TmcError> {
+        self.self_test_with(&SelfTestConfig::default(), delay)
+    }
+
+    /// Run the self-test; see the module docs for the stages.
+    ///
+    /// The UART stage runs `init_uart`, so this also initializes the chip. The
+    /// motor must be free to turn a few steps: the motion stage enables the
+    /// outputs at `config.irun`, steps forward and back again, then restores
+    /// IHOLD_IRUN, position, direction and the enable state. A communication
+    /// error in the UART stage is reported in the result; later ones, and
+    /// refused motion, are returned as errors.
+    pub fn self_test_with<D: DelayNs>(
+        &mut self,
+        config: &SelfTestConfig,
+        delay: &mut D,
+    ) -> Result<SelfTestReport, TmcError> {
+        let mut report = SelfTestReport::new();
+        let checks = InitChecks {
+            expected_version: None,
+            fail_on_drv_err: false,
+            fail_on_uv_cp: false,
+        };
+        let init = match self.init_uart_with(&checks) {
+            Ok(init) => init,
+            Err(e) if e.register().is_some() => {
+                report.uart = StageResult::Failed;
+                report.uart_error = Some(e);
+                return Ok(report);
+            }
+            Err(e) => return Err(e),
+        };
+        report.uart = StageResult::Passed;
+        report.init = Some(init);
+        report.version = stage(config.expected_version.is_none_or(|v| v == init.version));
+        report.gstat = stage(!init.drv_err() && !init.uv_cp());
+        if report.gstat != StageResult::Passed {
+            return Ok(report);
+        }
+
+        let (per_step, consistent, status) = self.self_test_motion(config, delay)?;
+        report.mscnt_per_step = per_step;
+        report.drv_status = Some(status);
+        report.motion = stage(per_step != 0 && consistent);
+        report.open_load = stage(!status.ola && !status.olb && !status.short());
+        Ok(report)
+    }
+
+    /// Step forward and back at low current, restoring the driver settings
+    /// afterwards. Returns the MSCNT change of the first step, whether every
+    /// step changed it by as much and MSCNT returned to its start, and
+    /// DRV_STATUS at the far end.
+    fn self_test_motion<D: DelayNs>(
+        &mut self,
+        config: &SelfTestConfig,
+        delay: &mut D,
+    ) -> Result<(i32, bool, DrvStatus), TmcError> {
+        self.ensure_can_move()?;
+        let saved_current = self.shadow_register(REG_IHOLD_IRUN);
+        let was_enabled = matches!(self.state(), DriverState::Enabled | DriverState::Moving);
+        let position = self.position();
+        let clockwise = self.direction();
+
+        let ihold = Ihold::saturating(config.irun.get());
+        self.set_run_hold_current(config.irun, ihold, IholdDelay::saturating(0))?;
+        self.enable()?;
+        let result = self.self_test_steps(config, clockwise, delay);
+
+        // Put everything back even if the move failed.
+        let restore = match saved_current {
+            Some(raw) => self.write_register(REG_IHOLD_IRUN, raw),
+            None => Ok(()),
+        };
+        self.set_position(position);
+        let direction = self.set_direction(clockwise);
+        let enable = if was_enabled { Ok(()) } else { self.disable() };
+        let result = result?;
+        restore?;
+        direction?;
+        enable?;
+        Ok(result)
+    }
+
+    fn self_test_steps<D: DelayNs>(
+        &mut self,
+        config: &SelfTestConfig,
+        clockwise: bool,
+        delay: &mut D,
+    ) -> Result<(i32, bool, DrvStatus), TmcError> {
+        let steps = config.steps.max(1);
+        let start = (self.read_register(REG_MSCNT)? & MSCNT_MASK) as i32;
+        self.run_profile(ConstantRate::new(1, config.speed), delay)?;
+        let first = (self.read_register(REG_MSCNT)? & MSCNT_MASK) as i32;
+        let per_step = wrap_mscnt(first - start);
+        self.run_profile(ConstantRate::new(steps - 1, config.speed), delay)?;
+        let end = (self.read_register(REG_MSCNT)? & MSCNT_MASK) as i32;
+        let status = self.read_drv_status()?;
+        let progressed = wrap_mscnt(end - start) == wrap_mscnt(per_step * steps as i32);
+
+        self.set_direction(!clockwise)?;
+        self.run_profile(ConstantRate::new(steps, config.speed), delay)?;
+        let back = (self.read_register(REG_MSCNT)? & MSCNT_MASK) as i32;
+        Ok((per_step, progressed && back == start, status))
+    }
+}
@@ -0,0 +1,561 @@
+//! One call per main-loop iteration to run all background monitoring.
+//!
+//! The idle-current policy, the CRC quarantine backoff, the jog dead-man
+//! timeout, status polling, thermal throttling and telemetry sampling each want
+//! to be called regularly, and each costs UART time. [`Tmc2209Service`] owns the
+//! driver and runs whichever of them are due from [`Tmc2209Service::tick`],
+//! spending at most a fixed number of UART transactions per call so that the
+//! loop's worst-case latency stays bounded. Work that does not fit is deferred
+//! to the next tick, starting with whatever was skipped.
+//!
+//! Applications can add their own registers to the rotation with
+//! [`RegisterPoll`], each at its own interval and optionally only while the
+//! motor moves, e.g. DRV_STATUS every 100 ms and SG_RESULT every 10 ms. A poll
+//! made with [`RegisterPoll::watch_field`] also checks one field of the value
+//! and queues [`TmcEvent::FieldWatch`] when it starts satisfying a predicate,
+//! e.g. to find out when CS_ACTUAL drops below 8 without writing polling code.
+//!
+//! ```ignore
+//! let mut buf = [TelemetrySample::default(); 64];
+//! let mut polls = [RegisterPoll::every(REG_SG_RESULT, 10).while_moving()];
+//! let mut service = Tmc2209Service::new(driver, ServiceConfig::default())
+//!     .with_thermal(ThermalManager::new(ThermalConfig::default()))
+//!     .with_telemetry(Telemetry::new(&mut buf))
+//!     .with_polls(&mut polls);
+//! loop {
+//!     service.tick(millis())?;
+//!     let sg = service.polled_value(REG_SG_RESULT);
+//!     for event in service.poll_events() { /* ... */ }
+//! }
+//! ```
+//!
+//! Several axes on one bus share its bandwidth. [`MachineService`] owns one
+//! service per axis and splits a single per-tick budget between them, starting
+//! each tick with a different axis so that four busy axes cannot starve each
+//! other.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::events::TmcEvent;
+use crate::protocol::Crc8Provider;
+use crate::state::DriverState;
+use crate::telemetry::Telemetry;
+use crate::thermal::ThermalManager;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Scheduling parameters for [`Tmc2209Service`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServiceConfig {
+    /// Maximum UART transactions (register reads or writes) per tick
+    pub transaction_budget: u8,
+    /// Interval between `check_status` polls, in milliseconds
+    pub status_interval_ms: u32,
+    /// Interval between thermal manager updates, in milliseconds
+    pub thermal_interval_ms: u32,
+    /// Interval between telemetry samples, in milliseconds
+    pub telemetry_interval_ms: u32,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        ServiceConfig {
+            transaction_budget: 6,
+            status_interval_ms: 100,
+            thermal_interval_ms: 1_000,
+            telemetry_interval_ms: 20,
+        }
+    }
+}
+
+/// What a call to [`Tmc2209Service::tick`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TickReport {
+    /// Worst-case UART transactions of the tasks that ran
+    pub transactions: u8,
+    /// Tasks that were due but deferred for lack of budget
+    pub deferred: u8,
+}
+
+/// Field check attached to a [`RegisterPoll`] by `watch_field`.
+#[derive(Debug, Clone, Copy)]
+struct FieldWatch {
+    mask: u32,
+    shift: u32,
+    predicate: fn(u32) -> bool,
+    /// Whether the predicate held at the previous read
+    matched: bool,
+}
+
+// Predicates are compared by address, which is all that is needed to tell
+// two polls apart.
+impl PartialEq for FieldWatch {
+    fn eq(&self, other: &Self) -> bool {
+        self.mask == other.mask
+            && self.shift == other.shift
+            && self.matched == other.matched
+            && core::ptr::fn_addr_eq(self.predicate, other.predicate)
+    }
+}
+
+impl Eq for FieldWatch {}
+
+/// A register read periodically by [`Tmc2209Service::tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterPoll {
+    reg: u8,
+    interval_ms: u32,
+    while_moving: bool,
+    last_read_ms: Option<u32>,
+    value: Option<u32>,
+    watch: Option<FieldWatch>,
+}
+
+impl RegisterPoll {
+    /// Read `reg` every `interval_ms` milliseconds.
+    pub const fn every(reg: u8, interval_ms: u32) -> Self {
+        RegisterPoll {
+            reg,
+            interval_ms,
+            while_moving: false,
+            last_read_ms: None,
+            value: None,
+            watch: None,
+        }
+    }
+
+    /// Read `reg` every tick and queue [`TmcEvent::FieldWatch`] each time
+    /// `(value & mask) >> shift` starts satisfying `predicate`. The event fires
+    /// once per transition, not on every read while the predicate holds.
+    ///
+    /// Combine with [`Self::with_interval`] to read less often.
+    pub const fn watch_field(reg: u8, mask: u32, shift: u32, predicate: fn(u32) -> bool) -> Self {
+        let mut poll = Self::every(reg, 0);
+        poll.watch = Some(FieldWatch {
+            mask,
+            shift,
+            predicate,
+            matched: false,
+        });
+        poll
+    }
+
+    /// Read every `interval_ms` milliseconds instead.
+    pub const fn with_interval(mut self, interval_ms: u32) -> Self {
+        self.interval_ms = interval_ms;
+        self
+    }
+
+    /// Only read while the motor is moving (steps or VACTUAL).
+    pub const fn while_moving(mut self) -> Self {
+        self.while_moving = true;
+        self
+    }
+
+    /// Register address.
+    pub fn reg(&self) -> u8 {
+        self.reg
+    }
+
+    /// Value from the most recent read.
+    pub fn value(&self) -> Option<u32> {
+        self.value
+    }
+
+    /// Timestamp of the most recent read, in ms.
+    pub fn last_read_ms(&self) -> Option<u32> {
+        self.last_read_ms
+    }
+
+    /// Record a read, returning the watched field if it just started
+    /// satisfying the predicate.
+    fn record(&mut self, value: u32) -> Option<u32> {
+        self.value = Some(value);
+        let watch = self.watch.as_mut()?;
+        let field = (value & watch.mask).checked_shr(watch.shift).unwrap_or(0);
+        let matched = (watch.predicate)(field);
+        let fired = matched && !watch.matched;
+        watch.matched = matched;
+        fired.then_some(field)
+    }
+}
+
+/// What a call to [`MachineService::tick`] did, per axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineTickReport<const N: usize> {
+    /// Report of each axis' service; all zero for axes that got no budget
+    pub axes: [TickReport; N],
+    /// Error of each axis' service, if one of its tasks failed
+    pub errors: [Option<TmcError>; N],
+}
+
+impl<const N: usize> MachineTickReport<N> {
+    /// Transactions spent over all axes.
+    pub fn transactions(&self) -> u32 {
+        self.axes.iter().map(|r| r.transactions as u32).sum()
+    }
+
+    /// Tasks deferred over all axes.
+    pub fn deferred(&self) -> u32 {
+        self.axes.iter().map(|r| r.deferred as u32).sum()
+    }
+
+    /// `true` if no axis reported an error.
+    pub fn all_ok(&self) -> bool {
+        self.errors.iter().all(Option::is_none)
+    }
+}
+
+/// Background work done by the service, in round-robin order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Task {
+    Comm,
+    Jog,
+    Idle,
+    Status,
+    Thermal,
+    Telemetry,
+    /// Entry of the user's [`RegisterPoll`] list
+    Poll(usize),
+}
+
+const TASKS: [Task; 6] = [
+    Task::Comm,
+    Task::Jog,
+    Task::Idle,
+    Task::Status,
+    Task::Thermal,
+    Task::Telemetry,
+];
+
+impl Task {
+    /// Transactions the task may use in the worst case.
+    fn cost(self) -> u8 {
+        match self {
+            Task::Comm => 0,
+            Task::Jog | Task::Idle | Task::Poll(_) => 1,
+            // GSTAT read, GSTAT clear, DRV_STATUS read
+            Task::Status => 3,
+            // DRV_STATUS read, IHOLD_IRUN write
+            Task::Thermal => 2,
+            // SG_RESULT, TSTEP and DRV_STATUS reads
+            Task::Telemetry => 3,
+        }
+    }
+
+    fn uses_uart(self) -> bool {
+        self != Task::Comm
+    }
+}
+
+/// Full UART driver plus the background tasks that keep it healthy.
+///
+/// Call [`Self::tick`] every loop iteration; foreground calls go through
+/// [`Self::driver`].
+pub struct Tmc2209Service<'a, DRV> {
+    driver: DRV,
+    config: ServiceConfig,
+    thermal: Option<ThermalManager>,
+    telemetry: Option<Telemetry<'a>>,
+    polls: &'a mut [RegisterPoll],
+    last_run_ms: [Option<u32>; TASKS.len()],
+    next: usize,
+}
+
+impl<'a, EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209Service<'a, Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Take over a configured driver. Thermal management and telemetry are off
+    /// until attached with [`Self::with_thermal`] and [`Self::with_telemetry`].
+    pub fn new(
+        driver: Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>,
+        config: ServiceConfig,
+    ) -> Self {
+        Tmc2209Service {
+            driver,
+            config,
+            thermal: None,
+            telemetry: None,
+            polls: &mut [],
+            last_run_ms: [None; TASKS.len()],
+            next: 0,
+        }
+    }
+
+    /// Run `manager` every `thermal_interval_ms`.
+    pub fn with_thermal(mut self, manager: ThermalManager) -> Self {
+        self.thermal = Some(manager);
+        self
+    }
+
+    /// Sample into `telemetry` every `telemetry_interval_ms`.
+    pub fn with_telemetry(mut self, telemetry: Telemetry<'a>) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    /// Read the registers in `polls` at their intervals, interleaved with the
+    /// built-in tasks.
+    pub fn with_polls(mut self, polls: &'a mut [RegisterPoll]) -> Self {
+        self.polls = polls;
+        self
+    }
+
+    /// Scheduling parameters.
+    pub fn config(&self) -> &ServiceConfig {
+        &self.config
+    }
+
+    /// The underlying driver.
+    pub fn driver(
+        &mut self,
+    ) -> &mut Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC> {
+        &mut self.driver
+    }
+
+    /// The attached thermal manager.
+    pub fn thermal(&self) -> Option<&ThermalManager> {
+        self.thermal.as_ref()
+    }
+
+    /// The attached telemetry recorder.
+    pub fn telemetry(&self) -> Option<&Telemetry<'a>> {
+        self.telemetry.as_ref()
+    }
+
+    /// The registered polls, with their latest values.
+    pub fn polls(&self) -> &[RegisterPoll] {
+        self.polls
+    }
+
+    /// Latest polled value of `reg`, if it is polled and has been read.
+    pub fn polled_value(&self, reg: u8) -> Option<u32> {
+        self.polls
+            .iter()
+            .filter(|poll| poll.reg == reg)
+            .filter_map(|poll| Some((poll.last_read_ms?, poll.value?)))
+            .max_by_key(|&(at, _)| at)
+            .map(|(_, value)| value)
+    }
+
+    /// Give the driver back.
+    pub fn into_inner(
+        self,
+    ) -> Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC> {
+        self.driver
+    }
+
+    /// Drain events queued by the driver and the background tasks.
+    pub fn poll_events(&mut self) -> impl Iterator<Item = TmcEvent> + '_ {
+        self.driver.poll_events()
+    }
+
+    /// Run the background tasks that are due, within the transaction budget.
+    ///
+    /// Call this every loop iteration with a millisecond timestamp (wrapping is
+    /// fine). Tasks are visited round-robin, starting with the first one
+    /// deferred by the previous tick, so a busy tick cannot starve any of
+    /// them. UART tasks are skipped while the CRC quarantine has traffic
+    /// suspended. A failing task is rescheduled normally and its error
+    /// returned; tasks after it run on the next tick.
+    pub fn tick(&mut self, now_ms: u32) -> Result<TickReport, TmcError> {
+        let mut report = TickReport::default();
+        self.run_due(now_ms, self.config.transaction_budget, &mut report)?;
+        Ok(report)
+    }
+
+    /// Body of [`Self::tick`] with an explicit budget. `report` is filled in
+    /// even when a task fails.
+    fn run_due(
+        &mut self,
+        now_ms: u32,
+        budget: u8,
+        report: &mut TickReport,
+    ) -> Result<(), TmcError> {
+        let count = TASKS.len() + self.polls.len();
+        let start = self.next % count;
+        let mut first_deferred = None;
+        for offset in 0..count {
+            let index = (start + offset) % count;
+            let task = self.task(index);
+            if !self.is_due(index, task, now_ms) {
+                continue;
+            }
+            if task.uses_uart() && self.driver.is_comm_suspended() {
+                continue;
+            }
+            let cost = task.cost();
+            if report.transactions.saturating_add(cost) > budget {
+                report.deferred = report.deferred.saturating_add(1);
+                first_deferred.get_or_insert(index);
+                continue;
+            }
+            report.transactions += cost;
+            if let Some(last) = self.last_run_ms.get_mut(index) {
+                *last = Some(now_ms);
+            }
+            self.next = (index + 1) % count;
+            self.run(task, now_ms)?;
+        }
+        if let Some(index) = first_deferred {
+            self.next = index;
+        }
+        Ok(())
+    }
+
+    fn task(&self, index: usize) -> Task {
+        TASKS
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| Task::Poll(index - TASKS.len()))
+    }
+
+    fn is_moving(&self) -> bool {
+        self.driver.state() == DriverState::Moving || self.driver.vactual() != 0
+    }
+
+    fn is_due(&self, index: usize, task: Task, now_ms: u32) -> bool {
+        let last = self.last_run_ms.get(index).copied().flatten();
+        let (last, interval) = match task {
+            Task::Comm | Task::Idle => return true,
+            Task::Jog => return self.driver.is_jogging(),
+            Task::Status => (last, self.config.status_interval_ms),
+            Task::Thermal if self.thermal.is_some() => (last, self.config.thermal_interval_ms),
+            Task::Telemetry if self.telemetry.is_some() => {
+                (last, self.config.telemetry_interval_ms)
+            }
+            Task::Thermal | Task::Telemetry => return false,
+            Task::Poll(i) => {
+                let poll = &self.polls[i];
+                if poll.while_moving && !self.is_moving() {
+                    return false;
+                }
+                (poll.last_read_ms, poll.interval_ms)
+            }
+        };
+        last.is_none_or(|last| now_ms.wrapping_sub(last) >= interval)
+    }
+
+    fn run(&mut self, task: Task, now_ms: u32) -> Result<(), TmcError> {
+        match task {
+            Task::Comm => {
+                self.driver.poll_comm(now_ms);
+            }
+            Task::Jog => {
+                self.driver.poll_jog(now_ms)?;
+            }
+            Task::Idle => {
+                self.driver.poll_idle(now_ms)?;
+            }
+            Task::Status => {
+                self.driver.check_status()?;
+            }
+            Task::Thermal => {
+                if let Some(thermal) = self.thermal.as_mut() {
+                    thermal.update(&mut self.driver, now_ms)?;
+                }
+            }
+            Task::Telemetry => {
+                if let Some(telemetry) = self.telemetry.as_mut() {
+                    telemetry.sample(&mut self.driver, now_ms)?;
+                }
+            }
+            Task::Poll(i) => {
+                let reg = self.polls[i].reg;
+                self.polls[i].last_read_ms = Some(now_ms);
+                let value = self.driver.read_register_blocking(reg)?;
+                if let Some(field) = self.polls[i].record(value) {
+                    self.driver.push_event(TmcEvent::FieldWatch { reg, field });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Services of several axes sharing one UART transaction budget.
+///
+/// Each tick hands the budget to the axes in turn, each limited to its own
+/// `transaction_budget` as well, and starts with the axis that was cut short
+/// last time (or the next one if none was). An axis whose task fails does not
+/// hold up the others: its error is reported and the remaining axes still run.
+pub struct MachineService<'a, DRV, const N: usize> {
+    axes: [Tmc2209Service<'a, DRV>; N],
+    transaction_budget: u8,
+    next: usize,
+}
+
+impl<'a, DRV, const N: usize> MachineService<'a, DRV, N> {
+    /// Schedule `axes` within `transaction_budget` UART transactions per tick
+    /// in total.
+    pub fn new(axes: [Tmc2209Service<'a, DRV>; N], transaction_budget: u8) -> Self {
+        MachineService {
+            axes,
+            transaction_budget,
+            next: 0,
+        }
+    }
+
+    /// Transactions shared by all axes per tick.
+    pub fn transaction_budget(&self) -> u8 {
+        self.transaction_budget
+    }
+
+    /// Service of axis `index`.
+    pub fn axis(&mut self, index: usize) -> Option<&mut Tmc2209Service<'a, DRV>> {
+        self.axes.get_mut(index)
+    }
+
+    /// Give the services back.
+    pub fn into_inner(self) -> [Tmc2209Service<'a, DRV>; N] {
+        self.axes
+    }
+}
+
+impl<'a, EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC, const N: usize>
+    MachineService<'a, Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>, N>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Run the due background tasks of every axis within the shared budget.
+    ///
+    /// Call this every loop iteration instead of the axes' own `tick`.
+    pub fn tick(&mut self, now_ms: u32) -> MachineTickReport<N> {
+        let mut report = MachineTickReport {
+            axes: [TickReport::default(); N],
+            errors: [None; N],
+        };
+        if N == 0 {
+            return report;
+        }
+        let start = self.next % N;
+        let mut left = self.transaction_budget;
+        let mut first_short = None;
+        for offset in 0..N {
+            let index = (start + offset) % N;
+            let service = &mut self.axes[index];
+            let budget = left.min(service.config.transaction_budget);
+            let axis = &mut report.axes[index];
+            if let Err(e) = service.run_due(now_ms, budget, axis) {
+                report.errors[index] = Some(e);
+            }
+            left -= axis.transactions;
+            if axis.deferred > 0 {
+                first_short.get_or_insert(index);
+            }
+        }
+        self.next = first_short.unwrap_or((start + 1) % N);
+        report
+    }
+}
@@ -0,0 +1,101 @@
+//! One coherent view of the driver's configuration.
+//!
+//! Several configuration registers are write-only. [`EffectiveSettings`]
+//! combines the driver's shadow copies of those with the readable
+//! configuration registers, for display and logging.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+use crate::values::{Ihold, IholdDelay, Irun, Sgthrs, Toff};
+
+/// Configuration as the driver believes it to be.
+///
+/// Fields from write-only registers are `None` until the driver has written
+/// them; they reflect what was sent, not what the chip holds after a reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectiveSettings {
+    /// GCONF as read from the chip
+    pub gconf: u32,
+    /// CHOPCONF as read from the chip
+    pub chopconf: u32,
+    /// PWMCONF as read from the chip
+    pub pwmconf: u32,
+    /// spreadCycle forced (GCONF.en_spreadcycle)
+    pub spreadcycle: bool,
+    /// Motor direction inverted (GCONF.shaft)
+    pub shaft: bool,
+    /// Chopper off time (CHOPCONF.toff); 0 means the outputs are off
+    pub toff: Toff,
+    /// Microsteps per full step (from CHOPCONF.mres)
+    pub microsteps: u16,
+    /// High-sensitivity current sense (CHOPCONF.vsense)
+    pub vsense: bool,
+    /// Interpolation to 256 microsteps (CHOPCONF.intpol)
+    pub interpolation: bool,
+    /// Run current scale (IHOLD_IRUN, shadowed)
+    pub irun: Option<Irun>,
+    /// Hold current scale (IHOLD_IRUN, shadowed)
+    pub ihold: Option<Ihold>,
+    /// Run-to-hold ramp delay (IHOLD_IRUN, shadowed)
+    pub ihold_delay: Option<IholdDelay>,
+    /// Standstill delay before power-down (TPOWERDOWN, shadowed)
+    pub tpowerdown: Option<u8>,
+    /// stealthChop upper threshold (TPWMTHRS, shadowed)
+    pub tpwmthrs: Option<u32>,
+    /// CoolStep/StallGuard lower threshold (TCOOLTHRS, shadowed)
+    pub tcoolthrs: Option<u32>,
+    /// StallGuard threshold (SGTHRS, shadowed)
+    pub sgthrs: Option<Sgthrs>,
+    /// CoolStep configuration (COOLCONF, shadowed)
+    pub coolconf: Option<u32>,
+    /// Internal step generator velocity (VACTUAL)
+    pub vactual: i32,
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Read GCONF, CHOPCONF and PWMCONF and combine them with the shadow
+    /// copies of the write-only registers.
+    pub fn effective_settings(&mut self) -> Result<EffectiveSettings, TmcError> {
+        let gconf = self.read_register_blocking(REG_GCONF)?;
+        let chopconf = self.read_register_blocking(REG_CHOPCONF)?;
+        let pwmconf = self.read_register_blocking(REG_PWMCONF)?;
+        let ihold_irun = self.shadow_register(REG_IHOLD_IRUN);
+        let field = |raw: u32, shift: u32, mask: u32| ((raw >> shift) & mask) as u8;
+
+        Ok(EffectiveSettings {
+            gconf,
+            chopconf,
+            pwmconf,
+            spreadcycle: gconf & GCONF_EN_SPREADCYCLE != 0,
+            shaft: gconf & GCONF_SHAFT != 0,
+            toff: Toff::saturating(field(chopconf, 0, 0x0F)),
+            microsteps: 256 >> field(chopconf, 24, 0x0F).min(8),
+            vsense: chopconf & CHOPCONF_VSENSE != 0,
+            interpolation: chopconf & CHOPCONF_INTPOL != 0,
+            irun: ihold_irun.map(|raw| Irun::saturating(field(raw, 8, 0x1F))),
+            ihold: ihold_irun.map(|raw| Ihold::saturating(field(raw, 0, 0x1F))),
+            ihold_delay: ihold_irun.map(|raw| IholdDelay::saturating(field(raw, 16, 0x0F))),
+            tpowerdown: self.shadow_register(REG_TPOWERDOWN).map(|raw| raw as u8),
+            tpwmthrs: self.shadow_register(REG_TPWMTHRS),
+            tcoolthrs: self.shadow_register(REG_TCOOLTHRS),
+            sgthrs: self
+                .shadow_register(REG_SGTHRS)
+                .map(|raw| Sgthrs::saturating(raw as u8)),
+            coolconf: self.shadow_register(REG_COOLCONF),
+            vactual: self.vactual(),
+        })
+    }
+}
@@ -0,0 +1,58 @@
+//! Shadow copies of write-only registers.
+//!
+//! Registers such as IHOLD_IRUN or VACTUAL cannot be read back, so the driver
+//! remembers the last value it wrote to each of them.
+
+use crate::registers::*;
+
+/// Write-only registers the driver keeps a shadow copy of.
+const SHADOWED: [u8; 8] = [
+    REG_SLAVECONF,
+    REG_IHOLD_IRUN,
+    REG_TPOWERDOWN,
+    REG_TPWMTHRS,
+    REG_TCOOLTHRS,
+    REG_VACTUAL,
+    REG_SGTHRS,
+    REG_COOLCONF,
+];
+
+/// Last values written to the write-only registers, `None` until first written.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ShadowRegisters {
+    values: [Option<u32>; SHADOWED.len()],
+}
+
+impl ShadowRegisters {
+    fn slot(reg: u8) -> Option<usize> {
+        SHADOWED.iter().position(|&r| r == reg)
+    }
+
+    /// Last value written to `reg`, if it is shadowed and has been written.
+    pub(crate) fn get(&self, reg: u8) -> Option<u32> {
+        Self::slot(reg).and_then(|i| self.values[i])
+    }
+
+    /// FNV-1a hash of the written configuration registers, to tell whether
+    /// two configurations are the same. VACTUAL is motion, not configuration,
+    /// and is left out.
+    pub(crate) fn fingerprint(&self) -> u32 {
+        let mut hash: u32 = 0x811C_9DC5;
+        for (&reg, value) in SHADOWED.iter().zip(self.values.iter()) {
+            let Some(value) = value.filter(|_| reg != REG_VACTUAL) else {
+                continue;
+            };
+            for byte in core::iter::once(reg).chain(value.to_le_bytes()) {
+                hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+            }
+        }
+        hash
+    }
+
+    /// Record a write. Registers that are not shadowed are ignored.
+    pub(crate) fn record(&mut self, reg: u8, value: u32) {
+        if let Some(i) = Self::slot(reg) {
+            self.values[i] = Some(value);
+        }
+    }
+}
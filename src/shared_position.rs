@@ -0,0 +1,39 @@
+//! Position counter readable from other contexts.
+//!
+//! Once the STEP/DIR half of a driver lives in a timer interrupt, a display or
+//! telemetry task can no longer borrow it to call `position`. A
+//! [`SharedPosition`] in a `static` is updated with every step and read from
+//! anywhere. It is a single 32-bit atomic written with plain loads and stores,
+//! so it works on cores without compare-and-swap (Cortex-M0) and a reader
+//! never sees a half-written value.
+
+use core::sync::atomic::{AtomicI32, Ordering};
+
+/// Copy of a driver's position counter, see `share_position`.
+///
+/// ```ignore
+/// static POSITION: SharedPosition = SharedPosition::new(0);
+///
+/// motion.share_position(Some(&POSITION)); // step ISR owns `motion`
+/// // ...
+/// display.show(POSITION.get());
+/// ```
+#[derive(Debug, Default)]
+pub struct SharedPosition(AtomicI32);
+
+impl SharedPosition {
+    /// A counter holding `position` until a driver is attached.
+    pub const fn new(position: i32) -> Self {
+        SharedPosition(AtomicI32::new(position))
+    }
+
+    /// Last position published by the driver, in steps.
+    pub fn get(&self) -> i32 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Publish `position`. Only the attached driver writes.
+    pub(crate) fn set(&self, position: i32) {
+        self.0.store(position, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,195 @@
+//! Snapshots of the chip configuration.
+//!
+//! A [`Tmc2209Snapshot`] holds every configuration register: readable ones as
+//! read from the chip, write-only ones from the driver's shadow copies. Its
+//! `Display` output is a stable `REGISTER.field=value` listing, one line per
+//! field, that can be dumped over a serial console and diffed between two
+//! machines.
+//!
+//! For storage and provisioning, [`Tmc2209Snapshot::to_bytes`] packs it into
+//! a fixed, versioned blob (little-endian):
+//! `[version, known: u16, values: 10 × u32, crc]`, where bit `i` of `known`
+//! says whether value `i` (in [`SNAPSHOT_REGISTERS`] order) is present.
+
+use core::fmt;
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::{calc_crc8, Crc8Provider};
+use crate::registers::*;
+use crate::regmap::{lookup_register, TMC2209_REGISTERS};
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Registers captured in a [`Tmc2209Snapshot`], in output order.
+pub const SNAPSHOT_REGISTERS: [u8; 10] = [
+    REG_GCONF,
+    REG_FACTORY_CONF,
+    REG_IHOLD_IRUN,
+    REG_TPOWERDOWN,
+    REG_TPWMTHRS,
+    REG_TCOOLTHRS,
+    REG_SGTHRS,
+    REG_COOLCONF,
+    REG_CHOPCONF,
+    REG_PWMCONF,
+];
+
+/// Length of a snapshot blob from [`Tmc2209Snapshot::to_bytes`].
+pub const SNAPSHOT_BLOB_LEN: usize = 3 + 4 * SNAPSHOT_REGISTERS.len() + 1;
+
+/// Format version written to byte 0 of a snapshot blob.
+pub const SNAPSHOT_BLOB_VERSION: u8 = 1;
+
+/// Configuration register values of one driver.
+///
+/// Write-only registers the driver has not written are unknown (`None`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tmc2209Snapshot {
+    values: [Option<u32>; SNAPSHOT_REGISTERS.len()],
+}
+
+impl Tmc2209Snapshot {
+    /// Value of `reg`, `None` if unknown or not part of a snapshot.
+    pub fn get(&self, reg: u8) -> Option<u32> {
+        let index = SNAPSHOT_REGISTERS.iter().position(|&r| r == reg)?;
+        self.values[index]
+    }
+
+    /// Set the value of `reg`. Returns [`TmcError::InvalidArgument`] if `reg`
+    /// is not in [`SNAPSHOT_REGISTERS`].
+    pub fn set(&mut self, reg: u8, value: Option<u32>) -> Result<(), TmcError> {
+        let index = SNAPSHOT_REGISTERS
+            .iter()
+            .position(|&r| r == reg)
+            .ok_or(TmcError::InvalidArgument)?;
+        self.values[index] = value;
+        Ok(())
+    }
+
+    /// Registers and their values, in [`SNAPSHOT_REGISTERS`] order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, Option<u32>)> + '_ {
+        SNAPSHOT_REGISTERS.iter().copied().zip(self.values)
+    }
+
+    /// Pack the snapshot into a CRC-protected blob, see the module docs for
+    /// the layout. Unknown values are stored as 0.
+    pub fn to_bytes(&self) -> [u8; SNAPSHOT_BLOB_LEN] {
+        let mut blob = [0u8; SNAPSHOT_BLOB_LEN];
+        let mut known = 0u16;
+        for (i, value) in self.values.iter().enumerate() {
+            if let Some(value) = value {
+                known |= 1 << i;
+                blob[3 + 4 * i..7 + 4 * i].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        blob[0] = SNAPSHOT_BLOB_VERSION;
+        blob[1..3].copy_from_slice(&known.to_le_bytes());
+        blob[SNAPSHOT_BLOB_LEN - 1] = calc_crc8(&blob[..SNAPSHOT_BLOB_LEN - 1]);
+        blob
+    }
+
+    /// Unpack a blob made by [`Self::to_bytes`]. Returns
+    /// [`TmcError::InvalidArgument`] for a blob that is truncated, from another
+    /// format version or corrupted.
+    pub fn from_bytes(blob: &[u8]) -> Result<Self, TmcError> {
+        let blob = blob
+            .get(..SNAPSHOT_BLOB_LEN)
+            .ok_or(TmcError::InvalidArgument)?;
+        let crc = calc_crc8(&blob[..SNAPSHOT_BLOB_LEN - 1]);
+        if blob[0] != SNAPSHOT_BLOB_VERSION || crc != blob[SNAPSHOT_BLOB_LEN - 1] {
+            return Err(TmcError::InvalidArgument);
+        }
+        let known = u16::from_le_bytes([blob[1], blob[2]]);
+        let mut snapshot = Tmc2209Snapshot::default();
+        for (i, value) in snapshot.values.iter_mut().enumerate() {
+            if known & (1 << i) != 0 {
+                let b = &blob[3 + 4 * i..7 + 4 * i];
+                *value = Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+impl fmt::Display for Tmc2209Snapshot {
+    /// One `REGISTER.field=value` line per field, `REGISTER=unknown` for
+    /// registers without a known value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (reg, value) in self.iter() {
+            let Some(def) = lookup_register(TMC2209_REGISTERS, reg) else {
+                continue;
+            };
+            match value {
+                None => writeln!(f, "{}=unknown", def.name)?,
+                Some(raw) if def.fields.is_empty() => writeln!(f, "{}=0x{:08X}", def.name, raw)?,
+                Some(raw) => {
+                    for field in def.fields {
+                        writeln!(f, "{}.{}={}", def.name, field.name, field.get(raw))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Read the readable configuration registers and combine them with the
+    /// shadow copies of the write-only ones.
+    ///
+    /// The readable registers are read with [`Self::read_registers_all`], so
+    /// they are pipelined where SENDDELAY permits.
+    pub fn snapshot(&mut self) -> Result<Tmc2209Snapshot, TmcError> {
+        let mut snapshot = Tmc2209Snapshot::default();
+        let mut readable = [0u8; SNAPSHOT_REGISTERS.len()];
+        let mut count = 0;
+        for (i, &reg) in SNAPSHOT_REGISTERS.iter().enumerate() {
+            if lookup_register(TMC2209_REGISTERS, reg).is_some_and(|def| def.access.is_readable()) {
+                readable[count] = reg;
+                count += 1;
+            } else {
+                snapshot.values[i] = self.shadow_register(reg);
+            }
+        }
+        let mut raw = [0u32; SNAPSHOT_REGISTERS.len()];
+        self.read_registers_all(&readable[..count], &mut raw)?;
+        for (&reg, value) in readable[..count].iter().zip(raw) {
+            snapshot.set(reg, Some(value))?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Write every known value of `snapshot` to the chip, e.g. one received
+    /// from [`Tmc2209Snapshot::from_bytes`].
+    ///
+    /// FACTORY_CONF is skipped: it holds the clock and temperature trim of
+    /// the chip the snapshot was taken from. Stops at the first failed write.
+    pub fn apply_snapshot(&mut self, snapshot: &Tmc2209Snapshot) -> Result<(), TmcError> {
+        for (reg, value) in snapshot.iter() {
+            match value {
+                Some(value) if reg != REG_FACTORY_CONF => self.write_register(reg, value)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Take a [`Self::snapshot`] and print it as `REGISTER.field=value` lines.
+    ///
+    /// The format is stable, so dumps from two machines can be diffed.
+    /// Returns [`TmcError::FormatError`] if `out` fails.
+    pub fn format_config<W: fmt::Write>(&mut self, out: &mut W) -> Result<(), TmcError> {
+        let snapshot = self.snapshot()?;
+        write!(out, "{}", snapshot).map_err(|_| TmcError::FormatError)
+    }
+}
@@ -0,0 +1,250 @@
+//! Running step generation and UART diagnostics side by side.
+//!
+//! `split` separates the STEP/DIR/EN pins into a [`MotionHandle`] that can
+//! live in a timer interrupt, from a UART-only driver that stays in the main
+//! loop for polling DRV_STATUS, StallGuard and so on. [`StepTiming`] measures
+//! the resulting step jitter on target.
+
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::odometer::Odometer;
+use crate::pins::NoPin;
+use crate::protocol::Crc8Provider;
+use crate::shared_position::SharedPosition;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// STEP/DIR/EN half of a split driver, see [`Tmc2209FullUartDiagnosticsAndControl::split`].
+///
+/// Only toggles pins and counts steps: no UART access, no allocation and no
+/// waiting, so it is safe to drive from an interrupt handler.
+pub struct MotionHandle<EN, STEP, DIR> {
+    en: EN,
+    step: STEP,
+    dir: DIR,
+    position: i32,
+    clockwise: bool,
+    inverted: bool,
+    odometer: Odometer,
+    shared_position: Option<&'static SharedPosition>,
+}
+
+impl<EN, STEP, DIR> MotionHandle<EN, STEP, DIR>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+{
+    /// Enable the motor driver outputs (EN low).
+    pub fn enable(&mut self) -> Result<(), TmcError> {
+        self.en.set_low().map_err(|_| TmcError::PinError)
+    }
+
+    /// Disable the motor driver outputs (EN high).
+    pub fn disable(&mut self) -> Result<(), TmcError> {
+        self.en.set_high().map_err(|_| TmcError::PinError)
+    }
+
+    /// Set direction. `true` => DIR pin HIGH.
+    pub fn set_direction(&mut self, clockwise: bool) -> Result<(), TmcError> {
+        if clockwise {
+            self.dir.set_high().map_err(|_| TmcError::PinError)?;
+        } else {
+            self.dir.set_low().map_err(|_| TmcError::PinError)?;
+        }
+        self.clockwise = clockwise;
+        Ok(())
+    }
+
+    /// Last direction set with [`Self::set_direction`].
+    pub fn direction(&self) -> bool {
+        self.clockwise
+    }
+
+    /// Issue one step pulse and count it.
+    ///
+    /// The pulse is as short as two pin writes; insert a delay between
+    /// separate calls if the pin toggles faster than the 100 ns minimum.
+    #[inline]
+    pub fn step_pulse(&mut self) -> Result<(), TmcError> {
+        let (active, idle) = if self.inverted {
+            (false, true)
+        } else {
+            (true, false)
+        };
+        self.step
+            .set_state(active.into())
+            .map_err(|_| TmcError::PinError)?;
+        self.position = self
+            .position
+            .wrapping_add(if self.clockwise { 1 } else { -1 });
+        self.odometer.record_step(self.clockwise);
+        self.publish_position();
+        self.step
+            .set_state(idle.into())
+            .map_err(|_| TmcError::PinError)
+    }
+
+    /// Position counter, in steps.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Overwrite the position counter.
+    pub fn set_position(&mut self, position: i32) {
+        self.position = position;
+        self.publish_position();
+    }
+
+    /// Keep `shared` up to date with the position counter, see
+    /// [`Tmc2209FullUartDiagnosticsAndControl::share_position`]. A counter
+    /// attached to the driver before `split` moves here automatically.
+    pub fn share_position(&mut self, shared: Option<&'static SharedPosition>) {
+        self.shared_position = shared;
+        self.publish_position();
+    }
+
+    #[inline]
+    fn publish_position(&self) {
+        if let Some(shared) = self.shared_position {
+            shared.set(self.position);
+        }
+    }
+
+    /// Steps and direction reversals, including those issued before the split.
+    pub fn odometer(&self) -> Odometer {
+        self.odometer
+    }
+}
+
+/// UART-only half of a split driver. Its pin operations are no-ops.
+pub type UartHandle<SERIAL, const HISTORY: usize, CRC> =
+    Tmc2209FullUartDiagnosticsAndControl<NoPin, NoPin, NoPin, SERIAL, HISTORY, CRC>;
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Separate the pins from the UART so that stepping and diagnostics can
+    /// run in different contexts, e.g. steps from a timer interrupt and
+    /// status polling from the main loop.
+    ///
+    /// The halves share no state, so a slow or timing-out read cannot delay
+    /// a step. If the serial port is itself shared with interrupt context,
+    /// pass it as a `SharedSerial` (feature `critical-section`) so
+    /// that UART polling holds the critical section only for one byte-sized
+    /// operation at a time.
+    ///
+    /// The motion handle takes over the position counter and its
+    /// [`crate::SharedPosition`], odometer, direction and STEP polarity. The UART half keeps everything else; its step-based motion
+    /// helpers (`move_by`, homing, ...) no longer reach the pins, while
+    /// VACTUAL-based motion keeps working. A move started with `start_move`
+    /// is abandoned.
+    pub fn split(
+        mut self,
+    ) -> (
+        MotionHandle<EN, STEP, DIR>,
+        UartHandle<SERIAL, HISTORY, CRC>,
+    ) {
+        self.cancel_move();
+        let position = self.position();
+        let clockwise = self.direction();
+        let inverted = self.step_pulse_shape().inverted;
+        let odometer = self.odometer();
+        let shared_position = self.shared_position();
+        self.share_position(None);
+        let (en, step, dir, uart) = self.replace_pins(NoPin, NoPin, NoPin);
+        let motion = MotionHandle {
+            en,
+            step,
+            dir,
+            position,
+            clockwise,
+            inverted,
+            odometer,
+            shared_position,
+        };
+        (motion, uart)
+    }
+
+    /// Put a split driver back together, taking the position, shared
+    /// position, odometer and direction from `motion`.
+    pub fn unsplit(
+        motion: MotionHandle<EN, STEP, DIR>,
+        uart: UartHandle<SERIAL, HISTORY, CRC>,
+    ) -> Self {
+        let (_, _, _, mut driver) = uart.replace_pins(motion.en, motion.step, motion.dir);
+        driver.set_position(motion.position);
+        driver.set_odometer(motion.odometer);
+        driver.share_position(motion.shared_position);
+        driver.set_split_direction(motion.clockwise);
+        driver
+    }
+}
+
+/// Step interval statistics for verifying timing on target.
+///
+/// Call [`Self::record`] from the step interrupt with a free-running timestamp
+/// (e.g. a cycle counter converted to ns) while the main loop hammers the
+/// UART, then compare [`Self::max_jitter_ns`] against the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepTiming {
+    period_ns: u32,
+    last_ns: Option<u32>,
+    max_late_ns: u32,
+    max_early_ns: u32,
+    steps: u32,
+}
+
+impl StepTiming {
+    /// Track steps that should be `period_ns` apart.
+    pub const fn new(period_ns: u32) -> Self {
+        StepTiming {
+            period_ns,
+            last_ns: None,
+            max_late_ns: 0,
+            max_early_ns: 0,
+            steps: 0,
+        }
+    }
+
+    /// Record a step at `now_ns` (wrapping is fine).
+    pub fn record(&mut self, now_ns: u32) {
+        if let Some(last) = self.last_ns {
+            let interval = now_ns.wrapping_sub(last);
+            if interval >= self.period_ns {
+                self.max_late_ns = self.max_late_ns.max(interval - self.period_ns);
+            } else {
+                self.max_early_ns = self.max_early_ns.max(self.period_ns - interval);
+            }
+        }
+        self.last_ns = Some(now_ns);
+        self.steps = self.steps.wrapping_add(1);
+    }
+
+    /// Largest delay of a step behind its schedule, in ns.
+    pub fn max_late_ns(&self) -> u32 {
+        self.max_late_ns
+    }
+
+    /// Largest deviation of a step interval from the period either way, in ns.
+    pub fn max_jitter_ns(&self) -> u32 {
+        self.max_late_ns.max(self.max_early_ns)
+    }
+
+    /// Steps recorded.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Forget all measurements.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.period_ns);
+    }
+}
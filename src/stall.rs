@@ -0,0 +1,148 @@
+//! StallGuard4 stall detection.
+//!
+//! The DIAG pin goes high on a driver error (overtemperature shutdown or a
+//! short) and, while TCOOLTHRS ≥ TSTEP, on a stall. [`DiagOutput`] names the
+//! two useful routings so a DIAG edge can be told apart.
+
+use crate::fields::DrvStatus;
+use crate::values::Percent;
+
+/// What the DIAG pin signals, set with `set_diag_output`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagOutput {
+    /// Stalls above the TCOOLTHRS velocity. Driver errors cannot be masked
+    /// and still raise DIAG, so an edge needs a DRV_STATUS read to classify.
+    #[default]
+    StallOnly,
+    /// Driver errors only: TCOOLTHRS = 0, which also disables CoolStep.
+    ErrorFlags,
+}
+
+/// Cause of a DIAG edge, see [`StallDetector::diag_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagEvent {
+    /// StallGuard4 detected a stall
+    Stall,
+    /// Overtemperature shutdown or a short circuit
+    DriverError,
+}
+
+/// How much to relax the stall threshold as the chip heats up.
+///
+/// SG_RESULT drifts with coil temperature, so a threshold tuned on a cold
+/// motor starts reporting false stalls after long runs. Each field is the
+/// reduction of SGTHRS applied while the corresponding DRV_STATUS flag is
+/// set; the hottest flag seen wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SgTemperatureCurve {
+    /// Reduction while the overtemperature pre-warning (otpw) is set
+    pub otpw: Percent,
+    /// Reduction above 120°C
+    pub t120: Percent,
+    /// Reduction above 143°C
+    pub t143: Percent,
+    /// Reduction above 150°C
+    pub t150: Percent,
+    /// Reduction above 157°C
+    pub t157: Percent,
+}
+
+impl SgTemperatureCurve {
+    /// Reduction for the temperature flags in `status`.
+    pub fn reduction(&self, status: &DrvStatus) -> Percent {
+        if status.t157 {
+            self.t157
+        } else if status.t150 {
+            self.t150
+        } else if status.t143 {
+            self.t143
+        } else if status.t120 {
+            self.t120
+        } else if status.otpw {
+            self.otpw
+        } else {
+            Percent::ZERO
+        }
+    }
+}
+
+/// Decides whether a SG_RESULT reading indicates a stall.
+///
+/// The chip flags a stall on DIAG when `SG_RESULT <= 2 * SGTHRS`; this applies
+/// the same rule to values read over UART.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StallDetector {
+    sgthrs: u8,
+    curve: Option<SgTemperatureCurve>,
+    reduction: Percent,
+    diag: DiagOutput,
+}
+
+impl StallDetector {
+    /// Create a detector for the given SGTHRS value.
+    pub fn new(sgthrs: u8) -> Self {
+        StallDetector {
+            sgthrs,
+            curve: None,
+            reduction: Percent::ZERO,
+            diag: DiagOutput::StallOnly,
+        }
+    }
+
+    /// Interpret DIAG edges according to `diag`, the routing programmed with
+    /// `set_diag_output`. Defaults to [`DiagOutput::StallOnly`].
+    pub fn with_diag_output(mut self, diag: DiagOutput) -> Self {
+        self.diag = diag;
+        self
+    }
+
+    /// Cause of a DIAG edge, given DRV_STATUS read right after it.
+    ///
+    /// With [`DiagOutput::ErrorFlags`] every edge is a driver error; with
+    /// [`DiagOutput::StallOnly`] it is a stall unless DRV_STATUS shows an
+    /// overtemperature shutdown or a short.
+    pub fn diag_event(&self, status: &DrvStatus) -> DiagEvent {
+        match self.diag {
+            DiagOutput::ErrorFlags => DiagEvent::DriverError,
+            DiagOutput::StallOnly if status.ot || status.short() => DiagEvent::DriverError,
+            DiagOutput::StallOnly => DiagEvent::Stall,
+        }
+    }
+
+    /// Relax the threshold according to `curve` as the chip heats up. Feed
+    /// temperature readings with [`Self::update_temperature`].
+    pub fn with_temperature_curve(mut self, curve: SgTemperatureCurve) -> Self {
+        self.curve = Some(curve);
+        self
+    }
+
+    /// Apply the temperature flags of a fresh DRV_STATUS reading.
+    pub fn update_temperature(&mut self, status: &DrvStatus) {
+        if let Some(curve) = &self.curve {
+            self.reduction = curve.reduction(status);
+        }
+    }
+
+    /// SGTHRS value this detector was created with.
+    pub fn sgthrs(&self) -> u8 {
+        self.sgthrs
+    }
+
+    /// SGTHRS after temperature compensation, e.g. to write back to the chip
+    /// when DIAG is used for stall detection.
+    pub fn effective_sgthrs(&self) -> u8 {
+        Percent::FULL
+            .saturating_sub(self.reduction)
+            .scale(self.sgthrs as u32) as u8
+    }
+
+    /// SG_RESULT value at or below which a stall is reported.
+    pub fn threshold(&self) -> u16 {
+        self.effective_sgthrs() as u16 * 2
+    }
+
+    /// `true` if `sg_result` indicates a stall.
+    pub fn is_stall(&self, sg_result: u16) -> bool {
+        sg_result <= self.threshold()
+    }
+}
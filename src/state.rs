@@ -0,0 +1,61 @@
+//! Driver lifecycle state.
+
+/// Fault that moved the driver into [`DriverState::Fault`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// Overtemperature shutdown (DRV_STATUS.ot).
+    Overtemperature,
+    /// Short to ground or across a low-side MOSFET.
+    Short,
+    /// Charge pump undervoltage (GSTAT.uv_cp).
+    ChargePumpUndervoltage,
+}
+
+/// Cause of a latched fault, see `last_fault`.
+///
+/// Ordered by severity, least severe first; when several causes show up in
+/// the same status poll, the most severe one is latched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FaultCause {
+    /// UART traffic was suspended by the CRC quarantine.
+    CommLoss,
+    /// Charge pump undervoltage (GSTAT.uv_cp).
+    ChargePumpUndervoltage,
+    /// Overtemperature shutdown (DRV_STATUS.ot).
+    Overtemperature,
+    /// Short to ground or across a low-side MOSFET.
+    Short,
+}
+
+/// The first fault seen since the latch was last cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatchedFault {
+    /// What went wrong
+    pub cause: FaultCause,
+    /// Time from the clock installed with `set_fault_clock`, `None` without one
+    pub timestamp_ms: Option<u32>,
+}
+
+/// Where the driver is in its lifecycle, as tracked from API calls and status polls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverState {
+    /// Created, not yet initialized over UART.
+    PoweredDown,
+    /// Initialized, outputs disabled.
+    Configured,
+    /// Outputs enabled, not moving.
+    Enabled,
+    /// A motion command is in progress.
+    Moving,
+    /// A fault was detected; motion is refused until it is cleared.
+    Fault(FaultKind),
+    /// Emergency stop; motion is refused until it is released.
+    EStopped,
+}
+
+impl DriverState {
+    /// `true` if motion commands are allowed in this state.
+    pub fn can_move(&self) -> bool {
+        !matches!(self, DriverState::Fault(_) | DriverState::EStopped)
+    }
+}
@@ -0,0 +1,106 @@
+//! StallGuard versus speed sweep, for finding resonances and tuning
+//! TPWMTHRS/SGTHRS.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::errors::TmcError;
+use crate::protocol::Crc8Provider;
+use crate::registers::*;
+use crate::tmc2209::Tmc2209FullUartDiagnosticsAndControl;
+
+/// Time given to the motor to settle at each speed before sampling, in ms.
+const SWEEP_SETTLE_MS: u32 = 200;
+
+/// SG_RESULT samples averaged per speed.
+const SWEEP_SAMPLES: u32 = 8;
+
+/// Time between samples, in ms.
+const SWEEP_SAMPLE_INTERVAL_MS: u32 = 10;
+
+/// StallGuard reading at one speed of a sweep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SweepPoint {
+    /// Speed, in µsteps/s
+    pub speed: u32,
+    /// Mean SG_RESULT at this speed
+    pub sg_mean: u16,
+    /// Lowest SG_RESULT seen at this speed; dips mark resonances
+    pub sg_min: u16,
+    /// TSTEP reported at this speed, to compare against TPWMTHRS/TCOOLTHRS
+    pub tstep: u32,
+}
+
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Run the motor through `out.len()` evenly spaced speeds from `min_speed`
+    /// to `max_speed` (µsteps/s) using the internal step generator, recording
+    /// the filtered SG_RESULT at each speed into `out`.
+    ///
+    /// Run it unloaded, or at a constant load: SG_RESULT dips that do not
+    /// follow the load point at resonance bands to avoid, and the spread of
+    /// values tells how much margin an SGTHRS setting has. Each point takes
+    /// about 0.3 s. The motor is stopped afterwards, also on error.
+    pub fn sweep_stallguard<D: DelayNs>(
+        &mut self,
+        min_speed: u32,
+        max_speed: u32,
+        out: &mut [SweepPoint],
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        if min_speed > max_speed {
+            return Err(TmcError::InvalidArgument);
+        }
+        let result = self.sweep_inner(min_speed, max_speed, out, delay);
+        let stopped = self.stop_rotation();
+        result.and(stopped)
+    }
+
+    fn sweep_inner<D: DelayNs>(
+        &mut self,
+        min_speed: u32,
+        max_speed: u32,
+        out: &mut [SweepPoint],
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        let points = out.len() as u64;
+        let fclk = self.clock_source().frequency_hz() as u64;
+        for (i, point) in out.iter_mut().enumerate() {
+            let speed = if points > 1 {
+                min_speed as u64 + (max_speed - min_speed) as u64 * i as u64 / (points - 1)
+            } else {
+                min_speed as u64
+            };
+            // VACTUAL is in µsteps per 2^24 clock cycles.
+            let vactual = ((speed << 24) / fclk).min(i32::MAX as u64) as i32;
+            self.check_cancel()?;
+            self.rotate_at(vactual)?;
+            delay.delay_ms(SWEEP_SETTLE_MS);
+
+            let mut sum = 0u32;
+            let mut min = u16::MAX;
+            for _ in 0..SWEEP_SAMPLES {
+                self.check_cancel()?;
+                let sg = self.read_sg_result()?;
+                sum += sg as u32;
+                min = min.min(sg);
+                delay.delay_ms(SWEEP_SAMPLE_INTERVAL_MS);
+            }
+            *point = SweepPoint {
+                speed: speed as u32,
+                sg_mean: (sum / SWEEP_SAMPLES) as u16,
+                sg_min: min,
+                tstep: self.read_register(REG_TSTEP)? & 0x000F_FFFF,
+            };
+        }
+        Ok(())
+    }
+}
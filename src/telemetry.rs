@@ -0,0 +1,124 @@
+//! Periodic telemetry sampling into a caller-provided ring buffer.
+//!
+//! Intended for tuning sessions: sample at a fixed rate from the main loop and
+//! stream the buffer over RTT/USB for live plotting.
+
+use crate::erased::ErasedTmc2209;
+use crate::errors::TmcError;
+use crate::fields::DrvStatus;
+use crate::registers::*;
+
+/// One timestamped telemetry sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TelemetrySample {
+    /// Application timestamp, in milliseconds
+    pub timestamp_ms: u32,
+    /// StallGuard result (SG_RESULT)
+    pub sg_result: u16,
+    /// Time between microsteps in 1/fCLK units (TSTEP, 20 bits)
+    pub tstep: u32,
+    /// Actual current scale (DRV_STATUS.CS_ACTUAL)
+    pub cs_actual: u8,
+    /// Overtemperature pre-warning
+    pub otpw: bool,
+    /// Overtemperature shutdown
+    pub ot: bool,
+    /// 120°C comparator exceeded
+    pub t120: bool,
+    /// 143°C comparator exceeded
+    pub t143: bool,
+    /// 150°C comparator exceeded
+    pub t150: bool,
+    /// 157°C comparator exceeded
+    pub t157: bool,
+}
+
+/// Telemetry recorder writing into a caller-provided buffer.
+///
+/// When the buffer is full the oldest sample is overwritten.
+pub struct Telemetry<'a> {
+    buf: &'a mut [TelemetrySample],
+    head: usize,
+    len: usize,
+}
+
+impl<'a> Telemetry<'a> {
+    /// Record into `buf`.
+    pub fn new(buf: &'a mut [TelemetrySample]) -> Self {
+        Telemetry {
+            buf,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Read SG_RESULT, TSTEP and DRV_STATUS from `driver` and store a sample.
+    pub fn sample<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+        now_ms: u32,
+    ) -> Result<TelemetrySample, TmcError> {
+        let sg_result = (driver.read_register(REG_SG_RESULT)? & 0x3FF) as u16;
+        let tstep = driver.read_register(REG_TSTEP)? & 0x000F_FFFF;
+        let status = DrvStatus::from_raw(driver.read_register(REG_DRVSTATUS)?);
+
+        let sample = TelemetrySample {
+            timestamp_ms: now_ms,
+            sg_result,
+            tstep,
+            cs_actual: status.cs_actual,
+            otpw: status.otpw,
+            ot: status.ot,
+            t120: status.t120,
+            t143: status.t143,
+            t150: status.t150,
+            t157: status.t157,
+        };
+        self.push(sample);
+        Ok(sample)
+    }
+
+    /// Store a sample, overwriting the oldest one if the buffer is full.
+    pub fn push(&mut self, sample: TelemetrySample) {
+        let cap = self.buf.len();
+        if cap == 0 {
+            return;
+        }
+        self.buf[(self.head + self.len) % cap] = sample;
+        if self.len == cap {
+            self.head = (self.head + 1) % cap;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Number of stored samples.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// `true` if no samples are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Forget all stored samples.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Most recent sample.
+    pub fn latest(&self) -> Option<&TelemetrySample> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(&self.buf[(self.head + self.len - 1) % self.buf.len()])
+    }
+
+    /// Stored samples, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &TelemetrySample> + '_ {
+        let cap = self.buf.len();
+        (0..self.len).map(move |i| &self.buf[(self.head + i) % cap])
+    }
+}
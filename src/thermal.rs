@@ -0,0 +1,160 @@
+//! Thermal duty-cycle management.
+//!
+//! When the chip keeps raising its overtemperature pre-warning (otpw), the
+//! [`ThermalManager`] steps the run current and the recommended maximum speed
+//! down, and restores them one level at a time once the warning has stayed
+//! clear for a cooldown period. Its state is exposed so a UI can show that the
+//! axis is being throttled rather than mysteriously losing torque.
+
+use crate::erased::ErasedTmc2209;
+use crate::errors::TmcError;
+use crate::fields::DrvStatus;
+use crate::homing::scale_irun;
+use crate::registers::*;
+use crate::values::Percent;
+
+/// Tuning for [`ThermalManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThermalConfig {
+    /// Consecutive updates with otpw set before throttling one more level
+    pub otpw_updates: u8,
+    /// Run current reduction per level, in percent of the configured IRUN
+    pub current_step_percent: Percent,
+    /// Maximum speed reduction per level, in percent
+    pub speed_step_percent: Percent,
+    /// Deepest throttling level
+    pub max_level: u8,
+    /// Time otpw must stay clear before restoring one level, in milliseconds
+    pub cooldown_ms: u32,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        ThermalConfig {
+            otpw_updates: 3,
+            current_step_percent: Percent::new(15),
+            speed_step_percent: Percent::new(15),
+            max_level: 3,
+            cooldown_ms: 30_000,
+        }
+    }
+}
+
+/// Snapshot of the thermal manager, for display and logging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThermalState {
+    /// Throttling level, 0 = not throttled
+    pub level: u8,
+    /// Applied run current, in percent of the configured IRUN
+    pub current_percent: Percent,
+    /// Recommended maximum speed, in percent of the configured maximum
+    pub speed_percent: Percent,
+    /// otpw as seen in the last update
+    pub otpw: bool,
+}
+
+/// Progressive current/speed throttling driven by the otpw flag.
+///
+/// The manager rewrites IHOLD_IRUN on level changes. It remembers the
+/// configured value from the driver's shadow copy when throttling starts, so
+/// change currents only while [`Self::is_throttled`] is `false`.
+pub struct ThermalManager {
+    config: ThermalConfig,
+    level: u8,
+    otpw_count: u8,
+    otpw: bool,
+    last_otpw_ms: u32,
+    base_current: Option<u32>,
+}
+
+impl ThermalManager {
+    /// Create a manager in the unthrottled state.
+    pub fn new(config: ThermalConfig) -> Self {
+        ThermalManager {
+            config,
+            level: 0,
+            otpw_count: 0,
+            otpw: false,
+            last_otpw_ms: 0,
+            base_current: None,
+        }
+    }
+
+    /// Read DRV_STATUS and adjust the throttling level.
+    ///
+    /// `now_ms` is a millisecond timestamp from the application's clock.
+    pub fn update<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+        now_ms: u32,
+    ) -> Result<ThermalState, TmcError> {
+        let status = DrvStatus::from_raw(driver.read_register(REG_DRVSTATUS)?);
+        self.otpw = status.otpw;
+
+        if status.otpw {
+            self.last_otpw_ms = now_ms;
+            self.otpw_count = self.otpw_count.saturating_add(1);
+            if self.otpw_count >= self.config.otpw_updates && self.level < self.config.max_level {
+                self.otpw_count = 0;
+                self.set_level(driver, self.level + 1)?;
+            }
+        } else {
+            self.otpw_count = 0;
+            let cooled = now_ms.wrapping_sub(self.last_otpw_ms) >= self.config.cooldown_ms;
+            if self.level > 0 && cooled {
+                // Restart the cooldown for the next level.
+                self.last_otpw_ms = now_ms;
+                self.set_level(driver, self.level - 1)?;
+            }
+        }
+        Ok(self.state())
+    }
+
+    /// Current throttling state.
+    pub fn state(&self) -> ThermalState {
+        ThermalState {
+            level: self.level,
+            current_percent: self.current_percent(),
+            speed_percent: self.speed_percent(),
+            otpw: self.otpw,
+        }
+    }
+
+    /// `true` if any throttling is in effect.
+    pub fn is_throttled(&self) -> bool {
+        self.level > 0
+    }
+
+    /// Scale a speed (any unit) by the current speed limit.
+    pub fn limit_speed(&self, speed: u32) -> u32 {
+        self.speed_percent().scale(speed)
+    }
+
+    fn current_percent(&self) -> Percent {
+        Percent::FULL.saturating_sub(self.config.current_step_percent.saturating_mul(self.level))
+    }
+
+    fn speed_percent(&self) -> Percent {
+        Percent::FULL.saturating_sub(self.config.speed_step_percent.saturating_mul(self.level))
+    }
+
+    fn set_level<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+        level: u8,
+    ) -> Result<(), TmcError> {
+        if self.level == 0 {
+            self.base_current = driver.shadow_register(REG_IHOLD_IRUN);
+        }
+        self.level = level;
+        if let Some(base) = self.base_current {
+            let value = if level == 0 {
+                base
+            } else {
+                scale_irun(base, self.current_percent())
+            };
+            driver.write_register(REG_IHOLD_IRUN, value)?;
+        }
+        Ok(())
+    }
+}
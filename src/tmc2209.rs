@@ -6,17 +6,88 @@
 //! 2. `Tmc2209StandaloneOtpPreconfig` – Option 2 (Standalone + OTP, same pins as Legacy)
 //! 3. `Tmc2209FullUartDiagnosticsAndControl` – Option 3 (Full UART Diagnostics & Control)
 
+#[cfg(feature = "uart")]
+use core::convert::Infallible;
+
+#[cfg(feature = "uart")]
+use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_io::{ErrorType, Read, Write};
+#[cfg(feature = "uart")]
+use embedded_io::{ErrorKind, Read, ReadReady, Write};
 
-use crate::errors::TmcError; // e.g. PinError, SerialError, etc.
-use crate::packet::{
+#[cfg(feature = "uart")]
+use crate::config::{
+    ChipVariant, ClockSource, CommQuarantine, IdleAction, IdlePolicy, InitChecks, JogConfig,
+    KinematicLimits, StepPulse, UartOptions, DEFAULT_READ_TIMEOUT_POLLS,
+};
+#[cfg(feature = "uart")]
+use crate::current::CurrentSettings;
+use crate::errors::TmcError;
+#[cfg(feature = "uart")]
+use crate::errors::{MultiReadStatus, Operation};
+#[cfg(feature = "uart")]
+use crate::events::{EventQueue, TmcEvent};
+#[cfg(feature = "uart")]
+use crate::fields::{encode_vactual, DrvStatus, InitReport, MsCurAct, PwmAuto, PwmScale};
+#[cfg(feature = "uart")]
+use crate::history::{DiagnosticsHistory, DiagnosticsSnapshot, DEFAULT_HISTORY_LEN};
+#[cfg(feature = "uart")]
+use crate::index_counter::IndexCounter;
+#[cfg(feature = "uart")]
+use crate::jog::ActiveJog;
+#[cfg(feature = "uart")]
+use crate::motion::{ActiveMove, ProgressTracker};
+#[cfg(feature = "uart")]
+use crate::odometer::Odometer;
+use crate::pins::{NoPin, Tmc2209Pins};
+#[cfg(feature = "uart")]
+use crate::protocol::{
     // for building / parsing TMC2209 frames
-    build_read_packet,
-    build_write_packet,
-    calc_crc8,
+    address_byte,
+    is_sync_byte,
+    read_packet_for,
+    write_packet_for,
+    Crc8Provider,
+    SoftwareCrc8,
+    MASTER_ADDRESS,
+    READ_REPLY_LEN,
 };
+#[cfg(feature = "uart")]
 use crate::registers::*; // TMC2209 register addresses & bit flags
+#[cfg(feature = "uart")]
+use crate::shadow::ShadowRegisters;
+#[cfg(feature = "uart")]
+use crate::shared_position::SharedPosition;
+#[cfg(feature = "uart")]
+use crate::stall::{DiagEvent, DiagOutput, StallDetector};
+#[cfg(feature = "uart")]
+use crate::state::{DriverState, FaultCause, FaultKind, LatchedFault};
+#[cfg(feature = "uart")]
+use crate::values::{Ihold, IholdDelay, Irun};
+
+/// Stray bytes skipped while looking for the start of a reply before the
+/// read is failed.
+#[cfg(feature = "uart")]
+pub(crate) const MAX_RESYNC_SKIP: usize = 2 * READ_REPLY_LEN;
+
+/// Most bytes discarded from RX after a failed read, so a babbling line
+/// cannot hang the driver.
+#[cfg(feature = "uart")]
+pub(crate) const MAX_DISCARD_BYTES: usize = 64;
+
+/// Smallest SLAVECONF.SENDDELAY at which the chip waits at least 5 × 8 bit
+/// times before replying, long enough for the 4-byte (40 bit) request for the
+/// next register to go out first.
+#[cfg(feature = "uart")]
+const PIPELINE_MIN_SENDDELAY: u32 = 4;
+
+/// Capacity of the driver's event queue.
+#[cfg(feature = "uart")]
+const EVENT_QUEUE_LEN: usize = 8;
+
+/// Interval between attempts in `wait_for_driver_ready`, in ms.
+#[cfg(feature = "uart")]
+const READY_POLL_INTERVAL_MS: u32 = 5;
 
 // ---------------------------------------------------------------------------
 // 1) Standalone Legacy (Option 1)
@@ -25,7 +96,7 @@ use crate::registers::*; // TMC2209 register addresses & bit flags
 /// TMC2209 in "Standalone Legacy" mode.
 /// No UART usage, pure step/dir. The driver is configured via pins (MS1, MS2, VREF).
 /// Optional DIAG and INDEX pins can be read if provided.
-pub struct Tmc2209StandaloneLegacy<EN, STEP, DIR, DIAG, INDEX>
+pub struct Tmc2209StandaloneLegacy<EN, STEP, DIR, DIAG = NoPin, INDEX = NoPin>
 where
     EN: OutputPin,
     STEP: OutputPin,
@@ -40,13 +111,11 @@ where
     index: Option<INDEX>,
 }
 
-impl<EN, STEP, DIR, DIAG, INDEX> Tmc2209StandaloneLegacy<EN, STEP, DIR, DIAG, INDEX>
+impl<EN, STEP, DIR> Tmc2209StandaloneLegacy<EN, STEP, DIR>
 where
     EN: OutputPin,
     STEP: OutputPin,
     DIR: OutputPin,
-    DIAG: InputPin,
-    INDEX: InputPin,
 {
     /// Create a new Legacy mode driver with *only* EN, STEP, and DIR pins.
     pub fn new_basic(en: EN, step: STEP, dir: DIR) -> Self {
@@ -58,7 +127,42 @@ where
             index: None,
         }
     }
+}
 
+impl<EN, STEP, DIR, DIAG> Tmc2209StandaloneLegacy<EN, STEP, DIR, DIAG>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    DIAG: InputPin,
+{
+    /// Create a Legacy mode driver with EN, STEP, DIR and a DIAG pin.
+    pub fn new_with_diag(en: EN, step: STEP, dir: DIR, diag: DIAG) -> Self {
+        Self::new_with_options(en, step, dir, Some(diag), None)
+    }
+}
+
+impl<EN, STEP, DIR, INDEX> Tmc2209StandaloneLegacy<EN, STEP, DIR, NoPin, INDEX>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    INDEX: InputPin,
+{
+    /// Create a Legacy mode driver with EN, STEP, DIR and an INDEX pin.
+    pub fn new_with_index(en: EN, step: STEP, dir: DIR, index: INDEX) -> Self {
+        Self::new_with_options(en, step, dir, None, Some(index))
+    }
+}
+
+impl<EN, STEP, DIR, DIAG, INDEX> Tmc2209StandaloneLegacy<EN, STEP, DIR, DIAG, INDEX>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    DIAG: InputPin,
+    INDEX: InputPin,
+{
     /// Create a new Legacy mode driver with optional DIAG and INDEX pins.
     pub fn new_with_options(
         en: EN,
@@ -67,12 +171,23 @@ where
         diag: Option<DIAG>,
         index: Option<INDEX>,
     ) -> Self {
-        Self {
+        Self::with_pins(Tmc2209Pins {
             en,
             step,
             dir,
             diag,
             index,
+        })
+    }
+
+    /// Create a Legacy mode driver from a pin bundle.
+    pub fn with_pins(pins: Tmc2209Pins<EN, STEP, DIR, DIAG, INDEX>) -> Self {
+        Self {
+            en: pins.en,
+            step: pins.step,
+            dir: pins.dir,
+            diag: pins.diag,
+            index: pins.index,
         }
     }
 
@@ -96,6 +211,7 @@ where
     }
 
     /// Step once by toggling STEP pin. (Blocking approach)
+    #[inline]
     pub fn step_pulse(&mut self) -> Result<(), TmcError> {
         self.step.set_high().map_err(|_| TmcError::PinError)?;
         // Possibly wait a few microseconds...
@@ -132,7 +248,7 @@ where
 /// TMC2209 in "Standalone OTP Preconfig" mode.
 /// Same pin usage as Legacy mode, but we assume the TMC2209 has been
 /// pre-configured via OTP or CPU-based writes bit-banged to TMC2209 UART input (handled outside of this driver). No normal UART usage.
-pub struct Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR, DIAG, INDEX>
+pub struct Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR, DIAG = NoPin, INDEX = NoPin>
 where
     EN: OutputPin,
     STEP: OutputPin,
@@ -147,13 +263,11 @@ where
     index: Option<INDEX>,
 }
 
-impl<EN, STEP, DIR, DIAG, INDEX> Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR, DIAG, INDEX>
+impl<EN, STEP, DIR> Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR>
 where
     EN: OutputPin,
     STEP: OutputPin,
     DIR: OutputPin,
-    DIAG: InputPin,
-    INDEX: InputPin,
 {
     /// Create an OTP Preconfig driver with *only* EN, STEP, and DIR pins.
     pub fn new_basic(en: EN, step: STEP, dir: DIR) -> Self {
@@ -165,7 +279,42 @@ where
             index: None,
         }
     }
+}
+
+impl<EN, STEP, DIR, DIAG> Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR, DIAG>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    DIAG: InputPin,
+{
+    /// Create an OTP Preconfig driver with EN, STEP, DIR and a DIAG pin.
+    pub fn new_with_diag(en: EN, step: STEP, dir: DIR, diag: DIAG) -> Self {
+        Self::new_with_options(en, step, dir, Some(diag), None)
+    }
+}
+
+impl<EN, STEP, DIR, INDEX> Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR, NoPin, INDEX>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    INDEX: InputPin,
+{
+    /// Create an OTP Preconfig driver with EN, STEP, DIR and an INDEX pin.
+    pub fn new_with_index(en: EN, step: STEP, dir: DIR, index: INDEX) -> Self {
+        Self::new_with_options(en, step, dir, None, Some(index))
+    }
+}
 
+impl<EN, STEP, DIR, DIAG, INDEX> Tmc2209StandaloneOtpPreconfig<EN, STEP, DIR, DIAG, INDEX>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    DIAG: InputPin,
+    INDEX: InputPin,
+{
     /// Create an OTP Preconfig driver with optional DIAG and INDEX pins.
     pub fn new_with_options(
         en: EN,
@@ -174,12 +323,23 @@ where
         diag: Option<DIAG>,
         index: Option<INDEX>,
     ) -> Self {
-        Self {
+        Self::with_pins(Tmc2209Pins {
             en,
             step,
             dir,
             diag,
             index,
+        })
+    }
+
+    /// Create a OTP Preconfig driver from a pin bundle.
+    pub fn with_pins(pins: Tmc2209Pins<EN, STEP, DIR, DIAG, INDEX>) -> Self {
+        Self {
+            en: pins.en,
+            step: pins.step,
+            dir: pins.dir,
+            diag: pins.diag,
+            index: pins.index,
         }
     }
 
@@ -203,6 +363,7 @@ where
     }
 
     /// Step once by toggling STEP pin. (Blocking)
+    #[inline]
     pub fn step_pulse(&mut self) -> Result<(), TmcError> {
         self.step.set_high().map_err(|_| TmcError::PinError)?;
         // Possibly wait a few microseconds...
@@ -238,138 +399,1847 @@ where
 
 /// TMC2209 in "Full UART Diagnostics and Control" mode.
 ///
-/// - Requires EN, STEP, DIR, plus a UART interface
+/// - Requires EN, STEP, DIR, plus a UART interface implementing the blocking
+///   `embedded-io` `Read`/`Write` traits and `ReadReady`, which is polled so
+///   that reads can time out instead of blocking forever.
 /// - No use of DIAG or INDEX pins here (user can wire them externally if desired).
-pub struct Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, E>
-where
+/// - `HISTORY` sets how many diagnostics snapshots are kept (see `capture_diagnostics`).
+#[cfg(feature = "uart")]
+pub struct Tmc2209FullUartDiagnosticsAndControl<
+    EN,
+    STEP,
+    DIR,
+    SERIAL,
+    const HISTORY: usize = DEFAULT_HISTORY_LEN,
+    CRC = SoftwareCrc8,
+> where
     EN: OutputPin,
     STEP: OutputPin,
     DIR: OutputPin,
-    SERIAL: Write + Read + ErrorType<Error = nb::Error<E>>,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
 {
     en: EN,
     step: STEP,
     dir: DIR,
-    slave_address: u8,
+    address_byte: u8,
     serial: SERIAL,
+    read_timeout: u32,
+    pending_read: Option<PendingRead>,
+    vactual: i32,
+    /// VACTUAL = 0 still has to reach the chip after an emergency stop
+    vactual_stop_pending: bool,
+    /// Timestamp up to which VACTUAL motion is included in `position`
+    vactual_since_ms: Option<u32>,
+    /// Sub-step remainder of the VACTUAL integration, in steps × 2^24 × 1000
+    vactual_remainder: i64,
+    position_estimated: bool,
+    clock: ClockSource,
+    variant: ChipVariant,
+    position: i32,
+    clockwise: bool,
+    shadow: ShadowRegisters,
+    idle_policy: Option<IdlePolicy>,
+    idle_applied: bool,
+    motion_since_poll: bool,
+    last_motion_ms: u32,
+    events: EventQueue<EVENT_QUEUE_LEN>,
+    last_status: DrvStatus,
+    state: DriverState,
+    /// State to return to when the current motion command ends
+    state_before_move: DriverState,
+    history: DiagnosticsHistory<HISTORY>,
+    crc: CRC,
+    echo_handling: bool,
+    retries: u8,
+    saved_mscnt: Option<u16>,
+    dir_setup_ns: u32,
+    dir_pending: bool,
+    step_shape: StepPulse,
+    active_move: Option<ActiveMove>,
+    homed: bool,
+    jog_config: JogConfig,
+    active_jog: Option<ActiveJog>,
+    quarantine: Option<CommQuarantine>,
+    crc_streak: u8,
+    comm_suspended: bool,
+    suspended_since_ms: Option<u32>,
+    current: CurrentSettings,
+    cancel: Option<fn() -> bool>,
+    progress: ProgressTracker,
+    odometer: Odometer,
+    fault_latch: Option<LatchedFault>,
+    fault_clock: Option<fn() -> u32>,
+    index_counter: Option<IndexCounter>,
+    shared_position: Option<&'static SharedPosition>,
+    limits: KinematicLimits,
+    /// `limits.min_step_period_ns()`, kept for the step loop
+    min_step_period_ns: u32,
 }
 
-impl<EN, STEP, DIR, SERIAL, E> Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, E>
+/// State of a register read started by `read_register_nb`.
+#[cfg(feature = "uart")]
+struct PendingRead {
+    reg: u8,
+    resp: [u8; READ_REPLY_LEN],
+    received: usize,
+}
+
+#[cfg(feature = "uart")]
+impl<EN, STEP, DIR, SERIAL> Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL>
 where
     EN: OutputPin,
     STEP: OutputPin,
     DIR: OutputPin,
-    SERIAL: Write + Read + ErrorType<Error = nb::Error<E>>,
+    SERIAL: Write + Read + ReadReady,
 {
     /// Create a new driver in Full UART mode.
     pub fn new(en: EN, step: STEP, dir: DIR, serial: SERIAL, slave_address: u8) -> Self {
+        Self::new_with_history(en, step, dir, serial, slave_address)
+    }
+
+    /// Create a new driver in Full UART mode from a pin bundle and options.
+    ///
+    /// DIAG and INDEX in `pins` are ignored. Fails with
+    /// [`TmcError::InvalidArgument`] if the options' external clock is out of
+    /// range, or [`TmcError::PinError`] if STEP cannot be set to its idle level.
+    pub fn with_pins<DIAG, INDEX>(
+        pins: Tmc2209Pins<EN, STEP, DIR, DIAG, INDEX>,
+        serial: SERIAL,
+        options: UartOptions,
+    ) -> Result<Self, TmcError> {
+        let mut driver = Self::new(pins.en, pins.step, pins.dir, serial, options.slave_address);
+        driver.set_clock_source(options.clock)?;
+        driver.set_read_timeout(options.read_timeout_polls);
+        driver.set_echo_handling(options.echo_handling);
+        driver.set_retries(options.retries);
+        driver.set_step_pulse(options.step_pulse)?;
+        driver.set_dir_setup_time(options.dir_setup_ns);
+        driver.idle_policy = options.idle_policy;
+        Ok(driver)
+    }
+}
+
+#[cfg(feature = "uart")]
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+{
+    /// Create a new driver in Full UART mode, keeping the last `HISTORY`
+    /// diagnostics snapshots.
+    pub fn new_with_history(
+        en: EN,
+        step: STEP,
+        dir: DIR,
+        serial: SERIAL,
+        slave_address: u8,
+    ) -> Self {
+        Self::new_with_crc(en, step, dir, serial, slave_address, SoftwareCrc8)
+    }
+}
+
+#[cfg(feature = "uart")]
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Create a new driver in Full UART mode using `crc` to compute datagram
+    /// checksums, e.g. a hardware CRC unit or [`crate::TableCrc8`].
+    pub fn new_with_crc(
+        en: EN,
+        step: STEP,
+        dir: DIR,
+        serial: SERIAL,
+        slave_address: u8,
+        crc: CRC,
+    ) -> Self {
         Self {
             en,
             step,
             dir,
-            slave_address,
+            address_byte: address_byte(slave_address),
             serial,
+            read_timeout: DEFAULT_READ_TIMEOUT_POLLS,
+            pending_read: None,
+            vactual: 0,
+            vactual_stop_pending: false,
+            vactual_since_ms: None,
+            vactual_remainder: 0,
+            position_estimated: false,
+            clock: ClockSource::Internal,
+            variant: ChipVariant::Tmc2209,
+            position: 0,
+            clockwise: true,
+            shadow: ShadowRegisters::default(),
+            idle_policy: None,
+            idle_applied: false,
+            motion_since_poll: false,
+            last_motion_ms: 0,
+            events: EventQueue::new(),
+            last_status: DrvStatus::default(),
+            state: DriverState::PoweredDown,
+            state_before_move: DriverState::PoweredDown,
+            history: DiagnosticsHistory::new(),
+            crc,
+            echo_handling: false,
+            retries: 0,
+            saved_mscnt: None,
+            dir_setup_ns: 0,
+            dir_pending: false,
+            step_shape: StepPulse::default(),
+            active_move: None,
+            homed: false,
+            jog_config: JogConfig::default(),
+            active_jog: None,
+            quarantine: None,
+            crc_streak: 0,
+            comm_suspended: false,
+            suspended_since_ms: None,
+            current: CurrentSettings::default(),
+            cancel: None,
+            progress: ProgressTracker::default(),
+            odometer: Odometer::default(),
+            fault_latch: None,
+            fault_clock: None,
+            index_counter: None,
+            shared_position: None,
+            limits: KinematicLimits::UNLIMITED,
+            min_step_period_ns: 0,
+        }
+    }
+
+    /// Tell the driver which clock the chip runs from.
+    ///
+    /// External clocks are validated against the 4–16 MHz datasheet range.
+    pub fn set_clock_source(&mut self, clock: ClockSource) -> Result<(), TmcError> {
+        clock.validate()?;
+        self.clock = clock;
+        Ok(())
+    }
+
+    /// Clock source the driver assumes, for diagnostics and timing conversions.
+    pub fn clock_source(&self) -> ClockSource {
+        self.clock
+    }
+
+    /// Tell the driver which chip it talks to. Accesses to registers the
+    /// chip does not have fail with [`TmcError::Unsupported`] without touching
+    /// the bus; [`ChipVariant::Unchecked`] lets every address through.
+    pub fn set_chip_variant(&mut self, variant: ChipVariant) {
+        self.variant = variant;
+    }
+
+    /// Chip variant the driver assumes.
+    pub fn chip_variant(&self) -> ChipVariant {
+        self.variant
+    }
+
+    /// Check that `baud` is within the autobaud range for the configured clock.
+    pub fn check_baud_rate(&self, baud: u32) -> Result<(), TmcError> {
+        let (min, max) = self.clock.baud_range();
+        if (min..=max).contains(&baud) {
+            Ok(())
+        } else {
+            Err(TmcError::InvalidArgument)
         }
     }
 
+    /// Talk to the node at `slave_address` from now on, e.g. when one driver
+    /// instance serves a whole bus. Abandons a pending non-blocking read.
+    pub(crate) fn set_slave_address(&mut self, slave_address: u8) {
+        self.address_byte = address_byte(slave_address);
+        self.pending_read = None;
+    }
+
+    /// Set how many times each reply byte is polled before a read gives up
+    /// with [`TmcError::Timeout`].
+    pub fn set_read_timeout(&mut self, polls: u32) {
+        self.read_timeout = polls;
+    }
+
+    /// Polls per reply byte before a read times out.
+    pub fn read_timeout(&self) -> u32 {
+        self.read_timeout
+    }
+
+    /// Consume the echo of every transmitted datagram before reading replies.
+    ///
+    /// Needed when TX and RX share the single PDN_UART wire (e.g. through a
+    /// resistor), so the MCU receives its own bytes back.
+    pub fn set_echo_handling(&mut self, enabled: bool) {
+        self.echo_handling = enabled;
+    }
+
+    /// `true` if transmitted datagrams are expected to echo back.
+    pub fn echo_handling(&self) -> bool {
+        self.echo_handling
+    }
+
     /// Enable the driver (active-low => EN = LOW).
+    ///
+    /// Refused with [`TmcError::InvalidState`] while faulted or emergency-stopped.
     pub fn enable(&mut self) -> Result<(), TmcError> {
-        self.en.set_low().map_err(|_| TmcError::PinError)
+        self.ensure_can_move()?;
+        self.en.set_low().map_err(|_| TmcError::PinError)?;
+        if self.state == DriverState::Moving {
+            self.state_before_move = DriverState::Enabled;
+        } else {
+            self.state = DriverState::Enabled;
+        }
+        Ok(())
     }
 
     /// Disable the driver (active-low => EN = HIGH).
+    ///
+    /// If the outputs were energised, MSCNT is recorded first (best effort) so
+    /// that [`Self::restore_phase`] can re-align the coils after re-enabling.
     pub fn disable(&mut self) -> Result<(), TmcError> {
-        self.en.set_high().map_err(|_| TmcError::PinError)
+        if matches!(self.state, DriverState::Enabled | DriverState::Moving) {
+            self.saved_mscnt = self
+                .read_register(REG_MSCNT)
+                .ok()
+                .map(|v| (v & MSCNT_MASK) as u16);
+        }
+        self.en.set_high().map_err(|_| TmcError::PinError)?;
+        if matches!(self.state, DriverState::Enabled | DriverState::Moving) {
+            self.state = DriverState::Configured;
+        }
+        Ok(())
+    }
+
+    /// Lifecycle state of the driver.
+    pub fn state(&self) -> DriverState {
+        self.state
+    }
+
+    /// Disable the outputs and stop the internal step generator, then refuse
+    /// motion until [`Self::release_estop`] is called.
+    ///
+    /// The driver enters [`DriverState::EStopped`] and forgets any VACTUAL
+    /// rotation even if EN or the UART fails; the error of the EN pin is
+    /// returned. Stopping VACTUAL is best effort: the outputs are already off
+    /// even if the UART is down, and a failed write is retried by
+    /// `release_estop`.
+    pub fn emergency_stop(&mut self) -> Result<(), TmcError> {
+        let pin = self.en.set_high().map_err(|_| TmcError::PinError);
+        self.state = DriverState::EStopped;
+        if self.vactual != 0 {
+            self.vactual = 0;
+            self.vactual_since_ms = None;
+            self.vactual_stop_pending = true;
+        }
+        if self.vactual_stop_pending && self.write_register(REG_VACTUAL, 0).is_ok() {
+            self.vactual_stop_pending = false;
+        }
+        pin
+    }
+
+    /// Leave the emergency-stop state. Outputs stay disabled until `enable()`.
+    ///
+    /// If `emergency_stop` could not write VACTUAL = 0, the write is retried
+    /// first; should it fail again, the error is returned and the driver stays
+    /// emergency-stopped, so the chip cannot resume rotating on `enable()`.
+    pub fn release_estop(&mut self) -> Result<(), TmcError> {
+        if self.state != DriverState::EStopped {
+            return Err(TmcError::InvalidState(self.state));
+        }
+        if self.vactual_stop_pending {
+            self.write_register(REG_VACTUAL, 0)?;
+            self.vactual_stop_pending = false;
+        }
+        self.state = DriverState::Configured;
+        Ok(())
+    }
+
+    /// Leave the fault state once the chip no longer reports the fault.
+    ///
+    /// Re-reads the status; if a fault is still present the driver stays in
+    /// [`DriverState::Fault`] and that state is returned as the error.
+    pub fn clear_fault(&mut self) -> Result<(), TmcError> {
+        if !matches!(self.state, DriverState::Fault(_)) {
+            return Err(TmcError::InvalidState(self.state));
+        }
+        self.last_status = DrvStatus::default();
+        self.state = DriverState::Configured;
+        self.check_status()?;
+        if let DriverState::Fault(_) = self.state {
+            return Err(TmcError::InvalidState(self.state));
+        }
+        Ok(())
+    }
+
+    /// Fail with [`TmcError::InvalidState`] unless motion is allowed.
+    pub(crate) fn ensure_can_move(&self) -> Result<(), TmcError> {
+        if self.state.can_move() {
+            Ok(())
+        } else {
+            Err(TmcError::InvalidState(self.state))
+        }
+    }
+
+    /// Mark the start or end of a motion command.
+    ///
+    /// The end of a move returns to the state it started from, so a move with
+    /// the outputs never enabled over EN does not leave the driver `Enabled`.
+    pub(crate) fn set_moving(&mut self, moving: bool) {
+        match (moving, self.state) {
+            (true, DriverState::Moving) => {}
+            (true, state) if state.can_move() => {
+                self.state_before_move = state;
+                self.state = DriverState::Moving;
+            }
+            (false, DriverState::Moving) => self.state = self.state_before_move,
+            _ => {}
+        }
     }
 
     /// Set the direction pin.
     pub fn set_direction(&mut self, clockwise: bool) -> Result<(), TmcError> {
         if clockwise {
-            self.dir.set_high().map_err(|_| TmcError::PinError)
+            self.dir.set_high().map_err(|_| TmcError::PinError)?;
         } else {
-            self.dir.set_low().map_err(|_| TmcError::PinError)
+            self.dir.set_low().map_err(|_| TmcError::PinError)?;
+        }
+        if clockwise != self.clockwise {
+            self.dir_pending = true;
+        }
+        self.clockwise = clockwise;
+        Ok(())
+    }
+
+    /// Time to wait between a direction change and the next step, in ns.
+    ///
+    /// The chip itself needs only 20 ns, but level shifters or DEDGE setups
+    /// may need more. The motion routines (`run_profile`, `move_by`, homing,
+    /// ...) wait this long before their first step after a direction change;
+    /// bare [`Self::step_pulse`] calls do not. Defaults to 0.
+    pub fn set_dir_setup_time(&mut self, ns: u32) {
+        self.dir_setup_ns = ns;
+    }
+
+    /// Wait out the DIR setup time if the direction changed since the last
+    /// call.
+    pub(crate) fn settle_direction<D: DelayNs>(&mut self, delay: &mut D) {
+        let wait_ns = self.take_dir_settle_ns();
+        if wait_ns > 0 {
+            delay.delay_ns(wait_ns);
         }
     }
 
+    /// DIR setup time still to wait before the next step, in ns; 0 if the
+    /// direction has not changed since the last call.
+    pub(crate) fn take_dir_settle_ns(&mut self) -> u32 {
+        if core::mem::take(&mut self.dir_pending) {
+            self.dir_setup_ns
+        } else {
+            0
+        }
+    }
+
+    /// Direction last set with [`Self::set_direction`].
+    pub fn direction(&self) -> bool {
+        self.clockwise
+    }
+
     /// Issue a single step pulse (blocking).
+    ///
+    /// Counts towards [`Self::position`]: clockwise steps increment it.
+    ///
+    /// Honours [`StepPulse::inverted`], but not its timing: pulses are as short
+    /// as the pin writes allow. The motion routines apply the full shape.
+    /// Refused with [`TmcError::InvalidState`] while faulted or
+    /// emergency-stopped, so the position does not count steps the motor
+    /// cannot take.
+    #[inline]
     pub fn step_pulse(&mut self) -> Result<(), TmcError> {
-        self.step.set_high().map_err(|_| TmcError::PinError)?;
-        // short delay if needed
-        self.step.set_low().map_err(|_| TmcError::PinError)
+        self.ensure_can_move()?;
+        self.begin_step()?;
+        self.end_step()
+    }
+
+    /// Configure the STEP pulse timing and polarity.
+    ///
+    /// Changing the polarity drives the pin to its new idle level.
+    pub fn set_step_pulse(&mut self, shape: StepPulse) -> Result<(), TmcError> {
+        self.step_shape = shape;
+        self.set_step_level(false)
+    }
+
+    /// Current STEP pulse configuration.
+    pub fn step_pulse_shape(&self) -> StepPulse {
+        self.step_shape
+    }
+
+    /// Issue one step pulse with the configured shape, then wait out the rest
+    /// of `period_ns` (at least the configured low time).
+    #[inline]
+    pub(crate) fn timed_step<D: DelayNs>(
+        &mut self,
+        period_ns: u32,
+        delay: &mut D,
+    ) -> Result<(), TmcError> {
+        self.check_cancel()?;
+        let spent = self.shaped_step(delay)?;
+        self.progress.step();
+        delay.delay_ns(self.step_gap_ns(period_ns, spent));
+        Ok(())
+    }
+
+    /// Issue one step pulse with the configured high time, returning the
+    /// time spent waiting in ns.
+    #[inline]
+    pub(crate) fn shaped_step<D: DelayNs>(&mut self, delay: &mut D) -> Result<u32, TmcError> {
+        let high_ns = self.step_high_ns();
+        self.begin_step()?;
+        if high_ns > 0 {
+            delay.delay_ns(high_ns);
+        }
+        self.end_step()?;
+        Ok(high_ns)
+    }
+
+    /// Configured active time of a step pulse, in ns.
+    #[inline]
+    pub(crate) fn step_high_ns(&self) -> u32 {
+        self.step_shape.high_ns
+    }
+
+    /// Wait after a step pulse that keeps the step `period_ns` long.
+    #[inline]
+    pub(crate) fn step_gap_ns(&self, period_ns: u32, spent_ns: u32) -> u32 {
+        period_ns
+            .max(self.min_step_period_ns)
+            .saturating_sub(spent_ns)
+            .max(self.step_shape.low_ns)
+    }
+
+    /// Move started with `start_move`, taking it out of the driver.
+    pub(crate) fn take_active_move(&mut self) -> Option<ActiveMove> {
+        self.active_move.take()
+    }
+
+    pub(crate) fn put_active_move(&mut self, active: Option<ActiveMove>) {
+        self.active_move = active;
+    }
+
+    /// Count `count` steps issued by other means in the current direction.
+    pub(crate) fn count_steps(&mut self, count: u32) {
+        self.motion_since_poll = true;
+        let delta = if self.clockwise {
+            count
+        } else {
+            count.wrapping_neg()
+        };
+        self.position = self.position.wrapping_add(delta as i32);
+        self.odometer.record_steps(self.clockwise, count);
+        self.publish_position();
+    }
+
+    /// Drive the active edge of a step pulse. The position is counted here,
+    /// where the chip latches the step.
+    #[inline]
+    pub(crate) fn begin_step(&mut self) -> Result<(), TmcError> {
+        self.note_motion()?;
+        self.set_step_level(true)?;
+        self.position = self
+            .position
+            .wrapping_add(if self.clockwise { 1 } else { -1 });
+        self.odometer.record_step(self.clockwise);
+        self.publish_position();
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn end_step(&mut self) -> Result<(), TmcError> {
+        self.set_step_level(false)
+    }
+
+    #[inline]
+    fn set_step_level(&mut self, active: bool) -> Result<(), TmcError> {
+        if active != self.step_shape.inverted {
+            self.step.set_high().map_err(|_| TmcError::PinError)
+        } else {
+            self.step.set_low().map_err(|_| TmcError::PinError)
+        }
+    }
+
+    /// Swap the pins for others, e.g. when splitting the driver.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn replace_pins<EN2, STEP2, DIR2>(
+        self,
+        en: EN2,
+        step: STEP2,
+        dir: DIR2,
+    ) -> (
+        EN,
+        STEP,
+        DIR,
+        Tmc2209FullUartDiagnosticsAndControl<EN2, STEP2, DIR2, SERIAL, HISTORY, CRC>,
+    )
+    where
+        EN2: OutputPin,
+        STEP2: OutputPin,
+        DIR2: OutputPin,
+    {
+        let driver = Tmc2209FullUartDiagnosticsAndControl {
+            en,
+            step,
+            dir,
+            address_byte: self.address_byte,
+            serial: self.serial,
+            read_timeout: self.read_timeout,
+            pending_read: self.pending_read,
+            vactual: self.vactual,
+            vactual_stop_pending: self.vactual_stop_pending,
+            vactual_since_ms: self.vactual_since_ms,
+            vactual_remainder: self.vactual_remainder,
+            position_estimated: self.position_estimated,
+            clock: self.clock,
+            variant: self.variant,
+            position: self.position,
+            clockwise: self.clockwise,
+            shadow: self.shadow,
+            idle_policy: self.idle_policy,
+            idle_applied: self.idle_applied,
+            motion_since_poll: self.motion_since_poll,
+            last_motion_ms: self.last_motion_ms,
+            events: self.events,
+            last_status: self.last_status,
+            state: self.state,
+            state_before_move: self.state_before_move,
+            history: self.history,
+            crc: self.crc,
+            echo_handling: self.echo_handling,
+            retries: self.retries,
+            saved_mscnt: self.saved_mscnt,
+            dir_setup_ns: self.dir_setup_ns,
+            dir_pending: self.dir_pending,
+            step_shape: self.step_shape,
+            active_move: self.active_move,
+            homed: self.homed,
+            jog_config: self.jog_config,
+            active_jog: self.active_jog,
+            quarantine: self.quarantine,
+            crc_streak: self.crc_streak,
+            comm_suspended: self.comm_suspended,
+            suspended_since_ms: self.suspended_since_ms,
+            current: self.current,
+            cancel: self.cancel,
+            progress: self.progress,
+            odometer: self.odometer,
+            fault_latch: self.fault_latch,
+            fault_clock: self.fault_clock,
+            index_counter: self.index_counter,
+            shared_position: self.shared_position,
+            limits: self.limits,
+            min_step_period_ns: self.min_step_period_ns,
+        };
+        (self.en, self.step, self.dir, driver)
+    }
+
+    /// Adopt the direction a split-off motion handle left the DIR pin in.
+    pub(crate) fn set_split_direction(&mut self, clockwise: bool) {
+        self.clockwise = clockwise;
+    }
+
+    /// Settings used by `set_current_ma`.
+    pub(crate) fn current_settings(&self) -> &CurrentSettings {
+        &self.current
+    }
+
+    pub(crate) fn current_settings_mut(&mut self) -> &mut CurrentSettings {
+        &mut self.current
+    }
+
+    /// Install a check polled by long-running routines (blocking moves,
+    /// homing, probing, phase alignment, sweeps), typically reading a flag
+    /// set by a button interrupt or a supervisory task. Once it returns
+    /// `true`, the routine stops at the next step or sample and fails with
+    /// [`TmcError::Cancelled`]. `None` removes it.
+    ///
+    /// A cancelled routine leaves the driver as if it had failed: the position
+    /// counter includes every step issued, temporarily reduced currents are
+    /// restored, the internal step generator is stopped and the outputs stay
+    /// enabled. The check stays installed; clear the flag before the next
+    /// routine.
+    pub fn set_cancel_check(&mut self, check: Option<fn() -> bool>) {
+        self.cancel = check;
+    }
+
+    /// Progress reporting state, see `set_progress_sink`.
+    pub(crate) fn progress_mut(&mut self) -> &mut ProgressTracker {
+        &mut self.progress
+    }
+
+    /// Fail with [`TmcError::Cancelled`] if the cancel check fires.
+    #[inline]
+    pub(crate) fn check_cancel(&self) -> Result<(), TmcError> {
+        match self.cancel {
+            Some(cancelled) if cancelled() => Err(TmcError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the velocity limit and dead-man timeout used by `start_jog`.
+    pub fn set_jog_config(&mut self, config: JogConfig) {
+        self.jog_config = config;
+    }
+
+    /// Limit every motion command to `limits`.
+    ///
+    /// Ramps passed to `move_by`, `move_to`, `start_move` and `run_planned` are
+    /// brought within the limits, step profiles run no faster than `max_v`,
+    /// jogs are clamped to it and [`Self::rotate_at`] refuses faster
+    /// velocities. VACTUAL handoff ramps are stretched to respect `max_a`.
+    pub fn set_kinematic_limits(&mut self, limits: KinematicLimits) {
+        self.limits = limits;
+        self.min_step_period_ns = if limits.max_v == u32::MAX {
+            0
+        } else {
+            limits.min_step_period_ns()
+        };
+    }
+
+    /// Kinematic limits in effect.
+    pub fn kinematic_limits(&self) -> KinematicLimits {
+        self.limits
+    }
+
+    /// Highest VACTUAL magnitude allowed by the kinematic limits.
+    pub(crate) fn max_vactual(&self) -> u32 {
+        self.limits.max_vactual(self.clock.frequency_hz())
+    }
+
+    /// Jog limits in effect.
+    pub fn jog_config(&self) -> JogConfig {
+        self.jog_config
+    }
+
+    /// Jog in progress, if any.
+    pub(crate) fn active_jog(&self) -> Option<ActiveJog> {
+        self.active_jog
+    }
+
+    pub(crate) fn active_jog_mut(&mut self) -> &mut Option<ActiveJog> {
+        &mut self.active_jog
+    }
+
+    /// INDEX pulse count, if enabled with `enable_index_counter`.
+    pub fn index_counter(&self) -> Option<IndexCounter> {
+        self.index_counter
+    }
+
+    pub(crate) fn index_counter_mut(&mut self) -> &mut Option<IndexCounter> {
+        &mut self.index_counter
+    }
+
+    /// Power down automatically after a period without motion, or `None` to
+    /// turn the policy off. Idle time is measured by [`Self::poll_idle`].
+    pub fn set_idle_policy(&mut self, policy: Option<IdlePolicy>) -> Result<(), TmcError> {
+        self.wake_from_idle()?;
+        self.idle_policy = policy;
+        // Start timing from the next poll.
+        self.motion_since_poll = true;
+        Ok(())
+    }
+
+    /// Check the idle policy against the application's clock.
+    ///
+    /// Call this regularly with a millisecond timestamp (wrapping is fine). Once
+    /// no motion has been commanded for the policy's timeout, its action is
+    /// applied; the next step or `rotate_at` call undoes it transparently.
+    /// Returns `true` if the idle action was applied during this call.
+    pub fn poll_idle(&mut self, now_ms: u32) -> Result<bool, TmcError> {
+        let Some(policy) = self.idle_policy else {
+            return Ok(false);
+        };
+        if self.motion_since_poll || self.vactual != 0 {
+            self.motion_since_poll = false;
+            self.last_motion_ms = now_ms;
+            return Ok(false);
+        }
+        if self.idle_applied || now_ms.wrapping_sub(self.last_motion_ms) < policy.timeout_ms {
+            return Ok(false);
+        }
+
+        match policy.action {
+            IdleAction::ReduceHoldCurrent(ihold) => {
+                let Some(raw) = self.shadow.get(REG_IHOLD_IRUN) else {
+                    // No current configured yet, nothing to reduce.
+                    return Ok(false);
+                };
+                let reduced = (raw & !0x1F) | (ihold.min(31) as u32);
+                self.write_register(REG_IHOLD_IRUN, reduced)?;
+                // Keep the configured value as the one to restore.
+                self.shadow.record(REG_IHOLD_IRUN, raw);
+            }
+            IdleAction::Disable => self.en.set_high().map_err(|_| TmcError::PinError)?,
+        }
+        self.idle_applied = true;
+        Ok(true)
+    }
+
+    /// `true` while the idle action is in effect.
+    pub fn is_idle(&self) -> bool {
+        self.idle_applied
+    }
+
+    /// Record a motion command, undoing the idle action first if needed.
+    #[inline]
+    fn note_motion(&mut self) -> Result<(), TmcError> {
+        self.motion_since_poll = true;
+        self.wake_from_idle()
+    }
+
+    /// Undo the idle action now rather than on the next step.
+    ///
+    /// Needed before [`Self::step_pulse_unchecked`], which skips that check.
+    pub fn wake(&mut self) -> Result<(), TmcError> {
+        self.wake_from_idle()
+    }
+
+    /// Undo the idle action, if it is in effect.
+    #[inline]
+    fn wake_from_idle(&mut self) -> Result<(), TmcError> {
+        if self.idle_applied {
+            self.leave_idle()
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cold]
+    fn leave_idle(&mut self) -> Result<(), TmcError> {
+        match self.idle_policy.map(|p| p.action) {
+            Some(IdleAction::ReduceHoldCurrent(_)) => {
+                if let Some(raw) = self.shadow.get(REG_IHOLD_IRUN) {
+                    self.write_register(REG_IHOLD_IRUN, raw)?;
+                }
+            }
+            Some(IdleAction::Disable) => self.en.set_low().map_err(|_| TmcError::PinError)?,
+            None => {}
+        }
+        self.idle_applied = false;
+        Ok(())
+    }
+
+    /// Position in steps, counted from the pulses issued by this driver.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Steps and direction reversals issued by this driver so far.
+    pub fn odometer(&self) -> Odometer {
+        self.odometer
+    }
+
+    /// Overwrite the usage counters, e.g. reset them with
+    /// `Odometer::default()` after replacing a belt.
+    pub fn set_odometer(&mut self, odometer: Odometer) {
+        self.odometer = odometer;
+    }
+
+    /// `true` once homing has succeeded, or a homed state was imported.
+    pub fn is_homed(&self) -> bool {
+        self.homed
+    }
+
+    /// Overwrite the position counter, e.g. after homing. Clears the
+    /// estimated flag and restarts the INDEX pulse count.
+    pub fn set_position(&mut self, position: i32) {
+        self.position = position;
+        self.vactual_remainder = 0;
+        self.position_estimated = false;
+        self.publish_position();
+        if let Some(counter) = &mut self.index_counter {
+            counter.restart(position);
+        }
+    }
+
+    /// Keep `shared` up to date with the position counter, so that other
+    /// contexts can read it while this driver steps; `None` detaches it. The
+    /// current position is published at once.
+    pub fn share_position(&mut self, shared: Option<&'static SharedPosition>) {
+        self.shared_position = shared;
+        self.publish_position();
+    }
+
+    /// Counter attached with [`Self::share_position`].
+    pub fn shared_position(&self) -> Option<&'static SharedPosition> {
+        self.shared_position
+    }
+
+    #[inline]
+    fn publish_position(&self) {
+        if let Some(shared) = self.shared_position {
+            shared.set(self.position);
+        }
+    }
+
+    /// `true` if the position includes motion estimated from VACTUAL rather
+    /// than counted step pulses, see [`Self::update_position_estimate`].
+    /// Cleared by [`Self::set_position`] and homing.
+    pub fn is_position_estimated(&self) -> bool {
+        self.position_estimated
     }
 
     /// check IFCNT, set PDN_DISABLE, etc.
-    pub fn init_uart(&mut self) -> Result<(), TmcError> {
-        let ifcnt_before = self.read_register(REG_IFCNT)?;
+    ///
+    /// Runs the default [`InitChecks`]; see [`Self::init_uart_with`].
+    pub fn init_uart(&mut self) -> Result<InitReport, TmcError> {
+        self.init_uart_with(&InitChecks::default())
+    }
+
+    /// Set PDN_DISABLE, verify that writes arrive (IFCNT), clear GSTAT and
+    /// report what was found.
+    ///
+    /// Returns an error if the UART does not work. A chip that answers but
+    /// fails one of `checks` (wrong VERSION, persistent drv_err or uv_cp) gives
+    /// a report with `passed == false`, and the driver stays in
+    /// [`DriverState::PoweredDown`].
+    pub fn init_uart_with(&mut self, checks: &InitChecks) -> Result<InitReport, TmcError> {
+        let ifcnt_before = self.read_register_blocking(REG_IFCNT)?;
+        let version = (self.read_register_blocking(REG_IOIN)? >> 24) as u8;
 
         // Set PDN_DISABLE => use UART-based config
-        let gconf = self.read_register(REG_GCONF)?;
+        let gconf = self.read_register_blocking(REG_GCONF)?;
         let new_gconf = gconf | GCONF_PDN_DISABLE;
         self.write_register(REG_GCONF, new_gconf)?;
 
-        let ifcnt_after = self.read_register(REG_IFCNT)?;
+        let ifcnt_after = self.read_register_blocking(REG_IFCNT)?;
         if ifcnt_after == ifcnt_before {
-            return Err(TmcError::SerialError);
+            return Err(TmcError::SerialError {
+                kind: ErrorKind::Other,
+                reg: REG_IFCNT,
+                op: Operation::Verify,
+            });
         }
-        Ok(())
+
+        let gstat = self.read_register_blocking(REG_GSTAT)? & 0x07;
+        if gstat != 0 {
+            // Write 1 to clear.
+            self.write_register(REG_GSTAT, gstat)?;
+        }
+        let gstat_after_clear = self.read_register_blocking(REG_GSTAT)? & 0x07;
+        let ifcnt = (self.read_register_blocking(REG_IFCNT)? & 0xFF) as u8;
+
+        let mut report = InitReport {
+            gstat: gstat as u8,
+            gstat_after_clear: gstat_after_clear as u8,
+            version,
+            ifcnt,
+            passed: true,
+        };
+        report.passed = checks.expected_version.is_none_or(|v| v == version)
+            && !(checks.fail_on_drv_err && report.drv_err())
+            && !(checks.fail_on_uv_cp && report.uv_cp());
+        if report.passed && self.state == DriverState::PoweredDown {
+            self.state = DriverState::Configured;
+        }
+        Ok(report)
+    }
+
+    /// Wait until the chip answers on the UART, for at most `timeout_ms`.
+    ///
+    /// VM and VIO may come up in either order and the chip only answers once
+    /// both are present, so call this instead of a fixed delay before
+    /// [`Self::init_uart`]. The chip is ready once IOIN returns a non-zero
+    /// VERSION and IFCNT can be read right after it. Garbled or missing replies
+    /// while it powers up are expected: they neither count towards the CRC
+    /// quarantine nor push events. Returns the time waited, in ms (rounded up
+    /// to the poll interval), or the last error once `timeout_ms` has passed.
+    pub fn wait_for_driver_ready<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<u32, TmcError> {
+        self.pending_read = None;
+        let mut waited = 0;
+        loop {
+            let err = match self.probe_register(REG_IOIN) {
+                Ok(ioin) if ioin >> 24 != 0 => match self.probe_register(REG_IFCNT) {
+                    Ok(_) => return Ok(waited),
+                    Err(e) => e,
+                },
+                Ok(_) => TmcError::VerificationError {
+                    reg: REG_IOIN,
+                    op: Operation::Read,
+                },
+                Err(e) => e,
+            };
+            if !(err.is_transient() || matches!(err, TmcError::SerialError { .. })) {
+                return Err(err);
+            }
+            if waited >= timeout_ms {
+                return Err(err);
+            }
+            delay.delay_ms(READY_POLL_INTERVAL_MS);
+            waited += READY_POLL_INTERVAL_MS;
+        }
+    }
+
+    /// Read `reg` once, cleaning up after a failure without reporting it.
+    fn probe_register(&mut self, reg: u8) -> Result<u32, TmcError> {
+        self.send_read_request(reg)?;
+        let result = self
+            .read_reply(reg)
+            .and_then(|resp| self.parse_reply(reg, &resp));
+        if result.is_err() {
+            let _ = self.drain_rx();
+        }
+        result
     }
 
     /// set run/hold current in IHOLD_IRUN via UART.
+    ///
+    /// Returns [`TmcError::InvalidArgument`] if a value is out of range; see
+    /// [`Self::set_run_hold_current`] for a version that cannot fail that way.
     pub fn set_current(&mut self, irun: u8, ihold: u8, ihold_delay: u8) -> Result<(), TmcError> {
-        if irun > 31 || ihold > 31 || ihold_delay > 15 {
-            return Err(TmcError::VerificationError);
-        }
+        self.set_run_hold_current(
+            Irun::try_new(irun)?,
+            Ihold::try_new(ihold)?,
+            IholdDelay::try_new(ihold_delay)?,
+        )
+    }
+
+    /// Set run/hold current in IHOLD_IRUN from range-checked values.
+    pub fn set_run_hold_current(
+        &mut self,
+        irun: Irun,
+        ihold: Ihold,
+        ihold_delay: IholdDelay,
+    ) -> Result<(), TmcError> {
         let mut val = 0u32;
-        val |= (ihold as u32) & 0x1F;
-        val |= ((irun as u32) & 0x1F) << 8;
-        val |= ((ihold_delay as u32) & 0x0F) << 16;
-        self.write_register(REG_IHOLD_IRUN, val)?;
+        val |= ihold.get() as u32;
+        val |= (irun.get() as u32) << 8;
+        val |= (ihold_delay.get() as u32) << 16;
+        self.write_register(REG_IHOLD_IRUN, val)
+    }
+
+    /// Set IHOLDDELAY so the run-to-hold current ramp takes about `ms`
+    /// milliseconds, using the configured clock. Returns the delay applied.
+    ///
+    /// The ramp length depends on IRUN − IHOLD, so the currents must have been
+    /// set first; otherwise this returns [`TmcError::InvalidState`].
+    pub fn set_hold_delay_ms(&mut self, ms: u32) -> Result<IholdDelay, TmcError> {
+        let Some(raw) = self.shadow.get(REG_IHOLD_IRUN) else {
+            return Err(TmcError::InvalidState(self.state));
+        };
+        let ihold = (raw & 0x1F) as u8;
+        let irun = ((raw >> 8) & 0x1F) as u8;
+        let delay =
+            IholdDelay::from_ramp_ms(ms, irun.saturating_sub(ihold), self.clock.frequency_hz());
+        self.set_run_hold_current(Irun::saturating(irun), Ihold::saturating(ihold), delay)?;
+        Ok(delay)
+    }
+
+    /// Set the StallGuard threshold (SGTHRS). DIAG signals a stall when
+    /// `SG_RESULT <= 2 * SGTHRS`.
+    pub fn set_stallguard_threshold(&mut self, sgthrs: u8) -> Result<(), TmcError> {
+        self.write_register(REG_SGTHRS, sgthrs as u32)
+    }
+
+    /// Program what the DIAG pin signals.
+    ///
+    /// - [`DiagOutput::StallOnly`]: stalls raise DIAG above the TCOOLTHRS
+    ///   velocity. A threshold set earlier, e.g. by `configure_hybrid`, is
+    ///   kept; without one TCOOLTHRS is set to its maximum so stalls are
+    ///   reported at every speed. Set SGTHRS separately.
+    /// - [`DiagOutput::ErrorFlags`]: TCOOLTHRS = 0, so only driver errors
+    ///   raise DIAG. This also turns CoolStep off.
+    pub fn set_diag_output(&mut self, diag: DiagOutput) -> Result<(), TmcError> {
+        const TCOOLTHRS_MAX: u32 = 0x000F_FFFF;
+        let tcoolthrs = match diag {
+            DiagOutput::StallOnly => match self.shadow_register(REG_TCOOLTHRS) {
+                Some(tcoolthrs) if tcoolthrs != 0 => return Ok(()),
+                _ => TCOOLTHRS_MAX,
+            },
+            DiagOutput::ErrorFlags => 0,
+        };
+        self.write_register(REG_TCOOLTHRS, tcoolthrs)
+    }
+
+    /// DIAG routing in effect, from the last TCOOLTHRS written. `None` before
+    /// TCOOLTHRS has been written; the chip then resets it to 0, i.e.
+    /// [`DiagOutput::ErrorFlags`].
+    pub fn diag_output(&self) -> Option<DiagOutput> {
+        self.shadow_register(REG_TCOOLTHRS).map(|tcoolthrs| {
+            if tcoolthrs == 0 {
+                DiagOutput::ErrorFlags
+            } else {
+                DiagOutput::StallOnly
+            }
+        })
+    }
+
+    /// Classify a DIAG edge by reading DRV_STATUS, according to
+    /// [`Self::diag_output`]; see [`StallDetector::diag_event`].
+    pub fn diag_event(&mut self) -> Result<DiagEvent, TmcError> {
+        let status = self.read_drv_status()?;
+        let diag = self.diag_output().unwrap_or(DiagOutput::ErrorFlags);
+        Ok(StallDetector::new(0)
+            .with_diag_output(diag)
+            .diag_event(&status))
+    }
+
+    /// Read the current StallGuard load measurement (SG_RESULT, 10 bits).
+    /// Lower values mean higher load.
+    pub fn read_sg_result(&mut self) -> Result<u16, TmcError> {
+        let raw = self.read_register_blocking(REG_SG_RESULT)?;
+        Ok((raw & 0x3FF) as u16)
+    }
+
+    /// Move using the internal step generator at `velocity` (VACTUAL, in µsteps/t).
+    ///
+    /// - `0` stops the internal generator and hands motion back to STEP/DIR.
+    /// - `±1` is the slowest possible rotation (about 0.7 µsteps/s with the internal
+    ///   clock): practically standstill, but the chip stays in VACTUAL mode and
+    ///   keeps ignoring the STEP input.
+    ///
+    /// Returns [`TmcError::RateTooHigh`] if `velocity` is outside ±[`crate::VACTUAL_MAX`]
+    /// or faster than the [`KinematicLimits`] allow.
+    pub fn rotate_at(&mut self, velocity: i32) -> Result<(), TmcError> {
+        if velocity.unsigned_abs() > self.max_vactual() {
+            return Err(TmcError::RateTooHigh);
+        }
+        if velocity != 0 {
+            self.ensure_can_move()?;
+            self.note_motion()?;
+        }
+        self.write_register(REG_VACTUAL, encode_vactual(velocity))?;
+        if velocity != self.vactual {
+            // Untracked change: integration restarts at the next update.
+            self.vactual_since_ms = None;
+        }
+        self.vactual = velocity;
+        self.set_moving(velocity != 0);
         Ok(())
     }
 
-    /// Low-level 32-bit register write via UART (blocking).
-    fn write_register(&mut self, reg: u8, value: u32) -> Result<(), TmcError> {
-        let packet = build_write_packet(self.slave_address, reg, value);
-        for &b in &packet {
-            nb::block!(self.serial.write(&[b])).map_err(|_| TmcError::SerialError)?;
+    /// Convert the position counter, VACTUAL bookkeeping and the move started
+    /// with `start_move` to a microstep resolution `num / den` times the
+    /// current one. VACTUAL itself must already have been rewritten.
+    pub(crate) fn rescale_microsteps(&mut self, vactual: i32, num: u32, den: u32) {
+        let scale = |v: i64| v * num as i64 / den as i64;
+        self.position = scale(self.position as i64) as i32;
+        self.publish_position();
+        self.vactual_remainder = scale(self.vactual_remainder);
+        self.vactual = vactual;
+        if let Some(active) = &mut self.active_move {
+            active.rescale(num, den);
         }
+        if let Some(counter) = &mut self.index_counter {
+            counter.rescale(num, den);
+        }
+    }
+
+    /// Like [`Self::rotate_at`], but keeps the position estimate exact across
+    /// the velocity change: motion up to `now_ms` is integrated at the old
+    /// velocity and the new one is integrated from `now_ms` on.
+    pub fn rotate_at_tracked(&mut self, velocity: i32, now_ms: u32) -> Result<(), TmcError> {
+        self.update_position_estimate(now_ms);
+        self.rotate_at(velocity)?;
+        self.vactual_since_ms = Some(now_ms);
         Ok(())
     }
 
+    /// Add the distance travelled under VACTUAL since the previous update to
+    /// the position counter and return the new position.
+    ///
+    /// Call this regularly with a millisecond timestamp (wrapping is fine)
+    /// while rotating. The estimate assumes the chip follows VACTUAL exactly at
+    /// the configured clock frequency and microstep resolution, so it drifts
+    /// with clock tolerance and is flagged by
+    /// [`Self::is_position_estimated`]. Time between a plain `rotate_at` and
+    /// the next update is not counted; change velocity with
+    /// [`Self::rotate_at_tracked`] to avoid that.
+    pub fn update_position_estimate(&mut self, now_ms: u32) -> i32 {
+        // VACTUAL is in microsteps per 2^24 clock cycles.
+        const DIVISOR: i128 = (1 << 24) * 1000;
+        if let (Some(since), true) = (self.vactual_since_ms, self.vactual != 0) {
+            let elapsed_ms = now_ms.wrapping_sub(since) as i128;
+            let travelled = self.vactual as i128 * self.clock.frequency_hz() as i128 * elapsed_ms
+                + self.vactual_remainder as i128;
+            let steps = travelled / DIVISOR;
+            self.vactual_remainder = (travelled % DIVISOR) as i64;
+            self.position = self.position.wrapping_add(steps as i32);
+            self.position_estimated = true;
+            self.publish_position();
+        }
+        self.vactual_since_ms = Some(now_ms);
+        self.position
+    }
+
+    /// Stop integrating VACTUAL motion and drop the sub-step remainder, once
+    /// VACTUAL is 0 and the position counter follows STEP pulses again.
+    pub(crate) fn end_vactual_tracking(&mut self) {
+        self.vactual_since_ms = None;
+        self.vactual_remainder = 0;
+    }
+
+    /// Like [`Self::rotate_at`], but clamps `velocity` to ±[`crate::VACTUAL_MAX`] and the
+    /// kinematic limits instead of failing. Returns the velocity actually applied.
+    pub fn rotate_at_clamped(&mut self, velocity: i32) -> Result<i32, TmcError> {
+        let max = self.max_vactual() as i32;
+        let velocity = velocity.clamp(-max, max);
+        self.rotate_at(velocity)?;
+        Ok(velocity)
+    }
+
+    /// Stop the internal step generator (VACTUAL = 0) and return to STEP/DIR control.
+    pub fn stop_rotation(&mut self) -> Result<(), TmcError> {
+        self.rotate_at(0)
+    }
+
+    /// Last velocity written to VACTUAL. The register itself is write-only.
+    pub fn vactual(&self) -> i32 {
+        self.vactual
+    }
+
+    /// Read and decode DRV_STATUS (temperature, short, open-load and standstill flags).
+    pub fn read_drv_status(&mut self) -> Result<DrvStatus, TmcError> {
+        let raw = self.read_register_blocking(REG_DRVSTATUS)?;
+        Ok(DrvStatus::from_raw(raw))
+    }
+
+    /// Poll GSTAT and DRV_STATUS and queue events for newly raised conditions.
+    ///
+    /// Flags that stay set only produce an event when they first appear. GSTAT
+    /// flags are cleared after being reported.
+    pub fn check_status(&mut self) -> Result<DrvStatus, TmcError> {
+        let gstat = self.read_register_blocking(REG_GSTAT)?;
+        let mut worst = None;
+        if gstat & GSTAT_RESET != 0 {
+            self.push_event(TmcEvent::Reset);
+        }
+        if gstat & GSTAT_UV_CP != 0 {
+            self.push_event(TmcEvent::ChargePumpUndervoltage);
+            self.enter_fault(FaultKind::ChargePumpUndervoltage);
+            worst = Some(FaultCause::ChargePumpUndervoltage);
+        }
+        // Latch a charge pump fault even if the rest of the poll fails.
+        let status = match self.clear_gstat_and_read_status(gstat) {
+            Ok(status) => status,
+            Err(e) => {
+                if let Some(cause) = worst {
+                    self.latch_fault(cause);
+                }
+                return Err(e);
+            }
+        };
+        let prev = self.last_status;
+        if status.otpw && !prev.otpw {
+            self.push_event(TmcEvent::Otpw);
+        }
+        if status.ot && !prev.ot {
+            self.push_event(TmcEvent::Overtemperature);
+        }
+        if status.short() && !prev.short() {
+            self.push_event(TmcEvent::Short);
+        }
+        if status.ot {
+            self.enter_fault(FaultKind::Overtemperature);
+            worst = Some(FaultCause::Overtemperature);
+        }
+        if status.short() {
+            self.enter_fault(FaultKind::Short);
+            worst = Some(FaultCause::Short);
+        }
+        if let Some(cause) = worst {
+            self.latch_fault(cause);
+        }
+        self.last_status = status;
+        Ok(status)
+    }
+
+    fn clear_gstat_and_read_status(&mut self, gstat: u32) -> Result<DrvStatus, TmcError> {
+        if gstat != 0 {
+            // Write 1 to clear.
+            self.write_register(REG_GSTAT, gstat)?;
+        }
+        self.read_drv_status()
+    }
+
+    /// Record a fault. An emergency stop takes precedence and is kept.
+    fn enter_fault(&mut self, kind: FaultKind) {
+        if self.state != DriverState::EStopped {
+            self.state = DriverState::Fault(kind);
+        }
+    }
+
+    /// Latch `cause` unless an earlier fault is still latched.
+    fn latch_fault(&mut self, cause: FaultCause) {
+        if self.fault_latch.is_none() {
+            self.fault_latch = Some(LatchedFault {
+                cause,
+                timestamp_ms: self.fault_clock.map(|clock| clock()),
+            });
+        }
+    }
+
+    /// The first fault (short, overtemperature, charge pump undervoltage or
+    /// communication loss) seen since [`Self::clear_fault_latch`], even if it
+    /// has cleared itself since.
+    ///
+    /// Faults are seen by [`Self::check_status`] and by the CRC quarantine
+    /// suspending UART traffic. If a single status poll shows several, the
+    /// most severe one is latched.
+    pub fn last_fault(&self) -> Option<LatchedFault> {
+        self.fault_latch
+    }
+
+    /// Forget the latched fault, so the next one is latched.
+    pub fn clear_fault_latch(&mut self) {
+        self.fault_latch = None;
+    }
+
+    /// Timestamp latched faults with `clock`, e.g. milliseconds since boot.
+    /// `None` removes it.
+    pub fn set_fault_clock(&mut self, clock: Option<fn() -> u32>) {
+        self.fault_clock = clock;
+    }
+
+    /// Read the diagnostic registers and append a snapshot to the history.
+    ///
+    /// Call this periodically; after a fault, [`Self::diagnostics_history`]
+    /// holds the last `HISTORY` snapshots leading up to it.
+    pub fn capture_diagnostics(&mut self, now_ms: u32) -> Result<DiagnosticsSnapshot, TmcError> {
+        let mut raw = [0u32; 4];
+        self.read_registers_all(
+            &[REG_GSTAT, REG_DRVSTATUS, REG_SG_RESULT, REG_TSTEP],
+            &mut raw,
+        )?;
+        let [gstat, drv_status, sg_result, tstep] = raw;
+        let drv_status = DrvStatus::from_raw(drv_status);
+        let sg_result = (sg_result & 0x3FF) as u16;
+        let tstep = tstep & 0x000F_FFFF;
+
+        let snapshot = DiagnosticsSnapshot {
+            timestamp_ms: now_ms,
+            gstat: (gstat & 0x07) as u8,
+            drv_status,
+            sg_result,
+            tstep,
+            position: self.position,
+        };
+        self.history.push(snapshot);
+        Ok(snapshot)
+    }
+
+    /// Snapshots recorded by [`Self::capture_diagnostics`], oldest first.
+    pub fn diagnostics_history(&self) -> impl Iterator<Item = &DiagnosticsSnapshot> + '_ {
+        self.history.iter()
+    }
+
+    /// Forget all recorded snapshots.
+    pub fn clear_diagnostics_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Drain queued events, oldest first.
+    pub fn poll_events(&mut self) -> impl Iterator<Item = TmcEvent> + '_ {
+        core::iter::from_fn(move || self.events.pop())
+    }
+
+    /// Number of events lost because the queue overflowed.
+    pub fn dropped_events(&self) -> u32 {
+        self.events.dropped()
+    }
+
+    /// Queue an event for [`Self::poll_events`].
+    pub(crate) fn push_event(&mut self, event: TmcEvent) {
+        self.events.push(event);
+    }
+
+    /// Mark the position counter as referenced to (or no longer to) home.
+    pub(crate) fn set_homed(&mut self, homed: bool) {
+        self.homed = homed;
+    }
+
+    /// Fingerprint of the configuration written so far.
+    pub(crate) fn config_fingerprint(&self) -> u32 {
+        self.shadow.fingerprint()
+    }
+
+    /// Exchange the shadow copies with `other`, for a bus driver that moves
+    /// to another node address.
+    pub(crate) fn swap_shadow(&mut self, other: &mut ShadowRegisters) {
+        core::mem::swap(&mut self.shadow, other);
+    }
+
+    /// MSCNT recorded by the last `disable`, clearing it.
+    pub(crate) fn take_saved_mscnt(&mut self) -> Option<u16> {
+        self.saved_mscnt.take()
+    }
+
+    /// Read the actual microstep currents of both coils (MSCURACT).
+    pub fn read_mscuract(&mut self) -> Result<MsCurAct, TmcError> {
+        let raw = self.read_register_blocking(REG_MSCURACT)?;
+        Ok(MsCurAct::from_raw(raw))
+    }
+
+    /// Read the stealthChop amplitude results (PWM_SCALE).
+    pub fn read_pwm_scale(&mut self) -> Result<PwmScale, TmcError> {
+        let raw = self.read_register_blocking(REG_PWM_SCALE)?;
+        Ok(PwmScale::from_raw(raw))
+    }
+
+    /// Read the automatically tuned stealthChop parameters (PWM_AUTO).
+    pub fn read_pwm_auto(&mut self) -> Result<PwmAuto, TmcError> {
+        let raw = self.read_register_blocking(REG_PWM_AUTO)?;
+        Ok(PwmAuto::from_raw(raw))
+    }
+
+    /// Low-level 32-bit register write via UART (blocking).
+    pub fn write_register(&mut self, reg: u8, value: u32) -> Result<(), TmcError> {
+        let packet = write_packet_for(&mut self.crc, self.address_byte, reg, value);
+        self.send_datagram(reg, Operation::Write, &packet)?;
+        self.shadow.record(reg, value);
+        Ok(())
+    }
+
+    /// Write `value` unless the shadow copy shows the register already holds
+    /// it; only skips writes for shadowed (write-only) registers.
+    pub(crate) fn write_register_if_changed(
+        &mut self,
+        reg: u8,
+        value: u32,
+    ) -> Result<(), TmcError> {
+        if self.shadow.get(reg) == Some(value) {
+            return Ok(());
+        }
+        self.write_register(reg, value)
+    }
+
+    /// Last value written to a write-only register, `None` if it has not been
+    /// written yet or is readable (read it from the chip instead).
+    pub fn shadow_register(&self, reg: u8) -> Option<u32> {
+        self.shadow.get(reg)
+    }
+
     /// Low-level 32-bit register read via UART (blocking).
-    fn read_register(&mut self, reg: u8) -> Result<u32, TmcError> {
-        let packet = build_read_packet(self.slave_address, reg);
-        for &b in &packet {
-            nb::block!(self.serial.write(&[b])).map_err(|_| TmcError::SerialError)?;
+    ///
+    /// Same as [`Self::read_register_blocking`].
+    pub fn read_register(&mut self, reg: u8) -> Result<u32, TmcError> {
+        self.read_register_blocking(reg)
+    }
+
+    /// Read a register, blocking until the reply arrives or times out.
+    ///
+    /// Abandons any read started with [`Self::read_register_nb`].
+    pub fn read_register_blocking(&mut self, reg: u8) -> Result<u32, TmcError> {
+        self.pending_read = None;
+        let mut attempts_left = self.retries;
+        loop {
+            match self.read_register_once(reg) {
+                Err(e) if attempts_left > 0 && e.is_transient() => attempts_left -= 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// One request/reply transaction, without retries.
+    fn read_register_once(&mut self, reg: u8) -> Result<u32, TmcError> {
+        self.send_read_request(reg)?;
+        let resp = self.read_reply(reg).map_err(|e| self.read_failed(e))?;
+        self.parse_reply(reg, &resp)
+            .map_err(|e| self.read_failed(e))
+    }
+
+    /// Receive a reply frame, skipping stray bytes before its sync byte.
+    fn read_reply(&mut self, reg: u8) -> Result<[u8; READ_REPLY_LEN], TmcError> {
+        let mut resp = [0u8; READ_REPLY_LEN];
+        let mut skipped = 0;
+        loop {
+            let byte = self.read_reply_byte(reg, 0)?;
+            if is_sync_byte(byte) {
+                resp[0] = byte;
+                break;
+            }
+            skipped += 1;
+            if skipped > MAX_RESYNC_SKIP {
+                return Err(TmcError::VerificationError {
+                    reg,
+                    op: Operation::Read,
+                });
+            }
+        }
+        for (received, byte) in resp.iter_mut().enumerate().skip(1) {
+            *byte = self.read_reply_byte(reg, received)?;
         }
+        Ok(resp)
+    }
 
-        let mut resp = [0u8; 7];
-        for i in 0..7 {
-            let val = 0u8;
-            let val =
-                nb::block!(self.serial.read(&mut [val])).map_err(|_| TmcError::SerialError)?;
-            resp[i] = val as u8;
+    /// Suspend UART traffic for a while after `crc_failures` consecutive CRC
+    /// errors, or `None` to keep retrying (the default).
+    ///
+    /// While suspended, register accesses fail at once with
+    /// [`TmcError::CommSuspended`] instead of piling up blocking retries
+    /// during an EMI burst; STEP/DIR/EN keep working. Entering and leaving
+    /// the quarantine push [`TmcEvent::CommDegraded`] and
+    /// [`TmcEvent::CommRestored`]. The backoff is timed by
+    /// [`Self::poll_comm`]. Without a quarantine, every CRC error pushes
+    /// `CommDegraded`.
+    pub fn set_comm_quarantine(&mut self, quarantine: Option<CommQuarantine>) {
+        self.quarantine = quarantine;
+        self.crc_streak = 0;
+        if quarantine.is_none() && self.comm_suspended {
+            self.resume_comm();
         }
+    }
 
-        // Validate address
-        if (resp[0] & 0x0F) != (self.slave_address & 0x0F) {
-            return Err(TmcError::VerificationError);
+    /// Time the quarantine backoff against the application's clock.
+    ///
+    /// Call regularly with a millisecond timestamp (wrapping is fine). The
+    /// backoff starts at the first call after UART traffic was suspended.
+    /// Returns `true` when traffic has been resumed by this call.
+    pub fn poll_comm(&mut self, now_ms: u32) -> bool {
+        if !self.comm_suspended {
+            return false;
+        }
+        let backoff = self.quarantine.map_or(0, |q| q.backoff_ms);
+        let since = *self.suspended_since_ms.get_or_insert(now_ms);
+        if now_ms.wrapping_sub(since) < backoff {
+            return false;
+        }
+        self.resume_comm();
+        true
+    }
+
+    /// `true` while UART traffic is suspended by the quarantine.
+    pub fn is_comm_suspended(&self) -> bool {
+        self.comm_suspended
+    }
+
+    fn suspend_comm(&mut self) {
+        if !self.comm_suspended {
+            self.comm_suspended = true;
+            self.suspended_since_ms = None;
+            self.pending_read = None;
+            self.push_event(TmcEvent::CommDegraded);
+            self.latch_fault(FaultCause::CommLoss);
+        }
+    }
+
+    fn resume_comm(&mut self) {
+        self.comm_suspended = false;
+        self.suspended_since_ms = None;
+        self.crc_streak = 0;
+        self.push_event(TmcEvent::CommRestored);
+    }
+
+    /// Clean up after a failed reply so the next transaction starts on a
+    /// frame boundary: report CRC errors and discard whatever is left in RX.
+    fn read_failed(&mut self, err: TmcError) -> TmcError {
+        if matches!(err, TmcError::CrcError { .. }) {
+            self.crc_streak = self.crc_streak.saturating_add(1);
+            match self.quarantine {
+                Some(q) if self.crc_streak >= q.crc_failures => self.suspend_comm(),
+                Some(_) => {}
+                None => self.push_event(TmcEvent::CommDegraded),
+            }
+        }
+        // Already failing; a serial error here would only hide the cause.
+        let _ = self.drain_rx();
+        err
+    }
+
+    /// Discard bytes already waiting in RX and return how many were dropped.
+    ///
+    /// Stale bytes (echoes, noise while the chip powers up, the tail of a
+    /// timed-out reply) would otherwise be parsed as the next reply. This runs
+    /// automatically before every read request; at most 64 bytes are dropped
+    /// per call so a babbling line cannot hang the driver.
+    pub fn drain_rx(&mut self) -> Result<usize, TmcError> {
+        let serial_err = |e| TmcError::serial(e, 0, Operation::Read);
+        let mut buf = [0u8; 8];
+        let mut discarded = 0;
+        while discarded < MAX_DISCARD_BYTES && self.serial.read_ready().map_err(serial_err)? {
+            match self.serial.read(&mut buf).map_err(serial_err)? {
+                0 => break,
+                n => discarded += n,
+            }
+        }
+        Ok(discarded)
+    }
+
+    /// Read each register in `regs` into the matching entry of `out`, with the
+    /// usual retry and timeout handling per register.
+    ///
+    /// A failing register does not stop the others; the returned
+    /// [`MultiReadStatus`] says which entries of `out` are valid. At most 32
+    /// registers can be read per call ([`TmcError::InvalidArgument`] otherwise).
+    pub fn read_registers(
+        &mut self,
+        regs: &[u8],
+        out: &mut [u32],
+    ) -> Result<MultiReadStatus, TmcError> {
+        let count = regs.len().min(out.len());
+        if count > 32 {
+            return Err(TmcError::InvalidArgument);
+        }
+        let mut status = MultiReadStatus::new(count);
+        for i in 0..count {
+            match self.read_register_blocking(regs[i]) {
+                Ok(value) => out[i] = value,
+                Err(e) => status.record_failure(i, regs[i], e),
+            }
+        }
+        Ok(status)
+    }
+
+    /// Number of times a failed read (CRC, timeout, bad reply) is retried.
+    pub fn set_retries(&mut self, retries: u8) {
+        self.retries = retries;
+    }
+
+    /// Number of times a failed read is retried.
+    pub fn retries(&self) -> u8 {
+        self.retries
+    }
+
+    /// Read several registers back to back into `out` (one value per entry of
+    /// `regs`; extra entries of `out` are left untouched), failing on the
+    /// first register that cannot be read.
+    ///
+    /// If SLAVECONF.SENDDELAY was set to 4 or more through this driver and
+    /// echo handling is off, the request for each next register is sent while
+    /// the chip is still waiting out SENDDELAY before the previous reply,
+    /// which roughly halves the time per register. Otherwise, and for every
+    /// register after a failed pipelined reply, requests go strictly one at a
+    /// time with the usual retry and timeout handling. Use
+    /// [`Self::read_registers`] to keep going past a failing register.
+    pub fn read_registers_all(&mut self, regs: &[u8], out: &mut [u32]) -> Result<(), TmcError> {
+        let count = regs.len().min(out.len());
+        let mut done = 0;
+        if count > 1 && self.pipelined_reads() {
+            done = self.read_pipelined(&regs[..count], out);
+        }
+        for i in done..count {
+            out[i] = self.read_register_blocking(regs[i])?;
+        }
+        Ok(())
+    }
+
+    /// `true` if the next read request can be sent before the previous reply:
+    /// SENDDELAY leaves room for it and our own bytes do not echo back in
+    /// between the replies.
+    fn pipelined_reads(&self) -> bool {
+        let senddelay = self
+            .shadow
+            .get(REG_SLAVECONF)
+            .map_or(0, |raw| (raw >> 8) & 0x0F);
+        !self.echo_handling && senddelay >= PIPELINE_MIN_SENDDELAY
+    }
+
+    /// Read `regs` keeping one request ahead of the replies, without retries.
+    /// Returns how many leading entries of `out` were read; the caller reads
+    /// the rest one at a time.
+    fn read_pipelined(&mut self, regs: &[u8], out: &mut [u32]) -> usize {
+        self.pending_read = None;
+        if self.send_read_request(regs[0]).is_err() {
+            return 0;
+        }
+        for (i, &reg) in regs.iter().enumerate() {
+            if let Some(&next) = regs.get(i + 1) {
+                // No RX drain here: the reply to `reg` may already be arriving.
+                let packet = read_packet_for(&mut self.crc, self.address_byte, next);
+                if self.send_datagram(next, Operation::Read, &packet).is_err() {
+                    let _ = self.drain_rx();
+                    return i;
+                }
+            }
+            match self
+                .read_reply(reg)
+                .and_then(|resp| self.parse_reply(reg, &resp))
+            {
+                Ok(value) => out[i] = value,
+                Err(e) => {
+                    self.read_failed(e);
+                    return i;
+                }
+            }
+        }
+        regs.len()
+    }
+
+    /// Read a register without blocking on the reply.
+    ///
+    /// The first call sends the request; later calls with the same `reg` collect
+    /// whatever reply bytes have arrived and return `Ok(None)` until the frame
+    /// is complete. Calling with a different register while a read is in flight
+    /// returns [`TmcError::Busy`].
+    pub fn read_register_nb(&mut self, reg: u8) -> Result<Option<u32>, TmcError> {
+        let mut pending = match self.pending_read.take() {
+            Some(pending) if pending.reg != reg => {
+                self.pending_read = Some(pending);
+                return Err(TmcError::Busy);
+            }
+            Some(pending) => pending,
+            None => {
+                self.send_read_request(reg)?;
+                PendingRead {
+                    reg,
+                    resp: [0u8; READ_REPLY_LEN],
+                    received: 0,
+                }
+            }
+        };
+
+        while pending.received < READ_REPLY_LEN {
+            if !self
+                .serial
+                .read_ready()
+                .map_err(|e| TmcError::serial(e, reg, Operation::Read))?
+            {
+                self.pending_read = Some(pending);
+                return Ok(None);
+            }
+            // Until the sync byte shows up, read one byte at a time and drop
+            // anything that cannot start a frame.
+            let end = if pending.received == 0 {
+                1
+            } else {
+                READ_REPLY_LEN
+            };
+            let n = self
+                .serial
+                .read(&mut pending.resp[pending.received..end])
+                .map_err(|e| TmcError::serial(e, reg, Operation::Read))?;
+            if pending.received == 0 && n == 1 && !is_sync_byte(pending.resp[0]) {
+                continue;
+            }
+            pending.received += n;
+        }
+        self.parse_reply(reg, &pending.resp)
+            .map(Some)
+            .map_err(|e| self.read_failed(e))
+    }
+
+    /// Transmit a read request datagram for `reg`.
+    fn send_read_request(&mut self, reg: u8) -> Result<(), TmcError> {
+        self.drain_rx()?;
+        let packet = read_packet_for(&mut self.crc, self.address_byte, reg);
+        self.send_datagram(reg, Operation::Read, &packet)
+    }
+
+    /// Transmit a datagram, consuming its echo if echo handling is on.
+    fn send_datagram(&mut self, reg: u8, op: Operation, packet: &[u8]) -> Result<(), TmcError> {
+        if !self.variant.has_register(reg) {
+            return Err(TmcError::Unsupported);
+        }
+        if self.comm_suspended {
+            return Err(TmcError::CommSuspended);
+        }
+        let serial_err = |e| TmcError::serial(e, reg, op);
+        self.serial.write_all(packet).map_err(serial_err)?;
+        self.serial.flush().map_err(serial_err)?;
+        if self.echo_handling {
+            for (received, &sent) in packet.iter().enumerate() {
+                if self.read_reply_byte(reg, received)? != sent {
+                    // Another node talked over us.
+                    return Err(TmcError::VerificationError { reg, op });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate a complete reply frame and extract its data word.
+    fn parse_reply(&mut self, reg: u8, resp: &[u8; READ_REPLY_LEN]) -> Result<u32, TmcError> {
+        // CRC first: address and register of a corrupted frame mean nothing
+        let crc_calc = self.crc.crc8(&resp[..7]);
+        if crc_calc != resp[7] {
+            return Err(TmcError::CrcError { reg });
+        }
+        self.crc_streak = 0;
+        // Validate address: replies go to the master
+        if resp[1] != MASTER_ADDRESS {
+            return Err(TmcError::ReplyAddressMismatch {
+                reg,
+                address: resp[1],
+            });
         }
         // Validate register
-        if (resp[1] & 0x7F) != (reg & 0x7F) {
-            return Err(TmcError::VerificationError);
+        if (resp[2] & 0x7F) != (reg & 0x7F) {
+            return Err(TmcError::ReplyRegisterMismatch {
+                reg,
+                observed: resp[2] & 0x7F,
+            });
         }
-        // CRC
-        let crc_calc = calc_crc8(&resp[..6]);
-        if crc_calc != resp[6] {
-            return Err(TmcError::CrcError);
+        Ok(u32::from_be_bytes([resp[3], resp[4], resp[5], resp[6]]))
+    }
+
+    /// Poll for one reply byte, giving up after `read_timeout` attempts.
+    fn read_reply_byte(&mut self, reg: u8, received: usize) -> Result<u8, TmcError> {
+        let mut buf = [0u8; 1];
+        for _ in 0..self.read_timeout {
+            if self
+                .serial
+                .read_ready()
+                .map_err(|e| TmcError::serial(e, reg, Operation::Read))?
+                && self
+                    .serial
+                    .read(&mut buf)
+                    .map_err(|e| TmcError::serial(e, reg, Operation::Read))?
+                    == 1
+            {
+                return Ok(buf[0]);
+            }
         }
+        Err(TmcError::Timeout {
+            reg,
+            bytes_received: received as u8,
+        })
+    }
+}
+
+#[cfg(feature = "uart")]
+impl<EN, STEP, DIR, SERIAL, const HISTORY: usize, CRC>
+    Tmc2209FullUartDiagnosticsAndControl<EN, STEP, DIR, SERIAL, HISTORY, CRC>
+where
+    EN: OutputPin,
+    STEP: OutputPin<Error = Infallible>,
+    DIR: OutputPin,
+    SERIAL: Write + Read + ReadReady,
+    CRC: Crc8Provider,
+{
+    /// Issue a single step pulse on an infallible STEP pin, for tight
+    /// stepping loops.
+    ///
+    /// Unlike [`Self::step_pulse`] this neither undoes an idle action nor
+    /// checks the driver state; call [`Self::wake`] before a burst of steps.
+    /// The position counter and [`StepPulse::inverted`] are honoured.
+    #[inline]
+    pub fn step_pulse_unchecked(&mut self) {
+        debug_assert!(!self.idle_applied, "stepping while idle");
+        self.motion_since_poll = true;
+        let (active, idle) = (!self.step_shape.inverted, self.step_shape.inverted);
+        set_infallible(&mut self.step, active);
+        set_infallible(&mut self.step, idle);
+        self.position = self
+            .position
+            .wrapping_add(if self.clockwise { 1 } else { -1 });
+        self.odometer.record_step(self.clockwise);
+        self.publish_position();
+    }
+}
 
-        let d0 = resp[2] as u32;
-        let d1 = resp[3] as u32;
-        let d2 = resp[4] as u32;
-        let d3 = resp[5] as u32;
-        let val = d0 | (d1 << 8) | (d2 << 16) | (d3 << 24);
-        Ok(val)
+#[cfg(feature = "uart")]
+#[inline]
+fn set_infallible<P: OutputPin<Error = Infallible>>(pin: &mut P, high: bool) {
+    let result = if high { pin.set_high() } else { pin.set_low() };
+    match result {
+        Ok(()) => {}
+        Err(never) => match never {},
     }
 }
@@ -0,0 +1,342 @@
+//! Adapters that sit between the driver and the UART.
+//!
+//! Each adapter implements the same `embedded-io` traits the Full UART driver
+//! requires, so it is passed in place of the bare serial port.
+
+#[cfg(feature = "critical-section")]
+use core::cell::RefCell;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write};
+
+/// Error of a [`HalfDuplex`] transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HalfDuplexError<E> {
+    /// The underlying serial port failed.
+    Serial(E),
+    /// The direction-control pin could not be set.
+    Pin,
+}
+
+impl<E: embedded_io::Error> embedded_io::Error for HalfDuplexError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            HalfDuplexError::Serial(e) => e.kind(),
+            HalfDuplexError::Pin => ErrorKind::Other,
+        }
+    }
+}
+
+/// UART behind a half-duplex transceiver or tri-state buffer.
+///
+/// The direction-control pin (DE, or DE and /RE tied together) is driven high
+/// on the first write of a datagram. On `flush`, which the driver calls after
+/// every datagram, the adapter waits for the serial port to finish sending,
+/// waits `turnaround_ns` more and releases the pin so the reply can come in.
+/// The serial port's `flush` must therefore only return once the last stop
+/// bit has left the shift register.
+pub struct HalfDuplex<S, DE, D> {
+    serial: S,
+    de: DE,
+    delay: D,
+    turnaround_ns: u32,
+    transmitting: bool,
+}
+
+impl<S, DE, D> HalfDuplex<S, DE, D>
+where
+    S: Read + Write + ReadReady,
+    DE: OutputPin,
+    D: DelayNs,
+{
+    /// Wrap `serial`, controlling the transceiver direction with `de`.
+    pub fn new(serial: S, de: DE, delay: D, turnaround_ns: u32) -> Self {
+        HalfDuplex {
+            serial,
+            de,
+            delay,
+            turnaround_ns,
+            transmitting: false,
+        }
+    }
+
+    /// Give back the serial port, pin and delay.
+    pub fn release(self) -> (S, DE, D) {
+        (self.serial, self.de, self.delay)
+    }
+
+    /// Switch the transceiver to receive if it is transmitting.
+    fn release_bus(&mut self) -> Result<(), HalfDuplexError<S::Error>> {
+        if self.transmitting {
+            self.delay.delay_ns(self.turnaround_ns);
+            self.de.set_low().map_err(|_| HalfDuplexError::Pin)?;
+            self.transmitting = false;
+        }
+        Ok(())
+    }
+}
+
+impl<S, DE, D> ErrorType for HalfDuplex<S, DE, D>
+where
+    S: ErrorType,
+{
+    type Error = HalfDuplexError<S::Error>;
+}
+
+impl<S, DE, D> Write for HalfDuplex<S, DE, D>
+where
+    S: Read + Write + ReadReady,
+    DE: OutputPin,
+    D: DelayNs,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if !self.transmitting {
+            self.de.set_high().map_err(|_| HalfDuplexError::Pin)?;
+            self.transmitting = true;
+        }
+        self.serial.write(buf).map_err(HalfDuplexError::Serial)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.serial.flush().map_err(HalfDuplexError::Serial)?;
+        self.release_bus()
+    }
+}
+
+impl<S, DE, D> Read for HalfDuplex<S, DE, D>
+where
+    S: Read + Write + ReadReady,
+    DE: OutputPin,
+    D: DelayNs,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.release_bus()?;
+        self.serial.read(buf).map_err(HalfDuplexError::Serial)
+    }
+}
+
+impl<S, DE, D> ReadReady for HalfDuplex<S, DE, D>
+where
+    S: Read + Write + ReadReady,
+    DE: OutputPin,
+    D: DelayNs,
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        self.release_bus()?;
+        self.serial.read_ready().map_err(HalfDuplexError::Serial)
+    }
+}
+
+/// Receiver of the raw bytes exchanged with the chip, e.g. to stream them to
+/// a host-side protocol analyser.
+pub trait BusTap {
+    /// A complete transmitted datagram (request or write).
+    fn on_tx(&mut self, bytes: &[u8]);
+
+    /// Bytes as they were received, in arrival-sized chunks.
+    fn on_rx(&mut self, bytes: &[u8]);
+}
+
+impl<T: BusTap + ?Sized> BusTap for &mut T {
+    fn on_tx(&mut self, bytes: &[u8]) {
+        (**self).on_tx(bytes)
+    }
+
+    fn on_rx(&mut self, bytes: &[u8]) {
+        (**self).on_rx(bytes)
+    }
+}
+
+/// Longest datagram the driver sends; longer runs are reported in pieces.
+const TAP_TX_BUF_LEN: usize = 8;
+
+/// UART that reports all traffic to a [`BusTap`].
+///
+/// Transmitted bytes are collected until `flush`, which the driver calls after
+/// each datagram, and reported as one datagram. Received bytes are reported
+/// as soon as they are read.
+pub struct Tapped<S, T> {
+    serial: S,
+    tap: T,
+    tx: [u8; TAP_TX_BUF_LEN],
+    tx_len: usize,
+}
+
+impl<S, T> Tapped<S, T>
+where
+    S: Read + Write + ReadReady,
+    T: BusTap,
+{
+    /// Wrap `serial`, reporting its traffic to `tap`.
+    pub fn new(serial: S, tap: T) -> Self {
+        Tapped {
+            serial,
+            tap,
+            tx: [0; TAP_TX_BUF_LEN],
+            tx_len: 0,
+        }
+    }
+
+    /// The tap, e.g. to drain a buffer it fills.
+    pub fn tap(&mut self) -> &mut T {
+        &mut self.tap
+    }
+
+    /// Give back the serial port and the tap.
+    pub fn release(self) -> (S, T) {
+        (self.serial, self.tap)
+    }
+
+    fn report_tx(&mut self) {
+        if self.tx_len > 0 {
+            self.tap.on_tx(&self.tx[..self.tx_len]);
+            self.tx_len = 0;
+        }
+    }
+}
+
+impl<S, T> ErrorType for Tapped<S, T>
+where
+    S: ErrorType,
+{
+    type Error = S::Error;
+}
+
+impl<S, T> Write for Tapped<S, T>
+where
+    S: Read + Write + ReadReady,
+    T: BusTap,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let written = self.serial.write(buf)?;
+        for &byte in &buf[..written] {
+            if self.tx_len == TAP_TX_BUF_LEN {
+                self.report_tx();
+            }
+            self.tx[self.tx_len] = byte;
+            self.tx_len += 1;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.report_tx();
+        self.serial.flush()
+    }
+}
+
+impl<S, T> Read for Tapped<S, T>
+where
+    S: Read + Write + ReadReady,
+    T: BusTap,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.report_tx();
+        let n = self.serial.read(buf)?;
+        if n > 0 {
+            self.tap.on_rx(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl<S, T> ReadReady for Tapped<S, T>
+where
+    S: Read + Write + ReadReady,
+    T: BusTap,
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        self.serial.read_ready()
+    }
+}
+
+/// Serial port that reports when its transmitter has gone idle, so a flush
+/// can be polled instead of blocking. Needed by [`SharedSerial`].
+#[cfg(feature = "critical-section")]
+pub trait TxIdle: ErrorType {
+    /// `true` once every byte written so far has been sent.
+    fn tx_idle(&mut self) -> Result<bool, Self::Error>;
+}
+
+/// Serial port shared between contexts through a `critical-section` mutex.
+///
+/// Every operation takes the critical section for as little as possible: one
+/// byte per `write`, one byte per `read` and a single `read_ready` or
+/// [`TxIdle::tx_idle`] check, with the section released between polls. A UART
+/// read that waits for a slow or missing reply, or a `flush` waiting for the
+/// last byte to leave, therefore never holds off interrupts (such as the step
+/// timer) for longer than one access of the serial peripheral.
+#[cfg(feature = "critical-section")]
+pub struct SharedSerial<'a, S> {
+    serial: &'a critical_section::Mutex<RefCell<S>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<'a, S> SharedSerial<'a, S>
+where
+    S: Read + Write + ReadReady,
+{
+    /// Access the serial port inside `serial`.
+    pub fn new(serial: &'a critical_section::Mutex<RefCell<S>>) -> Self {
+        SharedSerial { serial }
+    }
+
+    fn with<R>(&mut self, f: impl FnOnce(&mut S) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.serial.borrow_ref_mut(cs)))
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<S: ErrorType> ErrorType for SharedSerial<'_, S> {
+    type Error = S::Error;
+}
+
+#[cfg(feature = "critical-section")]
+impl<S> Write for SharedSerial<'_, S>
+where
+    S: Read + Write + ReadReady + TxIdle,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.with(|s| s.write(&buf[..1]))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        while !self.with(|s| s.tx_idle())? {}
+        Ok(())
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<S> Read for SharedSerial<'_, S>
+where
+    S: Read + Write + ReadReady,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let received = self.with(|s| match s.read_ready()? {
+                true => s.read(&mut buf[..1]).map(Some),
+                false => Ok(None),
+            })?;
+            if let Some(n) = received {
+                return Ok(n);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<S> ReadReady for SharedSerial<'_, S>
+where
+    S: Read + Write + ReadReady,
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        self.with(|s| s.read_ready())
+    }
+}
@@ -0,0 +1,214 @@
+//! Bounds-checked values for register fields.
+//!
+//! Each type can only hold values the corresponding register field accepts, so
+//! an out-of-range setting is rejected where it is created instead of deep
+//! inside a driver call.
+
+use crate::errors::TmcError;
+
+macro_rules! bounded_field {
+    ($(#[$doc:meta])* $name:ident, $max:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        pub struct $name(u8);
+
+        impl $name {
+            /// Largest accepted value.
+            pub const MAX: u8 = $max;
+
+            /// Create the value, or [`TmcError::InvalidArgument`] if above [`Self::MAX`].
+            pub const fn try_new(value: u8) -> Result<Self, TmcError> {
+                if value > Self::MAX {
+                    Err(TmcError::InvalidArgument)
+                } else {
+                    Ok($name(value))
+                }
+            }
+
+            /// Create the value, clamping it to [`Self::MAX`].
+            pub const fn saturating(value: u8) -> Self {
+                if value > Self::MAX {
+                    $name(Self::MAX)
+                } else {
+                    $name(value)
+                }
+            }
+
+            /// Raw field value.
+            pub const fn get(self) -> u8 {
+                self.0
+            }
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = TmcError;
+
+            fn try_from(value: u8) -> Result<Self, TmcError> {
+                Self::try_new(value)
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(value: $name) -> u8 {
+                value.0
+            }
+        }
+    };
+}
+
+bounded_field!(
+    /// Run current scale IRUN, in [0..31] (n+1)/32 of full scale.
+    Irun,
+    31
+);
+bounded_field!(
+    /// Standstill current scale IHOLD, in [0..31] (n+1)/32 of full scale.
+    Ihold,
+    31
+);
+bounded_field!(
+    /// Run-to-hold current ramp delay IHOLDDELAY, in [0..15] (2^18 clocks per step).
+    IholdDelay,
+    15
+);
+bounded_field!(
+    /// StallGuard threshold SGTHRS, in [0..255].
+    Sgthrs,
+    255
+);
+bounded_field!(
+    /// CoolStep lower threshold SEMIN, in [0..15] (0 disables CoolStep).
+    Semin,
+    15
+);
+bounded_field!(
+    /// CoolStep upper hysteresis SEMAX, in [0..15].
+    Semax,
+    15
+);
+bounded_field!(
+    /// Chopper off time TOFF, in [0..15] (0 disables the driver).
+    Toff,
+    15
+);
+
+impl IholdDelay {
+    /// Clock cycles per IHOLDDELAY unit.
+    pub const CLOCKS_PER_UNIT: u32 = 1 << 18;
+
+    /// Delay that ramps the current down over `current_steps` steps (IRUN − IHOLD)
+    /// in about `ms` milliseconds with a chip clock of `fclk_hz`.
+    ///
+    /// Rounds to the nearest unit; any non-zero time gives at least 1, and long
+    /// times saturate at [`Self::MAX`].
+    pub const fn from_ramp_ms(ms: u32, current_steps: u8, fclk_hz: u32) -> Self {
+        if ms == 0 {
+            return IholdDelay(0);
+        }
+        let steps = if current_steps == 0 {
+            1
+        } else {
+            current_steps as u64
+        };
+        let clocks = ms as u64 * fclk_hz as u64 / 1000;
+        let unit = steps * Self::CLOCKS_PER_UNIT as u64;
+        let value = (clocks + unit / 2) / unit;
+        if value == 0 {
+            IholdDelay(1)
+        } else if value > Self::MAX as u64 {
+            IholdDelay(Self::MAX)
+        } else {
+            IholdDelay(value as u8)
+        }
+    }
+
+    /// Ramp time in milliseconds for `current_steps` steps at `fclk_hz`.
+    pub const fn ramp_ms(self, current_steps: u8, fclk_hz: u32) -> u32 {
+        let clocks = self.0 as u64 * current_steps as u64 * Self::CLOCKS_PER_UNIT as u64;
+        (clocks * 1000 / fclk_hz as u64) as u32
+    }
+}
+
+/// A percentage in [0..100]. Arithmetic saturates at both ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Percent(u8);
+
+impl Percent {
+    /// 0 %
+    pub const ZERO: Percent = Percent(0);
+    /// 100 %
+    pub const FULL: Percent = Percent(100);
+
+    /// Create a percentage, clamping `value` to 100.
+    pub const fn new(value: u8) -> Self {
+        if value > 100 {
+            Percent(100)
+        } else {
+            Percent(value)
+        }
+    }
+
+    /// Create a percentage, or [`TmcError::InvalidArgument`] if above 100.
+    pub const fn try_new(value: u8) -> Result<Self, TmcError> {
+        if value > 100 {
+            Err(TmcError::InvalidArgument)
+        } else {
+            Ok(Percent(value))
+        }
+    }
+
+    /// Value in percent.
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    /// Sum, capped at 100 %.
+    pub const fn saturating_add(self, other: Percent) -> Self {
+        Percent::new(self.0 + other.0)
+    }
+
+    /// Difference, floored at 0 %.
+    pub const fn saturating_sub(self, other: Percent) -> Self {
+        Percent(self.0.saturating_sub(other.0))
+    }
+
+    /// `self` repeated `n` times, capped at 100 %.
+    pub const fn saturating_mul(self, n: u8) -> Self {
+        let product = self.0 as u16 * n as u16;
+        if product > 100 {
+            Percent(100)
+        } else {
+            Percent(product as u8)
+        }
+    }
+
+    /// `self` of `other`, e.g. 50 % of 50 % is 25 %. Rounds down.
+    pub const fn of(self, other: Percent) -> Self {
+        Percent((self.0 as u16 * other.0 as u16 / 100) as u8)
+    }
+
+    /// Scale `value` by this percentage, rounding down. Cannot overflow.
+    pub const fn scale(self, value: u32) -> u32 {
+        (value as u64 * self.0 as u64 / 100) as u32
+    }
+}
+
+impl Default for Percent {
+    fn default() -> Self {
+        Percent::FULL
+    }
+}
+
+impl TryFrom<u8> for Percent {
+    type Error = TmcError;
+
+    fn try_from(value: u8) -> Result<Self, TmcError> {
+        Self::try_new(value)
+    }
+}
+
+impl From<Percent> for u8 {
+    fn from(value: Percent) -> u8 {
+        value.0
+    }
+}
@@ -0,0 +1,146 @@
+//! Change detection on polled registers.
+//!
+//! Logging every poll of a status register drowns the interesting moments.
+//! [`RegisterWatcher`] remembers the previous value of each watched register
+//! and only reports what changed, which also makes EMI-induced bit flips in
+//! otherwise static configuration registers stand out. Changes are decoded
+//! through the register definitions into the fields that changed, with
+//! their old and new values.
+
+use crate::erased::ErasedTmc2209;
+use crate::errors::TmcError;
+use crate::regmap::{lookup_register, FieldDef, RegisterDef, TMC2209_REGISTERS};
+
+/// A watched register whose value differs from the previous poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange {
+    /// Register address
+    pub reg: u8,
+    /// Value at the previous poll, `None` on the first poll
+    pub old: Option<u32>,
+    /// Value just read
+    pub new: u32,
+    /// Bits that differ between `old` and `new` (all bits of `new` on the first poll)
+    pub changed_bits: u32,
+    /// Definition of the register, `None` if the watcher's table lacks it
+    pub def: Option<&'static RegisterDef>,
+}
+
+impl RegisterChange {
+    /// The fields that changed, lowest bit first.
+    ///
+    /// On the first poll every field with a non-zero value is reported.
+    /// Changed bits outside all defined fields only show in `changed_bits`.
+    pub fn fields(&self) -> impl Iterator<Item = FieldChange> + '_ {
+        let fields: &'static [FieldDef] = self.def.map_or(&[], |def| def.fields);
+        fields
+            .iter()
+            .filter(|field| field.mask() & self.changed_bits != 0)
+            .map(|field| FieldChange {
+                reg: self.reg,
+                name: field.name,
+                old: self.old.map(|old| field.get(old)),
+                new: field.get(self.new),
+            })
+    }
+}
+
+/// A field of a watched register whose value changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldChange {
+    /// Register address
+    pub reg: u8,
+    /// Datasheet name of the field
+    pub name: &'static str,
+    /// Field value at the previous poll, `None` on the first poll
+    pub old: Option<u32>,
+    /// Field value just read
+    pub new: u32,
+}
+
+/// Watches a fixed list of `N` registers for changes.
+pub struct RegisterWatcher<const N: usize> {
+    regs: [u8; N],
+    defs: &'static [RegisterDef],
+    last: [Option<u32>; N],
+    changes: [Option<RegisterChange>; N],
+}
+
+impl<const N: usize> RegisterWatcher<N> {
+    /// Watch the registers in `regs`, decoding fields with
+    /// [`TMC2209_REGISTERS`].
+    pub fn new(regs: [u8; N]) -> Self {
+        Self::with_definitions(regs, TMC2209_REGISTERS)
+    }
+
+    /// Watch the registers in `regs`, decoding fields with `defs`, e.g. a
+    /// table from `tmc_registers!` for a related chip.
+    pub fn with_definitions(regs: [u8; N], defs: &'static [RegisterDef]) -> Self {
+        RegisterWatcher {
+            regs,
+            defs,
+            last: [None; N],
+            changes: [None; N],
+        }
+    }
+
+    /// Read all watched registers and return those that changed since the
+    /// previous poll, in the order they were given.
+    ///
+    /// On a read error, registers read before the failure keep their new value.
+    pub fn poll<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+    ) -> Result<impl Iterator<Item = RegisterChange> + '_, TmcError> {
+        self.refresh(driver)?;
+        Ok(self.changes.iter().flatten().copied())
+    }
+
+    /// Like [`Self::poll`], reporting the fields that changed instead of
+    /// whole registers: every changed field of every changed register, in
+    /// register order.
+    pub fn poll_fields<D: ErasedTmc2209 + ?Sized>(
+        &mut self,
+        driver: &mut D,
+    ) -> Result<impl Iterator<Item = FieldChange> + '_, TmcError> {
+        self.refresh(driver)?;
+        Ok(self
+            .changes
+            .iter()
+            .flatten()
+            .flat_map(|change| change.fields()))
+    }
+
+    /// Read all watched registers and record what changed in `changes`.
+    fn refresh<D: ErasedTmc2209 + ?Sized>(&mut self, driver: &mut D) -> Result<(), TmcError> {
+        self.changes = [None; N];
+        for i in 0..N {
+            let new = driver.read_register(self.regs[i])?;
+            let old = self.last[i];
+            if old != Some(new) {
+                self.changes[i] = Some(RegisterChange {
+                    reg: self.regs[i],
+                    old,
+                    new,
+                    changed_bits: old.map_or(new, |old| old ^ new),
+                    def: lookup_register(self.defs, self.regs[i]),
+                });
+            }
+            self.last[i] = Some(new);
+        }
+        Ok(())
+    }
+
+    /// Value of `reg` at the last poll.
+    pub fn last_value(&self, reg: u8) -> Option<u32> {
+        self.regs
+            .iter()
+            .position(|&r| r == reg)
+            .and_then(|i| self.last[i])
+    }
+
+    /// Forget all previous values; the next poll reports every register.
+    pub fn reset(&mut self) {
+        self.last = [None; N];
+    }
+}
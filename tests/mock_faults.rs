@@ -0,0 +1,171 @@
+//! The Full UART driver against a `MockTmc2209` with injected line faults.
+
+use tmc2209_driver::registers::*;
+use tmc2209_driver::{
+    ChipVariant, Fault, MockTmc2209, NoPin, Tmc2209Bus, Tmc2209FullUartDiagnosticsAndControl,
+    TmcError,
+};
+
+type Driver = Tmc2209FullUartDiagnosticsAndControl<NoPin, NoPin, NoPin, MockTmc2209>;
+
+const CHOPCONF: u32 = 0x1000_0053;
+
+/// Driver at node 0 talking to a chip whose CHOPCONF is [`CHOPCONF`], with
+/// `faults` queued for its next replies.
+fn driver_with(faults: &[Fault]) -> Driver {
+    let mut chip = MockTmc2209::new(0);
+    chip.set_register(REG_CHOPCONF, CHOPCONF);
+    for &fault in faults {
+        chip.inject(fault).unwrap();
+    }
+    Tmc2209FullUartDiagnosticsAndControl::new(NoPin, NoPin, NoPin, chip, 0)
+}
+
+#[test]
+fn clean_read() {
+    let mut driver = driver_with(&[]);
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn corrupt_crc_is_reported() {
+    let mut driver = driver_with(&[Fault::CorruptCrc]);
+    assert_eq!(
+        driver.read_register(REG_CHOPCONF),
+        Err(TmcError::CrcError { reg: REG_CHOPCONF })
+    );
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn corrupt_crc_is_retried() {
+    let mut driver = driver_with(&[Fault::CorruptCrc, Fault::CorruptCrc]);
+    driver.set_retries(2);
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn retries_run_out() {
+    let mut driver = driver_with(&[Fault::CorruptCrc, Fault::CorruptCrc]);
+    driver.set_retries(1);
+    assert_eq!(
+        driver.read_register(REG_CHOPCONF),
+        Err(TmcError::CrcError { reg: REG_CHOPCONF })
+    );
+}
+
+#[test]
+fn dropped_byte_times_out() {
+    let mut driver = driver_with(&[Fault::DropByte(4)]);
+    assert_eq!(
+        driver.read_register(REG_CHOPCONF),
+        Err(TmcError::Timeout {
+            reg: REG_CHOPCONF,
+            bytes_received: 7,
+        })
+    );
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn missing_reply_times_out() {
+    let mut driver = driver_with(&[Fault::NoReply]);
+    assert_eq!(
+        driver.read_register(REG_CHOPCONF),
+        Err(TmcError::Timeout {
+            reg: REG_CHOPCONF,
+            bytes_received: 0,
+        })
+    );
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn short_delay_is_waited_out() {
+    let mut driver = driver_with(&[Fault::DelayReply(10)]);
+    driver.set_read_timeout(100);
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn long_delay_times_out() {
+    let mut driver = driver_with(&[Fault::DelayReply(1_000)]);
+    driver.set_read_timeout(100);
+    assert!(matches!(
+        driver.read_register(REG_CHOPCONF),
+        Err(TmcError::Timeout {
+            reg: REG_CHOPCONF,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn reply_from_wrong_address_is_rejected() {
+    let mut driver = driver_with(&[Fault::WrongAddress]);
+    assert_eq!(
+        driver.read_register(REG_CHOPCONF),
+        Err(TmcError::ReplyAddressMismatch {
+            reg: REG_CHOPCONF,
+            address: 0x00,
+        })
+    );
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn reply_for_wrong_register_is_rejected() {
+    let mut driver = driver_with(&[Fault::WrongRegister]);
+    assert_eq!(
+        driver.read_register(REG_CHOPCONF),
+        Err(TmcError::ReplyRegisterMismatch {
+            reg: REG_CHOPCONF,
+            observed: REG_CHOPCONF + 1,
+        })
+    );
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn every_fault_is_retried() {
+    let mut driver = driver_with(&[
+        Fault::CorruptCrc,
+        Fault::DropByte(2),
+        Fault::NoReply,
+        Fault::WrongAddress,
+        Fault::WrongRegister,
+    ]);
+    driver.set_retries(5);
+    assert_eq!(driver.read_register(REG_CHOPCONF), Ok(CHOPCONF));
+}
+
+#[test]
+fn bus_keeps_shadow_registers_per_address() {
+    let mut bus = Tmc2209Bus::new(MockTmc2209::new(0));
+    bus.driver(0)
+        .unwrap()
+        .write_register(REG_IHOLD_IRUN, 0x6_1F0A)
+        .unwrap();
+    assert_eq!(bus.driver(1).unwrap().shadow_register(REG_IHOLD_IRUN), None);
+    bus.driver(1)
+        .unwrap()
+        .write_register(REG_IHOLD_IRUN, 0x1_0505)
+        .unwrap();
+    assert_eq!(
+        bus.driver(0).unwrap().shadow_register(REG_IHOLD_IRUN),
+        Some(0x6_1F0A)
+    );
+    assert_eq!(
+        bus.driver(1).unwrap().shadow_register(REG_IHOLD_IRUN),
+        Some(0x1_0505)
+    );
+}
+
+#[test]
+fn unchecked_variant_reaches_unmapped_registers() {
+    let mut driver = driver_with(&[]);
+    assert_eq!(driver.read_register(0x7E), Err(TmcError::Unsupported));
+    driver.set_chip_variant(ChipVariant::Unchecked);
+    driver.write_register(0x7E, 0x1234).unwrap();
+    assert_eq!(driver.read_register(0x7E), Ok(0x1234));
+}
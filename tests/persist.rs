@@ -0,0 +1,142 @@
+//! State blob export and import against a `MockTmc2209`.
+
+use tmc2209_driver::{
+    calc_crc8, DriverState, MockTmc2209, NoPin, Odometer, RampConfig,
+    Tmc2209FullUartDiagnosticsAndControl, TmcError, STATE_BLOB_LEN, STATE_BLOB_VERSION,
+};
+
+type Driver = Tmc2209FullUartDiagnosticsAndControl<NoPin, NoPin, NoPin, MockTmc2209>;
+
+fn driver() -> Driver {
+    let mut driver =
+        Tmc2209FullUartDiagnosticsAndControl::new(NoPin, NoPin, NoPin, MockTmc2209::new(0), 0);
+    driver.set_current(16, 8, 4).unwrap();
+    driver
+}
+
+/// Recompute the CRC after editing a blob.
+fn reseal(blob: &mut [u8]) {
+    let last = blob.len() - 1;
+    blob[last] = calc_crc8(&blob[..last]);
+}
+
+#[test]
+fn state_round_trips() {
+    let mut source = driver();
+    source.set_position(-123_456);
+    source.set_odometer(Odometer::new(9_876_543_210, 42));
+    let blob = source.export_state();
+    assert_eq!(blob.len(), STATE_BLOB_LEN);
+    assert_eq!(blob[0], STATE_BLOB_VERSION);
+
+    let mut target = driver();
+    target.import_state(&blob).unwrap();
+    assert_eq!(target.position(), -123_456);
+    assert!(!target.is_homed());
+    assert_eq!(target.odometer().steps, 9_876_543_210);
+    assert_eq!(target.odometer().reversals, 42);
+    assert_eq!(target.export_state(), blob);
+}
+
+#[test]
+fn homed_flag_round_trips() {
+    let mut blob = driver().export_state();
+    blob[1] = 1;
+    reseal(&mut blob);
+
+    let mut target = driver();
+    target.import_state(&blob).unwrap();
+    assert!(target.is_homed());
+    assert_eq!(target.export_state(), blob);
+}
+
+#[test]
+fn version_1_blob_is_accepted() {
+    let v2 = driver().export_state();
+    let mut v1 = [0u8; 11];
+    v1[0] = 1;
+    v1[1] = 1;
+    v1[2..6].copy_from_slice(&777i32.to_le_bytes());
+    v1[6..10].copy_from_slice(&v2[6..10]);
+    reseal(&mut v1);
+
+    let mut target = driver();
+    target.set_odometer(Odometer::new(500, 3));
+    target.import_state(&v1).unwrap();
+    assert_eq!(target.position(), 777);
+    assert!(target.is_homed());
+    assert_eq!(target.odometer().steps, 500);
+    assert_eq!(target.odometer().reversals, 3);
+}
+
+#[test]
+fn truncated_blob_is_refused() {
+    let mut source = driver();
+    source.set_position(55);
+    let blob = source.export_state();
+
+    let mut target = driver();
+    for len in 0..STATE_BLOB_LEN {
+        assert_eq!(
+            target.import_state(&blob[..len]),
+            Err(TmcError::InvalidArgument),
+            "length {len}"
+        );
+    }
+    assert_eq!(target.position(), 0);
+}
+
+#[test]
+fn unknown_version_is_refused() {
+    let mut blob = driver().export_state();
+    blob[0] = STATE_BLOB_VERSION + 1;
+    reseal(&mut blob);
+    assert_eq!(driver().import_state(&blob), Err(TmcError::InvalidArgument));
+}
+
+#[test]
+fn corrupted_blob_is_refused() {
+    let mut source = driver();
+    source.set_position(55);
+    let blob = source.export_state();
+
+    let mut target = driver();
+    for i in 1..STATE_BLOB_LEN {
+        let mut corrupted = blob;
+        corrupted[i] ^= 0x01;
+        assert_eq!(
+            target.import_state(&corrupted),
+            Err(TmcError::InvalidArgument),
+            "byte {i}"
+        );
+    }
+    assert_eq!(target.position(), 0);
+}
+
+#[test]
+fn different_configuration_is_refused() {
+    let mut source = driver();
+    source.set_position(55);
+    let blob = source.export_state();
+
+    let mut target = driver();
+    target.set_current(20, 8, 4).unwrap();
+    assert_eq!(target.import_state(&blob), Err(TmcError::InvalidArgument));
+    assert_eq!(target.position(), 0);
+}
+
+#[test]
+fn import_is_refused_while_moving() {
+    let mut source = driver();
+    source.set_position(55);
+    let blob = source.export_state();
+
+    let mut target = driver();
+    target.start_move(100, &RampConfig::default()).unwrap();
+    assert_eq!(target.state(), DriverState::Moving);
+    assert_eq!(
+        target.import_state(&blob),
+        Err(TmcError::InvalidState(DriverState::Moving))
+    );
+    assert_eq!(target.position(), 0);
+}
@@ -0,0 +1,139 @@
+//! Pipelined multi-register reads against a `MockTmc2209`.
+
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+use tmc2209_driver::registers::*;
+use tmc2209_driver::{Fault, MockTmc2209, NoPin, Tmc2209FullUartDiagnosticsAndControl};
+
+/// Serial port in front of a mock that notes whether a read request was sent
+/// before anything had been read since the previous one.
+struct Recorder {
+    chip: MockTmc2209,
+    read_since_request: bool,
+    overlapped: Rc<Cell<bool>>,
+}
+
+impl ErrorType for Recorder {
+    type Error = Infallible;
+}
+
+impl Write for Recorder {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        // Read requests are the only 4-byte datagrams.
+        if buf.len() == 4 {
+            if !self.read_since_request {
+                self.overlapped.set(true);
+            }
+            self.read_since_request = false;
+        }
+        self.chip.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        self.chip.flush()
+    }
+}
+
+impl Read for Recorder {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        let n = self.chip.read(buf)?;
+        if n > 0 {
+            self.read_since_request = true;
+        }
+        Ok(n)
+    }
+}
+
+impl ReadReady for Recorder {
+    fn read_ready(&mut self) -> Result<bool, Infallible> {
+        self.chip.read_ready()
+    }
+}
+
+type Driver = Tmc2209FullUartDiagnosticsAndControl<NoPin, NoPin, NoPin, Recorder>;
+
+const REGS: [u8; 4] = [REG_GCONF, REG_IOIN, REG_CHOPCONF, REG_PWMCONF];
+const VALUES: [u32; 4] = [0x0000_01C0, 0x2100_0040, 0x1000_0053, 0xC10D_0024];
+
+/// Driver whose SENDDELAY is `senddelay`, talking to a chip holding
+/// [`VALUES`], and the flag telling whether requests overlapped replies.
+fn driver(senddelay: u32, echo: bool, faults: &[Fault]) -> (Driver, Rc<Cell<bool>>) {
+    let mut chip = MockTmc2209::new(0);
+    chip.set_echo(echo);
+    for (&reg, &value) in REGS.iter().zip(VALUES.iter()) {
+        chip.set_register(reg, value);
+    }
+    for &fault in faults {
+        chip.inject(fault).unwrap();
+    }
+    let overlapped = Rc::new(Cell::new(false));
+    let serial = Recorder {
+        chip,
+        read_since_request: true,
+        overlapped: overlapped.clone(),
+    };
+    let mut driver = Tmc2209FullUartDiagnosticsAndControl::new(NoPin, NoPin, NoPin, serial, 0);
+    driver.set_echo_handling(echo);
+    driver
+        .write_register(REG_SLAVECONF, senddelay << 8)
+        .unwrap();
+    (driver, overlapped)
+}
+
+#[test]
+fn long_senddelay_pipelines_requests() {
+    let (mut driver, overlapped) = driver(4, false, &[]);
+    let mut out = [0u32; 4];
+    driver.read_registers_all(&REGS, &mut out).unwrap();
+    assert_eq!(out, VALUES);
+    assert!(overlapped.get());
+}
+
+#[test]
+fn short_senddelay_reads_one_at_a_time() {
+    let (mut driver, overlapped) = driver(3, false, &[]);
+    let mut out = [0u32; 4];
+    driver.read_registers_all(&REGS, &mut out).unwrap();
+    assert_eq!(out, VALUES);
+    assert!(!overlapped.get());
+}
+
+#[test]
+fn echo_handling_reads_one_at_a_time() {
+    let (mut driver, overlapped) = driver(15, true, &[]);
+    let mut out = [0u32; 4];
+    driver.read_registers_all(&REGS, &mut out).unwrap();
+    assert_eq!(out, VALUES);
+    assert!(!overlapped.get());
+}
+
+#[test]
+fn single_register_is_not_pipelined() {
+    let (mut driver, overlapped) = driver(4, false, &[]);
+    let mut out = [0u32; 1];
+    driver.read_registers_all(&REGS[..1], &mut out).unwrap();
+    assert_eq!(out[0], VALUES[0]);
+    assert!(!overlapped.get());
+}
+
+#[test]
+fn failed_pipelined_reply_falls_back_to_single_reads() {
+    for fault in [Fault::CorruptCrc, Fault::NoReply, Fault::DropByte(3)] {
+        let (mut driver, _) = driver(4, false, &[Fault::NoReply, fault]);
+        driver.set_retries(0);
+        let mut out = [0u32; 4];
+        driver.read_registers_all(&REGS, &mut out).unwrap();
+        assert_eq!(out, VALUES, "{fault:?}");
+    }
+}
+
+#[test]
+fn snapshot_matches_single_reads() {
+    let (mut pipelined, overlapped) = driver(4, false, &[]);
+    let (mut single, _) = driver(4, true, &[]);
+    assert_eq!(pipelined.snapshot(), single.snapshot());
+    assert!(overlapped.get());
+}
@@ -0,0 +1,53 @@
+//! Protocol properties from `test_support`, run on the host.
+
+use tmc2209_driver::registers::*;
+use tmc2209_driver::test_support::*;
+use tmc2209_driver::{MockTmc2209, NoPin, Tmc2209FullUartDiagnosticsAndControl};
+
+const CASES: u32 = 2_000;
+
+#[test]
+fn wire_format_matches_reference() {
+    assert_eq!(verify_wire_format(), Ok(()));
+}
+
+#[test]
+fn datagrams_round_trip() {
+    assert_eq!(
+        run_property(0x1234, CASES, |rng| {
+            let datagram = Datagram::random(rng);
+            check_datagram_round_trip(datagram)
+                && check_crc_invariants(datagram.encode().as_bytes())
+        }),
+        Ok(())
+    );
+}
+
+#[test]
+fn replies_round_trip() {
+    assert_eq!(
+        run_property(0x5678, CASES, |rng| {
+            let reg = random_register(rng);
+            let value = random_value(rng, reg);
+            check_reply_round_trip(reg, value) && check_crc_invariants(&reply_frame(reg, value))
+        }),
+        Ok(())
+    );
+}
+
+#[test]
+fn driver_writes_reach_the_chip() {
+    let mut driver =
+        Tmc2209FullUartDiagnosticsAndControl::new(NoPin, NoPin, NoPin, MockTmc2209::new(0), 0);
+    assert_eq!(
+        run_property(0x9ABC, CASES, |rng| {
+            let reg = random_register(rng);
+            if reg == REG_GSTAT || reg == REG_IFCNT {
+                return true;
+            }
+            let value = random_value(rng, reg);
+            driver.write_register(reg, value).is_ok() && driver.read_register(reg) == Ok(value)
+        }),
+        Ok(())
+    );
+}
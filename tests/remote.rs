@@ -0,0 +1,178 @@
+//! Framing of the remote command set.
+
+use tmc2209_driver::remote::*;
+use tmc2209_driver::{DriverState, FaultKind, TmcError, TmcErrorCode};
+
+const COMMANDS: [Command; 9] = [
+    Command::MoveTo { position: 0 },
+    Command::MoveTo { position: i32::MIN },
+    Command::MoveBy { steps: -12_345 },
+    Command::MoveBy { steps: i32::MAX },
+    Command::Home,
+    Command::SetCurrent {
+        run_ma: 800,
+        hold_ma: 400,
+    },
+    Command::SetCurrent {
+        run_ma: u16::MAX,
+        hold_ma: 0,
+    },
+    Command::QueryStatus,
+    Command::MoveTo {
+        position: 0xA5A5_A5A5_u32 as i32,
+    },
+];
+
+const STATES: [DriverState; 8] = [
+    DriverState::PoweredDown,
+    DriverState::Configured,
+    DriverState::Enabled,
+    DriverState::Moving,
+    DriverState::Fault(FaultKind::Overtemperature),
+    DriverState::Fault(FaultKind::Short),
+    DriverState::Fault(FaultKind::ChargePumpUndervoltage),
+    DriverState::EStopped,
+];
+
+fn responses() -> impl Iterator<Item = Response> {
+    let fixed = [
+        Response::Done { position: -1 },
+        Response::Done { position: i32::MAX },
+        Response::Error {
+            code: TmcErrorCode::from(TmcError::Timeout {
+                reg: 0x6F,
+                bytes_received: 3,
+            }),
+        },
+        Response::Error {
+            code: TmcErrorCode::from(TmcError::InvalidArgument),
+        },
+    ];
+    let statuses = STATES.into_iter().enumerate().map(|(i, state)| {
+        Response::Status(Status {
+            position: i as i32 * -1_000,
+            state,
+            homed: i % 2 == 0,
+            drv_status: 0xC000_0000 | i as u32,
+        })
+    });
+    fixed.into_iter().chain(statuses)
+}
+
+/// Feed `bytes` to `reader`, returning the frames it completes.
+fn feed(reader: &mut FrameReader, bytes: &[u8]) -> Vec<Vec<u8>> {
+    bytes
+        .iter()
+        .filter_map(|&byte| reader.push(byte))
+        .map(|frame| frame.as_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn commands_round_trip() {
+    for command in COMMANDS {
+        let frame = command.encode();
+        let bytes = frame.as_bytes();
+        assert_eq!(bytes[0], FRAME_SYNC);
+        assert!(bytes.len() <= MAX_FRAME_LEN);
+        assert_eq!(Command::decode(bytes), Some(command));
+        assert_eq!(Response::decode(bytes), None);
+    }
+}
+
+#[test]
+fn responses_round_trip() {
+    for response in responses() {
+        let frame = response.encode();
+        let bytes = frame.as_bytes();
+        assert_eq!(bytes[0], FRAME_SYNC);
+        assert!(bytes.len() <= MAX_FRAME_LEN);
+        assert_eq!(Response::decode(bytes), Some(response));
+        assert_eq!(Command::decode(bytes), None);
+    }
+}
+
+#[test]
+fn every_corrupted_byte_is_detected() {
+    for command in COMMANDS {
+        let frame = command.encode();
+        for i in 0..frame.as_bytes().len() {
+            let mut bytes = frame.as_bytes().to_vec();
+            bytes[i] ^= 0x10;
+            assert_ne!(Command::decode(&bytes), Some(command), "byte {i}");
+        }
+    }
+}
+
+#[test]
+fn truncated_frames_do_not_decode() {
+    let frame = Command::MoveBy { steps: 7 }.encode();
+    let bytes = frame.as_bytes();
+    for len in 0..bytes.len() {
+        assert_eq!(Command::decode(&bytes[..len]), None);
+    }
+}
+
+#[test]
+fn reader_passes_frames_through() {
+    let mut reader = FrameReader::new();
+    for command in COMMANDS {
+        let frame = command.encode();
+        assert_eq!(feed(&mut reader, frame.as_bytes()), [frame.as_bytes()]);
+    }
+    for response in responses() {
+        let frame = response.encode();
+        assert_eq!(feed(&mut reader, frame.as_bytes()), [frame.as_bytes()]);
+    }
+    assert_eq!(reader.rejected(), 0);
+}
+
+#[test]
+fn reader_skips_junk_before_a_frame() {
+    let mut reader = FrameReader::new();
+    let frame = Command::Home.encode();
+    let mut stream = vec![0x00, 0xFF, 0x13, 0x37];
+    stream.extend_from_slice(frame.as_bytes());
+    assert_eq!(feed(&mut reader, &stream), [frame.as_bytes()]);
+    assert_eq!(reader.rejected(), 0);
+}
+
+#[test]
+fn reader_rejects_bad_lengths() {
+    let mut reader = FrameReader::new();
+    let frame = Command::QueryStatus.encode();
+    assert!(feed(&mut reader, &[FRAME_SYNC, 0]).is_empty());
+    assert!(feed(&mut reader, &[FRAME_SYNC, MAX_FRAME_LEN as u8]).is_empty());
+    assert_eq!(reader.rejected(), 2);
+    assert_eq!(feed(&mut reader, frame.as_bytes()), [frame.as_bytes()]);
+}
+
+#[test]
+fn reader_rejects_bad_crc_and_recovers() {
+    let mut reader = FrameReader::new();
+    let good = Command::MoveTo { position: 42 }.encode();
+    let mut bad = good.as_bytes().to_vec();
+    *bad.last_mut().unwrap() ^= 0xFF;
+    assert!(feed(&mut reader, &bad).is_empty());
+    assert_eq!(reader.rejected(), 1);
+    assert_eq!(feed(&mut reader, good.as_bytes()), [good.as_bytes()]);
+}
+
+#[test]
+fn reader_joins_frames_split_across_pushes() {
+    let mut reader = FrameReader::new();
+    let first = Command::SetCurrent {
+        run_ma: 1_200,
+        hold_ma: 600,
+    }
+    .encode();
+    let second = Command::MoveBy { steps: -3 }.encode();
+    let mut stream = first.as_bytes().to_vec();
+    stream.extend_from_slice(second.as_bytes());
+    let (head, tail) = stream.split_at(5);
+    assert!(feed(&mut reader, head).is_empty());
+    assert_eq!(
+        feed(&mut reader, tail),
+        [first.as_bytes(), second.as_bytes()]
+    );
+}
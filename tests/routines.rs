@@ -0,0 +1,95 @@
+//! Long-running Full UART routines against a `MockTmc2209`.
+
+use embedded_hal::delay::DelayNs;
+use tmc2209_driver::registers::*;
+use tmc2209_driver::{
+    AutotuneConfig, DriverState, MockTmc2209, NoPin, RampConfig,
+    Tmc2209FullUartDiagnosticsAndControl, TmcError,
+};
+
+type Driver = Tmc2209FullUartDiagnosticsAndControl<NoPin, NoPin, NoPin, MockTmc2209>;
+
+struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+fn driver() -> Driver {
+    Tmc2209FullUartDiagnosticsAndControl::new(NoPin, NoPin, NoPin, MockTmc2209::new(0), 0)
+}
+
+const AUTOTUNE: AutotuneConfig = AutotuneConfig {
+    standstill_ms: 130,
+    speed: 3_200,
+    steps: 100,
+    direction: true,
+};
+
+#[test]
+fn autotune_needs_a_run_current() {
+    let mut driver = driver();
+    assert_eq!(
+        driver.autotune_stealthchop(&AUTOTUNE, &mut NoDelay),
+        Err(TmcError::InvalidArgument)
+    );
+    assert_eq!(driver.read_register(REG_PWMCONF), Ok(0));
+}
+
+#[test]
+fn autotune_needs_stealthchop() {
+    let mut driver = driver();
+    driver.set_current(20, 8, 6).unwrap();
+    driver
+        .write_register(REG_GCONF, GCONF_EN_SPREADCYCLE)
+        .unwrap();
+    assert_eq!(
+        driver.autotune_stealthchop(&AUTOTUNE, &mut NoDelay),
+        Err(TmcError::InvalidArgument)
+    );
+}
+
+#[test]
+fn autotune_restores_hold_current_and_state() {
+    let mut driver = driver();
+    driver.set_current(20, 8, 6).unwrap();
+    let ihold_irun = driver.shadow_register(REG_IHOLD_IRUN);
+    let state = driver.state();
+    let position = driver.position();
+
+    driver
+        .autotune_stealthchop(&AUTOTUNE, &mut NoDelay)
+        .unwrap();
+
+    let pwmconf = driver.read_register(REG_PWMCONF).unwrap();
+    assert_ne!(pwmconf & PWMCONF_PWM_AUTOSCALE, 0);
+    assert_ne!(pwmconf & PWMCONF_PWM_AUTOGRAD, 0);
+    assert_eq!(driver.shadow_register(REG_IHOLD_IRUN), ihold_irun);
+    assert_eq!(driver.state(), state);
+    assert_ne!(driver.state(), DriverState::Moving);
+    assert_eq!(driver.position(), position + AUTOTUNE.steps as i32);
+}
+
+#[test]
+fn step_pulse_is_refused_while_estopped() {
+    let mut driver = driver();
+    driver.step_pulse().unwrap();
+    let position = driver.position();
+    let _ = driver.emergency_stop();
+    assert_eq!(
+        driver.step_pulse(),
+        Err(TmcError::InvalidState(DriverState::EStopped))
+    );
+    assert_eq!(driver.position(), position);
+}
+
+#[test]
+fn move_to_out_of_range_is_refused() {
+    let mut driver = driver();
+    driver.set_position(1);
+    assert_eq!(
+        driver.move_to(i32::MIN, &RampConfig::default(), &mut NoDelay),
+        Err(TmcError::InvalidArgument)
+    );
+    assert_eq!(driver.position(), 1);
+}
@@ -0,0 +1,104 @@
+//! Snapshot blob packing and unpacking.
+
+use tmc2209_driver::{
+    calc_crc8, Tmc2209Snapshot, TmcError, SNAPSHOT_BLOB_LEN, SNAPSHOT_BLOB_VERSION,
+    SNAPSHOT_REGISTERS,
+};
+
+/// A snapshot with every other register known, with distinct values.
+fn sample() -> Tmc2209Snapshot {
+    let mut snapshot = Tmc2209Snapshot::default();
+    for (i, &reg) in SNAPSHOT_REGISTERS.iter().enumerate() {
+        let value = (i % 2 == 0).then_some(0x0101_0101u32.wrapping_mul(i as u32 + 1) ^ 0x8000_0000);
+        snapshot.set(reg, value).unwrap();
+    }
+    snapshot
+}
+
+#[test]
+fn snapshot_round_trips() {
+    let snapshot = sample();
+    let blob = snapshot.to_bytes();
+    assert_eq!(blob.len(), SNAPSHOT_BLOB_LEN);
+    assert_eq!(blob[0], SNAPSHOT_BLOB_VERSION);
+    assert_eq!(Tmc2209Snapshot::from_bytes(&blob), Ok(snapshot));
+}
+
+#[test]
+fn empty_and_full_snapshots_round_trip() {
+    let empty = Tmc2209Snapshot::default();
+    assert_eq!(Tmc2209Snapshot::from_bytes(&empty.to_bytes()), Ok(empty));
+
+    let mut full = Tmc2209Snapshot::default();
+    for &reg in SNAPSHOT_REGISTERS.iter() {
+        full.set(reg, Some(u32::MAX)).unwrap();
+    }
+    assert_eq!(Tmc2209Snapshot::from_bytes(&full.to_bytes()), Ok(full));
+}
+
+#[test]
+fn known_zero_differs_from_unknown() {
+    let mut snapshot = Tmc2209Snapshot::default();
+    snapshot.set(SNAPSHOT_REGISTERS[0], Some(0)).unwrap();
+    let restored = Tmc2209Snapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+    assert_eq!(restored.get(SNAPSHOT_REGISTERS[0]), Some(0));
+    assert_eq!(restored.get(SNAPSHOT_REGISTERS[1]), None);
+}
+
+#[test]
+fn trailing_bytes_are_ignored() {
+    let snapshot = sample();
+    let mut longer = [0xFFu8; SNAPSHOT_BLOB_LEN + 4];
+    longer[..SNAPSHOT_BLOB_LEN].copy_from_slice(&snapshot.to_bytes());
+    assert_eq!(Tmc2209Snapshot::from_bytes(&longer), Ok(snapshot));
+}
+
+#[test]
+fn truncated_blob_is_refused() {
+    let blob = sample().to_bytes();
+    for len in 0..SNAPSHOT_BLOB_LEN {
+        assert_eq!(
+            Tmc2209Snapshot::from_bytes(&blob[..len]),
+            Err(TmcError::InvalidArgument),
+            "length {len}"
+        );
+    }
+}
+
+#[test]
+fn unknown_version_is_refused() {
+    let mut blob = sample().to_bytes();
+    blob[0] = SNAPSHOT_BLOB_VERSION + 1;
+    blob[SNAPSHOT_BLOB_LEN - 1] = calc_crc8(&blob[..SNAPSHOT_BLOB_LEN - 1]);
+    assert_eq!(
+        Tmc2209Snapshot::from_bytes(&blob),
+        Err(TmcError::InvalidArgument)
+    );
+}
+
+#[test]
+fn corrupted_blob_is_refused() {
+    let blob = sample().to_bytes();
+    for i in 0..SNAPSHOT_BLOB_LEN {
+        let mut corrupted = blob;
+        corrupted[i] ^= 0x01;
+        assert_eq!(
+            Tmc2209Snapshot::from_bytes(&corrupted),
+            Err(TmcError::InvalidArgument),
+            "byte {i}"
+        );
+    }
+}
+
+#[test]
+fn registers_outside_a_snapshot_are_refused() {
+    let mut snapshot = Tmc2209Snapshot::default();
+    let outside = (0..=0x7F)
+        .find(|reg| !SNAPSHOT_REGISTERS.contains(reg))
+        .unwrap();
+    assert_eq!(
+        snapshot.set(outside, Some(1)),
+        Err(TmcError::InvalidArgument)
+    );
+    assert_eq!(snapshot.get(outside), None);
+}